@@ -0,0 +1,102 @@
+//! Spatial index maintenance while dragging in a large scene, alone and
+//! with the computed state `dispatch` returns, and queries on each backend
+//! in a scene of very uneven element sizes.
+//!
+//!     cargo bench --bench spatial
+//!
+//! A plain timing loop, so it needs no benchmark framework.
+
+use std::time::{Duration, Instant};
+use kinetic_engine::KineticEngine;
+use kinetic_engine::core::color::Color;
+use kinetic_engine::core::geometry::{Rect, Shape};
+use kinetic_engine::core::paint::Paint;
+use kinetic_engine::core::spatial::{IndexOptions, SpatialBackend};
+use kinetic_engine::core::state::{reducer, Action, EngineState};
+
+const ELEMENTS: usize = 50_000;
+const GROUPED: usize = 2_500;
+const DRAG_STEPS: usize = 1_000;
+
+/// A 250 column grid of boxes, indexed in one go as when a document loads.
+/// The first ten rows are one group.
+fn scene() -> KineticEngine {
+    let mut state = EngineState::new();
+    let columns = 250;
+    for i in 0..ELEMENTS {
+        if i == GROUPED {
+            let children = (0..GROUPED).map(|i| format!("el{}", i)).collect();
+            reducer(&mut state, Action::GroupElements { group_id: "group".to_string(), children });
+        }
+        let (x, y) = ((i % columns) as f32 * 36.0 - 4500.0, (i / columns) as f32 * 36.0 - 4500.0);
+        reducer(&mut state, Action::AddElement {
+            id: format!("el{}", i),
+            name: "Box".to_string(),
+            shape: Shape::Rect(Rect::new(x, y, 24.0, 24.0)),
+            fill: Paint::Solid(Color::BLACK),
+        });
+    }
+    KineticEngine::from_state(state)
+}
+
+/// Tiny icons spread over large overlapping backgrounds.
+fn uneven_state() -> EngineState {
+    let mut state = EngineState::new();
+    let mut add = |id: String, rect: Rect| {
+        reducer(&mut state, Action::AddElement { id, name: "Box".to_string(), shape: Shape::Rect(rect), fill: Paint::Solid(Color::BLACK) });
+    };
+    for i in 0..200 {
+        let offset = (i % 20) as f32 * 400.0 - 4000.0;
+        add(format!("bg{}", i), Rect::new(offset, offset, 12000.0, 12000.0));
+    }
+    for i in 0..ELEMENTS {
+        let (x, y) = ((i % 250) as f32 * 40.0 - 5000.0, (i / 250) as f32 * 40.0 - 4000.0);
+        add(format!("icon{}", i), Rect::new(x, y, 2.0, 2.0));
+    }
+    state
+}
+
+fn time(label: &str, runs: usize, mut f: impl FnMut(usize)) {
+    let start = Instant::now();
+    for i in 0..runs {
+        f(i);
+    }
+    let per_run = start.elapsed() / runs as u32;
+    println!("{:<40} {:>12?} per run", label, per_run.max(Duration::from_nanos(1)));
+}
+
+fn main() {
+    let start = Instant::now();
+    let mut engine = scene();
+    println!("{:<40} {:>12?}", format!("build {} elements", ELEMENTS), start.elapsed());
+
+    let drag = |id: &str, i: usize| Action::MoveElement { id: id.to_string(), dx: if i.is_multiple_of(2) { 3.0 } else { -3.0 }, dy: 1.0 };
+    time("drag one element (incremental)", DRAG_STEPS, |i| engine.apply(drag("el31234", i)));
+    time("drag a group child (incremental)", DRAG_STEPS, |i| engine.apply(drag("el1234", i)));
+    time("drag one element (full rebuild)", 10, |i| {
+        engine.apply(drag("el31234", i));
+        engine.rebuild_index();
+    });
+    // What `dispatch` does short of converting the result for JavaScript.
+    time("drag one element (dispatch)", 100, |i| {
+        engine.apply(drag("el31234", i));
+        engine.state().get_computed_state();
+    });
+    time("drag a group child (dispatch)", 100, |i| {
+        engine.apply(drag("el1234", i));
+        engine.state().get_computed_state();
+    });
+    time("set time (no index change)", DRAG_STEPS, |i| engine.apply(Action::SetTime { time: i as f32 }));
+
+    let uneven = uneven_state();
+    for backend in [SpatialBackend::Quadtree, SpatialBackend::RTree] {
+        let options = IndexOptions { backend, ..IndexOptions::default() };
+        let start = Instant::now();
+        let engine = KineticEngine::from_state_with_index(uneven.clone(), &options);
+        println!("{:<40} {:>12?}", format!("build uneven scene ({:?})", backend), start.elapsed());
+        time(&format!("query uneven scene ({:?})", backend), DRAG_STEPS, |i| {
+            let (x, y) = ((i * 37 % 9000) as f32 - 4500.0, (i * 53 % 7000) as f32 - 3500.0);
+            engine.query(&Rect::new(x, y, 100.0, 100.0), false);
+        });
+    }
+}
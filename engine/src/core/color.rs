@@ -0,0 +1,436 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::core::geometry::{atan2_deg, sin_cos_deg, Scalar};
+
+/// An sRGB color with straight alpha, channels in [0, 1]. Serialized as a
+/// CSS hex string so it round-trips through the frontend unchanged.
+///
+/// Conversions to other spaces run entirely in fixed point, with roots and
+/// angles computed from integer arithmetic, so interpolated colors are
+/// bit-identical across platforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub r: Scalar,
+    pub g: Scalar,
+    pub b: Scalar,
+    pub a: Scalar,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgba8(0, 0, 0, 255);
+    pub const TRANSPARENT: Color = Color::rgba8(0, 0, 0, 0);
+
+    pub const fn rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        // Built from raw bits since fixed-point division is not const.
+        const fn channel(v: u8) -> Scalar {
+            Scalar::from_bits(((v as i64) << 16) / 255)
+        }
+        Self { r: channel(r), g: channel(g), b: channel(b), a: channel(a) }
+    }
+
+    pub fn to_rgba8(&self) -> [u8; 4] {
+        [self.r, self.g, self.b, self.a].map(|c| {
+            (c.clamp(Scalar::ZERO, Scalar::ONE) * Scalar::from_num(255)).round().to_num::<u8>()
+        })
+    }
+
+    /// Parses a CSS color: `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`,
+    /// `rgb()`/`rgba()` and `hsl()`/`hsla()` in comma or space syntax, and
+    /// the CSS named colors including `transparent`.
+    pub fn parse(css: &str) -> Option<Color> {
+        let css = css.trim().to_ascii_lowercase();
+        if let Some(hex) = css.strip_prefix('#') {
+            return parse_hex(hex);
+        }
+        if let Some(color) = named(&css) {
+            return Some(color);
+        }
+        let (name, args) = css.strip_suffix(')')?.split_once('(')?;
+        let args = split_args(args)?;
+        match name.trim() {
+            "rgb" | "rgba" => {
+                let [r, g, b] = [&args[0], &args[1], &args[2]].map(|a| parse_channel(a, Scalar::from_num(255)));
+                Some(Color { r: r?, g: g?, b: b?, a: parse_alpha(args.get(3))? })
+            }
+            "hsl" | "hsla" => {
+                let h = parse_hue(&args[0])?;
+                let [s, l] = [&args[1], &args[2]].map(|a| parse_channel(a, Scalar::from_num(100)));
+                Some(Color::from_srgb(hsl_to_srgb(h, s?, l?), parse_alpha(args.get(3))?))
+            }
+            _ => None,
+        }
+    }
+
+    /// CSS hex notation, with the alpha byte only when not opaque.
+    pub fn to_css(&self) -> String {
+        let [r, g, b, a] = self.to_rgba8();
+        if a == 255 {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        }
+    }
+
+    /// Builds a color from gamma-encoded sRGB, clipping out-of-gamut values.
+    pub fn from_srgb(rgb: [Scalar; 3], alpha: Scalar) -> Color {
+        let [r, g, b, a] = [rgb[0], rgb[1], rgb[2], alpha].map(|c| c.clamp(Scalar::ZERO, Scalar::ONE));
+        Color { r, g, b, a }
+    }
+
+    pub fn to_srgb(&self) -> [Scalar; 3] {
+        [self.r, self.g, self.b]
+    }
+
+    pub fn to_linear_srgb(&self) -> [Scalar; 3] {
+        self.to_srgb().map(srgb_to_linear)
+    }
+
+    pub fn from_linear_srgb(rgb: [Scalar; 3], alpha: Scalar) -> Color {
+        Color::from_srgb(rgb.map(linear_to_srgb), alpha)
+    }
+
+    /// OKLab as `[L, a, b]`.
+    pub fn to_oklab(&self) -> [Scalar; 3] {
+        let [r, g, b] = self.to_linear_srgb();
+        let [l, m, s] = transform(&LINEAR_SRGB_TO_LMS, [r, g, b]).map(|c| root(c, 3));
+        transform(&LMS_TO_OKLAB, [l, m, s])
+    }
+
+    pub fn from_oklab(lab: [Scalar; 3], alpha: Scalar) -> Color {
+        let lms = transform(&OKLAB_TO_LMS, lab).map(|c| c * c * c);
+        Color::from_linear_srgb(transform(&LMS_TO_LINEAR_SRGB, lms), alpha)
+    }
+
+    /// OKLCH as `[L, C, h]` with the hue in degrees in [0, 360).
+    pub fn to_oklch(&self) -> [Scalar; 3] {
+        let [l, a, b] = self.to_oklab();
+        let hue = atan2_deg(b, a).rem_euclid(Scalar::from_num(360));
+        [l, (a * a + b * b).sqrt(), hue]
+    }
+
+    pub fn from_oklch([l, c, h]: [Scalar; 3], alpha: Scalar) -> Color {
+        let (sin, cos) = sin_cos_deg(h);
+        Color::from_oklab([l, c * cos, c * sin], alpha)
+    }
+
+    pub fn to_space(&self, space: ColorSpace) -> [Scalar; 3] {
+        match space {
+            ColorSpace::Srgb => self.to_srgb(),
+            ColorSpace::LinearSrgb => self.to_linear_srgb(),
+            ColorSpace::Oklab => self.to_oklab(),
+            ColorSpace::Oklch => self.to_oklch(),
+        }
+    }
+
+    pub fn from_space(space: ColorSpace, components: [Scalar; 3], alpha: Scalar) -> Color {
+        match space {
+            ColorSpace::Srgb => Color::from_srgb(components, alpha),
+            ColorSpace::LinearSrgb => Color::from_linear_srgb(components, alpha),
+            ColorSpace::Oklab => Color::from_oklab(components, alpha),
+            ColorSpace::Oklch => Color::from_oklch(components, alpha),
+        }
+    }
+
+    /// Mixes towards `other` by `t` in `space`, with premultiplied alpha as in
+    /// CSS `color-mix()`. OKLCH hues take the shorter arc, and the hue of a
+    /// gray end is taken from the other end so it does not swing through red.
+    pub fn interpolate(&self, other: &Color, t: Scalar, space: ColorSpace) -> Color {
+        if t <= Scalar::ZERO {
+            return *self;
+        }
+        if t >= Scalar::ONE {
+            return *other;
+        }
+        let (mut from, mut to) = (self.to_space(space), other.to_space(space));
+        let (a0, a1) = (self.a, other.a);
+        // OKLCH hue is an angle and is neither premultiplied nor lerped linearly.
+        let rectangular = if space == ColorSpace::Oklch { 2 } else { 3 };
+        if space == ColorSpace::Oklch {
+            const ACHROMATIC: Scalar = Scalar::lit("0.0001");
+            if from[1] < ACHROMATIC {
+                from[2] = to[2];
+            } else if to[1] < ACHROMATIC {
+                to[2] = from[2];
+            }
+            let half = Scalar::from_num(180);
+            let delta = (to[2] - from[2] + half).rem_euclid(Scalar::from_num(360)) - half;
+            to[2] = from[2] + delta;
+        }
+        for i in 0..rectangular {
+            from[i] *= a0;
+            to[i] *= a1;
+        }
+        let alpha = a0 + (a1 - a0) * t;
+        let mut mixed = [Scalar::ZERO; 3];
+        for i in 0..3 {
+            mixed[i] = from[i] + (to[i] - from[i]) * t;
+            if i < rectangular && alpha > Scalar::ZERO {
+                mixed[i] /= alpha;
+            }
+        }
+        Color::from_space(space, mixed, alpha)
+    }
+}
+
+/// Space in which colors are interpolated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColorSpace {
+    Srgb,
+    LinearSrgb,
+    #[default]
+    Oklab,
+    Oklch,
+}
+
+type Matrix = [[Scalar; 3]; 3];
+
+const LINEAR_SRGB_TO_LMS: Matrix = [
+    [Scalar::lit("0.4122214708"), Scalar::lit("0.5363325363"), Scalar::lit("0.0514459929")],
+    [Scalar::lit("0.2119034982"), Scalar::lit("0.6806995451"), Scalar::lit("0.1073969566")],
+    [Scalar::lit("0.0883024619"), Scalar::lit("0.2817188376"), Scalar::lit("0.6299787005")],
+];
+
+const LMS_TO_OKLAB: Matrix = [
+    [Scalar::lit("0.2104542553"), Scalar::lit("0.7936177850"), Scalar::lit("-0.0040720468")],
+    [Scalar::lit("1.9779984951"), Scalar::lit("-2.4285922050"), Scalar::lit("0.4505937099")],
+    [Scalar::lit("0.0259040371"), Scalar::lit("0.7827717662"), Scalar::lit("-0.8086757660")],
+];
+
+const OKLAB_TO_LMS: Matrix = [
+    [Scalar::ONE, Scalar::lit("0.3963377774"), Scalar::lit("0.2158037573")],
+    [Scalar::ONE, Scalar::lit("-0.1055613458"), Scalar::lit("-0.0638541728")],
+    [Scalar::ONE, Scalar::lit("-0.0894841775"), Scalar::lit("-1.2914855480")],
+];
+
+const LMS_TO_LINEAR_SRGB: Matrix = [
+    [Scalar::lit("4.0767416621"), Scalar::lit("-3.3077115913"), Scalar::lit("0.2309699292")],
+    [Scalar::lit("-1.2684380046"), Scalar::lit("2.6097574011"), Scalar::lit("-0.3413193965")],
+    [Scalar::lit("-0.0041960863"), Scalar::lit("-0.7034186147"), Scalar::lit("1.7076147010")],
+];
+
+fn transform(m: &Matrix, v: [Scalar; 3]) -> [Scalar; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+/// `n`-th root of `x` for `n` up to 5, exact to the last fixed-point bit.
+/// Non-positive inputs give zero.
+fn root(x: Scalar, n: u32) -> Scalar {
+    if x <= Scalar::ZERO {
+        return Scalar::ZERO;
+    }
+    // With x = bits / 2^16, x^(1/n) = (bits * 2^(16 (n - 1)))^(1/n) / 2^16,
+    // an integer root found bit by bit. The shifted value fits for n <= 5.
+    let v = (x.to_bits() as u128) << (Scalar::FRAC_NBITS * (n - 1));
+    let mut r: u128 = 0;
+    for bit in (0..=128 / n).rev() {
+        let candidate = r | (1 << bit);
+        if candidate.checked_pow(n).is_some_and(|p| p <= v) {
+            r = candidate;
+        }
+    }
+    Scalar::from_bits(r as i64)
+}
+
+fn srgb_to_linear(c: Scalar) -> Scalar {
+    if c <= Scalar::lit("0.04045") {
+        c / Scalar::lit("12.92")
+    } else {
+        // x^2.4 as x^2 * (x^(1/5))^2.
+        let x = (c + Scalar::lit("0.055")) / Scalar::lit("1.055");
+        let fifth = root(x, 5);
+        x * x * fifth * fifth
+    }
+}
+
+fn linear_to_srgb(c: Scalar) -> Scalar {
+    if c <= Scalar::lit("0.0031308") {
+        c * Scalar::lit("12.92")
+    } else {
+        // c^(1/2.4) as c^(1/4) * c^(1/6).
+        Scalar::lit("1.055") * root(c, 4) * root(root(c, 3), 2) - Scalar::lit("0.055")
+    }
+}
+
+/// Hue in degrees, from a bare number or one with a CSS angle unit.
+fn parse_hue(s: &str) -> Option<Scalar> {
+    let units = [("deg", Scalar::ONE), ("grad", Scalar::lit("0.9")), ("rad", Scalar::from_num(180) / Scalar::PI), ("turn", Scalar::from_num(360))];
+    let (number, scale) = units
+        .iter()
+        .find_map(|&(unit, scale)| s.strip_suffix(unit).map(|n| (n, scale)))
+        .unwrap_or((s, Scalar::ONE));
+    Some(parse_number(number)? * scale)
+}
+
+fn hsl_to_srgb(h: Scalar, s: Scalar, l: Scalar) -> [Scalar; 3] {
+    let h = h.rem_euclid(Scalar::from_num(360)) / 30;
+    let a = s * l.min(Scalar::ONE - l);
+    let twelve = Scalar::from_num(12);
+    [0, 8, 4].map(|n| {
+        let k = (Scalar::from_num(n) + h) % twelve;
+        l - a * (k - Scalar::from_num(3)).min(Scalar::from_num(9) - k).clamp(-Scalar::ONE, Scalar::ONE)
+    })
+}
+
+fn named(name: &str) -> Option<Color> {
+    let i = NAMED_COLORS.binary_search_by_key(&name, |&(n, _)| n).ok()?;
+    let [_, r, g, b] = NAMED_COLORS[i].1.to_be_bytes();
+    let a = if name == "transparent" { 0 } else { 255 };
+    Some(Color::rgba8(r, g, b, a))
+}
+
+/// CSS named colors, sorted by name for binary search.
+const NAMED_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff), ("antiquewhite", 0xfaebd7), ("aqua", 0x00ffff), ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff), ("beige", 0xf5f5dc), ("bisque", 0xffe4c4), ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd), ("blue", 0x0000ff), ("blueviolet", 0x8a2be2), ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887), ("cadetblue", 0x5f9ea0), ("chartreuse", 0x7fff00), ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50), ("cornflowerblue", 0x6495ed), ("cornsilk", 0xfff8dc), ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff), ("darkblue", 0x00008b), ("darkcyan", 0x008b8b), ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9), ("darkgreen", 0x006400), ("darkgrey", 0xa9a9a9), ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b), ("darkolivegreen", 0x556b2f), ("darkorange", 0xff8c00), ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000), ("darksalmon", 0xe9967a), ("darkseagreen", 0x8fbc8f), ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f), ("darkslategrey", 0x2f4f4f), ("darkturquoise", 0x00ced1), ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493), ("deepskyblue", 0x00bfff), ("dimgray", 0x696969), ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff), ("firebrick", 0xb22222), ("floralwhite", 0xfffaf0), ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff), ("gainsboro", 0xdcdcdc), ("ghostwhite", 0xf8f8ff), ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520), ("gray", 0x808080), ("green", 0x008000), ("greenyellow", 0xadff2f),
+    ("grey", 0x808080), ("honeydew", 0xf0fff0), ("hotpink", 0xff69b4), ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082), ("ivory", 0xfffff0), ("khaki", 0xf0e68c), ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5), ("lawngreen", 0x7cfc00), ("lemonchiffon", 0xfffacd), ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080), ("lightcyan", 0xe0ffff), ("lightgoldenrodyellow", 0xfafad2), ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90), ("lightgrey", 0xd3d3d3), ("lightpink", 0xffb6c1), ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa), ("lightskyblue", 0x87cefa), ("lightslategray", 0x778899), ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de), ("lightyellow", 0xffffe0), ("lime", 0x00ff00), ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6), ("magenta", 0xff00ff), ("maroon", 0x800000), ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd), ("mediumorchid", 0xba55d3), ("mediumpurple", 0x9370db), ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee), ("mediumspringgreen", 0x00fa9a), ("mediumturquoise", 0x48d1cc), ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970), ("mintcream", 0xf5fffa), ("mistyrose", 0xffe4e1), ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead), ("navy", 0x000080), ("oldlace", 0xfdf5e6), ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23), ("orange", 0xffa500), ("orangered", 0xff4500), ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa), ("palegreen", 0x98fb98), ("paleturquoise", 0xafeeee), ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5), ("peachpuff", 0xffdab9), ("peru", 0xcd853f), ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd), ("powderblue", 0xb0e0e6), ("purple", 0x800080), ("rebeccapurple", 0x663399),
+    ("red", 0xff0000), ("rosybrown", 0xbc8f8f), ("royalblue", 0x4169e1), ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072), ("sandybrown", 0xf4a460), ("seagreen", 0x2e8b57), ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d), ("silver", 0xc0c0c0), ("skyblue", 0x87ceeb), ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090), ("slategrey", 0x708090), ("snow", 0xfffafa), ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4), ("tan", 0xd2b48c), ("teal", 0x008080), ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347), ("transparent", 0x000000), ("turquoise", 0x40e0d0), ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3), ("white", 0xffffff), ("whitesmoke", 0xf5f5f5), ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|d| d * 17);
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    let [r, g, b, a] = match hex.len() {
+        3 => [digit(0)?, digit(1)?, digit(2)?, 255],
+        4 => [digit(0)?, digit(1)?, digit(2)?, digit(3)?],
+        6 => [byte(0)?, byte(2)?, byte(4)?, 255],
+        8 => [byte(0)?, byte(2)?, byte(4)?, byte(6)?],
+        _ => return None,
+    };
+    Some(Color::rgba8(r, g, b, a))
+}
+
+/// Splits function arguments in either `a, b, c[, d]` or `a b c[ / d]` form.
+fn split_args(args: &str) -> Option<Vec<String>> {
+    let parts: Vec<String> = if args.contains(',') {
+        args.split(',').map(|s| s.trim().to_string()).collect()
+    } else {
+        let (color, alpha) = match args.split_once('/') {
+            Some((c, a)) => (c, Some(a.trim())),
+            None => (args, None),
+        };
+        let mut parts: Vec<String> = color.split_whitespace().map(str::to_string).collect();
+        parts.extend(alpha.map(str::to_string));
+        parts
+    };
+    (3..=4).contains(&parts.len()).then_some(parts)
+}
+
+pub(crate) fn parse_number(s: &str) -> Option<Scalar> {
+    let v: f64 = s.parse().ok()?;
+    v.is_finite().then(|| Scalar::from_num(v))
+}
+
+/// A channel given either as a percentage or as a number out of `max`.
+fn parse_channel(s: &str, max: Scalar) -> Option<Scalar> {
+    let v = match s.strip_suffix('%') {
+        Some(p) => parse_number(p)? / Scalar::from_num(100),
+        None => parse_number(s)? / max,
+    };
+    Some(v.clamp(Scalar::ZERO, Scalar::ONE))
+}
+
+fn parse_alpha(s: Option<&String>) -> Option<Scalar> {
+    s.map_or(Some(Scalar::ONE), |s| parse_channel(s, Scalar::ONE))
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_css())
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let css = String::deserialize(deserializer)?;
+        Color::parse(&css).ok_or_else(|| serde::de::Error::custom(format!("invalid color: {}", css)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format() {
+        assert_eq!(Color::parse("#f00"), Some(Color::rgba8(255, 0, 0, 255)));
+        assert_eq!(Color::parse("#FF000080").unwrap().to_css(), "#ff000080");
+        assert_eq!(Color::parse("rgb(0, 128, 255)").unwrap().to_css(), "#0080ff");
+        assert_eq!(Color::parse("rgba(0 128 255 / 50%)").unwrap().to_css(), "#0080ff80");
+        assert_eq!(Color::parse("rgb(100%, 0%, 0%, 0.25)").unwrap().to_rgba8(), [255, 0, 0, 64]);
+        assert_eq!(Color::parse("transparent"), Some(Color::TRANSPARENT));
+        assert_eq!(Color::parse("#12345"), None);
+        assert_eq!(Color::parse("rgb(1, 2)"), None);
+        assert_eq!(Color::BLACK.a, Scalar::ONE);
+        assert_eq!(Color::parse("RebeccaPurple").unwrap().to_css(), "#663399");
+        assert_eq!(Color::parse("hsl(120, 100%, 25%)").unwrap().to_css(), "#008000");
+        assert_eq!(Color::parse("hsla(0.5turn 100% 50% / 0.5)").unwrap().to_css(), "#00ffff80");
+    }
+
+    #[test]
+    fn test_color_spaces_and_interpolation() {
+        let red = Color::parse("red").unwrap();
+        let blue = Color::parse("blue").unwrap();
+        for space in [ColorSpace::Srgb, ColorSpace::LinearSrgb, ColorSpace::Oklab, ColorSpace::Oklch] {
+            let round_trip = Color::from_space(space, red.to_space(space), Scalar::ONE);
+            assert_eq!(round_trip.to_rgba8(), red.to_rgba8());
+        }
+        let half = Scalar::lit("0.5");
+        let [l, _, _] = Color::parse("white").unwrap().to_oklab();
+        assert!((l - Scalar::ONE).abs() < Scalar::lit("0.001"));
+
+        assert_eq!(red.interpolate(&blue, half, ColorSpace::Srgb).to_css(), "#800080");
+        assert_eq!(red.interpolate(&blue, half, ColorSpace::LinearSrgb).to_css(), "#bc00bc");
+        // Perceptual midpoints differ from the naive sRGB one; OKLCH stays saturated.
+        assert_eq!(red.interpolate(&blue, half, ColorSpace::Oklab).to_css(), "#8c53a2");
+        assert_eq!(red.interpolate(&blue, half, ColorSpace::Oklch).to_css(), "#ba00c2");
+        let dark = Color::parse("#0a0a0a").unwrap();
+        let white = Color::parse("white").unwrap();
+        assert_eq!(dark.interpolate(&white, half, ColorSpace::Oklab).to_css(), "#787878");
+        // A gray end takes the hue of the other, so the mix does not pass through red.
+        let navy = Color::parse("#123456").unwrap();
+        assert_eq!(white.interpolate(&navy, half, ColorSpace::Oklch).to_css(), "#8294a8");
+        let green = Color::parse("#00ff0080").unwrap();
+        assert_eq!(green.interpolate(&red, half, ColorSpace::Oklab).to_css(), "#e58701bf");
+        assert_eq!(green.interpolate(&red, half, ColorSpace::Oklch).to_css(), "#e98900bf");
+
+        // Premultiplied: fading to transparent keeps the hue instead of darkening.
+        let faded = red.interpolate(&Color::TRANSPARENT, half, ColorSpace::Srgb);
+        assert_eq!(faded.to_css(), "#ff000080");
+    }
+}
@@ -0,0 +1,106 @@
+use serde::{Serialize, Deserialize};
+use crate::core::color::Color;
+use crate::core::geometry::{Point, Rect, Scalar};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Shadow {
+    pub offset_x: Scalar,
+    pub offset_y: Scalar,
+    /// Blur radius; the shadow fades out over this distance.
+    pub blur: Scalar,
+    /// Grows (or with a negative value shrinks) the shadow before blurring.
+    pub spread: Scalar,
+    pub color: Color,
+}
+
+/// A layer effect, applied in list order when the element is rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Effect {
+    DropShadow(Shadow),
+    InnerShadow(Shadow),
+    LayerBlur { radius: Scalar },
+    BackgroundBlur { radius: Scalar },
+}
+
+impl Effect {
+    /// Grows `bounds` to cover what the effect paints outside the element.
+    /// Inner shadows and background blurs stay within the element's shape.
+    pub fn expand_bounds(&self, bounds: &Rect) -> Rect {
+        match self {
+            Effect::DropShadow(s) => {
+                let reach = (s.spread + s.blur.max(Scalar::ZERO)).max(Scalar::ZERO);
+                let mut shadow = bounds.inflate(reach);
+                shadow.origin = Point { x: shadow.origin.x + s.offset_x, y: shadow.origin.y + s.offset_y };
+                bounds.union(&shadow)
+            }
+            Effect::LayerBlur { radius } => bounds.inflate((*radius).max(Scalar::ZERO)),
+            Effect::InnerShadow(_) | Effect::BackgroundBlur { .. } => *bounds,
+        }
+    }
+
+    /// Sets a keyframeable numeric parameter; returns false if the effect has none by that name.
+    pub fn set_property(&mut self, prop: &str, value: f32) -> bool {
+        let value = Scalar::from_num(value);
+        match (self, prop) {
+            (Effect::DropShadow(s) | Effect::InnerShadow(s), _) => {
+                let field = match prop {
+                    "offset_x" => &mut s.offset_x,
+                    "offset_y" => &mut s.offset_y,
+                    "blur" => &mut s.blur,
+                    "spread" => &mut s.spread,
+                    _ => return false,
+                };
+                *field = value;
+                true
+            }
+            (Effect::LayerBlur { radius } | Effect::BackgroundBlur { radius }, "radius") => {
+                *radius = value.max(Scalar::ZERO);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn set_color(&mut self, color: Color) -> bool {
+        match self {
+            Effect::DropShadow(s) | Effect::InnerShadow(s) => {
+                s.color = color;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Splits an effect track name such as `effects.0.blur` into index and parameter.
+pub fn parse_track(prop: &str) -> Option<(usize, &str)> {
+    let (index, param) = prop.strip_prefix("effects.")?.split_once('.')?;
+    Some((index.parse().ok()?, param))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effect_bounds() {
+        let bounds = Rect::new(0.0, 0.0, 100.0, 50.0);
+        let mut shadow = Effect::DropShadow(Shadow {
+            offset_x: Scalar::from_num(10),
+            offset_y: Scalar::from_num(20),
+            blur: Scalar::from_num(4),
+            spread: Scalar::from_num(1),
+            color: Color::BLACK,
+        });
+        assert_eq!(shadow.expand_bounds(&bounds), Rect::new(0.0, 0.0, 115.0, 75.0));
+        assert!(shadow.set_property("offset_x", -10.0));
+        assert!(!shadow.set_property("radius", 1.0));
+        assert_eq!(shadow.expand_bounds(&bounds), Rect::new(-15.0, 0.0, 115.0, 75.0));
+
+        assert_eq!(Effect::LayerBlur { radius: Scalar::from_num(8) }.expand_bounds(&bounds), Rect::new(-8.0, -8.0, 116.0, 66.0));
+        assert_eq!(Effect::BackgroundBlur { radius: Scalar::from_num(8) }.expand_bounds(&bounds), bounds);
+        assert_eq!(parse_track("effects.2.blur"), Some((2, "blur")));
+        assert_eq!(parse_track("effects.x.blur"), None);
+    }
+}
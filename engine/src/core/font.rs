@@ -0,0 +1,256 @@
+use std::fmt;
+use std::sync::Arc;
+use ttf_parser::{name_id, Face, GlyphId, OutlineBuilder};
+use crate::core::geometry::{Point, Rect, Scalar, Vector};
+use crate::core::path::{PathCommand, PathShape};
+use crate::core::woff2;
+
+/// Vertical metrics of a font at a given size, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineMetrics {
+    pub ascent: Scalar,
+    pub descent: Scalar,
+    pub line_gap: Scalar,
+}
+
+/// One glyph produced by shaping. `cluster` is the byte offset of the first
+/// character it came from; advances and offsets are in pixels, y down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShapedGlyph {
+    pub glyph: GlyphId,
+    pub cluster: usize,
+    pub advance: Scalar,
+    pub offset: Vector,
+}
+
+/// A parsed font face. The raw bytes are kept so tables can be read again
+/// on demand; parsing a face only reads its table directory.
+#[derive(Clone)]
+pub struct Font {
+    data: Arc<Vec<u8>>,
+    pub family: String,
+    pub weight: u16,
+    pub italic: bool,
+    units_per_em: u16,
+}
+
+impl fmt::Debug for Font {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Font")
+            .field("family", &self.family)
+            .field("weight", &self.weight)
+            .field("italic", &self.italic)
+            .finish()
+    }
+}
+
+impl Font {
+    /// Parses TrueType or OpenType data; WOFF2 is decoded to sfnt first.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Font, String> {
+        let data = if woff2::is_woff2(&data) { woff2::decode(&data)? } else { data };
+        let face = Face::parse(&data, 0).map_err(|e| format!("invalid font: {}", e))?;
+        let name = |id: u16| {
+            face.names()
+                .into_iter()
+                .filter(|n| n.name_id == id && n.is_unicode())
+                .find_map(|n| n.to_string())
+        };
+        let family = name(name_id::TYPOGRAPHIC_FAMILY)
+            .or_else(|| name(name_id::FAMILY))
+            .ok_or_else(|| "font has no family name".to_string())?;
+        let (weight, italic, units_per_em) = (face.weight().to_number(), face.is_italic(), face.units_per_em());
+        Ok(Font { data: Arc::new(data), family, weight, italic, units_per_em })
+    }
+
+    pub fn face(&self) -> Face<'_> {
+        // Validated in `from_bytes`, and the bytes never change afterwards.
+        Face::parse(&self.data, 0).expect("font data was validated on load")
+    }
+
+    /// Converts font units to pixels at `size`.
+    pub fn scale(&self, units: i32, size: Scalar) -> Scalar {
+        Scalar::from_num(units) * size / Scalar::from_num(self.units_per_em)
+    }
+
+    pub fn line_metrics(&self, size: Scalar) -> LineMetrics {
+        let face = self.face();
+        LineMetrics {
+            ascent: self.scale(face.ascender() as i32, size),
+            descent: self.scale(-(face.descender() as i32), size),
+            line_gap: self.scale(face.line_gap() as i32, size),
+        }
+    }
+
+    pub fn glyph_index(&self, ch: char) -> Option<GlyphId> {
+        self.face().glyph_index(ch)
+    }
+
+    pub fn advance(&self, glyph: GlyphId, size: Scalar) -> Scalar {
+        let units = self.face().glyph_hor_advance(glyph).unwrap_or(0);
+        self.scale(units as i32, size)
+    }
+
+    /// Ink box of a glyph relative to its origin on the baseline, y down.
+    pub fn ink_bounds(&self, glyph: GlyphId, size: Scalar) -> Option<Rect> {
+        let b = self.face().glyph_bounding_box(glyph)?;
+        let origin = Point { x: self.scale(b.x_min as i32, size), y: self.scale(-(b.y_max as i32), size) };
+        Some(Rect {
+            origin,
+            width: self.scale(b.width() as i32, size),
+            height: self.scale(b.height() as i32, size),
+        })
+    }
+
+    /// Shapes a left-to-right run, applying kerning and, unless disabled,
+    /// standard and contextual ligatures.
+    pub fn shape(&self, text: &str, size: Scalar, ligatures: bool) -> Vec<ShapedGlyph> {
+        let face = rustybuzz::Face::from_face(self.face());
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.set_direction(rustybuzz::Direction::LeftToRight);
+        buffer.guess_segment_properties();
+        let features: Vec<rustybuzz::Feature> = if ligatures {
+            Vec::new()
+        } else {
+            [b"liga", b"clig"].iter().map(|tag| rustybuzz::Feature::new(ttf_parser::Tag::from_bytes(tag), 0, ..)).collect()
+        };
+        let shaped = rustybuzz::shape(&face, &features, buffer);
+        shaped
+            .glyph_infos()
+            .iter()
+            .zip(shaped.glyph_positions())
+            .map(|(info, pos)| ShapedGlyph {
+                glyph: GlyphId(info.glyph_id as u16),
+                cluster: info.cluster as usize,
+                advance: self.scale(pos.x_advance, size),
+                offset: Vector { x: self.scale(pos.x_offset, size), y: self.scale(-pos.y_offset, size) },
+            })
+            .collect()
+    }
+
+    /// Outline of a glyph as a path relative to its baseline origin, y down.
+    /// Quadratic TrueType curves are raised to cubics.
+    pub fn glyph_outline(&self, glyph: GlyphId, size: Scalar) -> Option<PathShape> {
+        let mut builder = OutlinePath { size, units_per_em: Scalar::from_num(self.units_per_em), path: PathShape::new(), current: Point::new(0.0, 0.0) };
+        self.face().outline_glyph(glyph, &mut builder)?;
+        Some(builder.path)
+    }
+}
+
+struct OutlinePath {
+    size: Scalar,
+    units_per_em: Scalar,
+    path: PathShape,
+    current: Point,
+}
+
+impl OutlinePath {
+    fn point(&self, x: f32, y: f32) -> Point {
+        // Scale before dividing, as `Font::scale` does, so whole units stay exact.
+        let scale = |v: f32| Scalar::from_num(v) * self.size / self.units_per_em;
+        Point { x: scale(x), y: -scale(y) }
+    }
+}
+
+impl OutlineBuilder for OutlinePath {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.current = self.point(x, y);
+        self.path.commands.push(PathCommand::MoveTo(self.current));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.current = self.point(x, y);
+        self.path.commands.push(PathCommand::LineTo(self.current));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (q, end) = (self.point(x1, y1), self.point(x, y));
+        let two_thirds = |from: Point| Point { x: from.x + (q.x - from.x) * 2 / 3, y: from.y + (q.y - from.y) * 2 / 3 };
+        self.path.commands.push(PathCommand::CurveTo(two_thirds(self.current), two_thirds(end), end));
+        self.current = end;
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.current = self.point(x, y);
+        self.path.commands.push(PathCommand::CurveTo(self.point(x1, y1), self.point(x2, y2), self.current));
+    }
+
+    fn close(&mut self) {
+        self.path.commands.push(PathCommand::Close);
+    }
+}
+
+/// Fonts available to text layout. Not part of the document: the host loads
+/// fonts into the engine and they are shared between state snapshots.
+#[derive(Debug, Clone, Default)]
+pub struct FontLibrary {
+    fonts: Vec<Font>,
+}
+
+impl FontLibrary {
+    /// Adds a font, replacing any loaded face with the same family, weight
+    /// and style. Returns the family name it was registered under.
+    pub fn load(&mut self, data: Vec<u8>) -> Result<String, String> {
+        let font = Font::from_bytes(data)?;
+        let family = font.family.clone();
+        self.fonts.retain(|f| !(f.family == font.family && f.weight == font.weight && f.italic == font.italic));
+        self.fonts.push(font);
+        Ok(family)
+    }
+
+    pub fn families(&self) -> Vec<String> {
+        let mut families: Vec<String> = self.fonts.iter().map(|f| f.family.clone()).collect();
+        families.sort();
+        families.dedup();
+        families
+    }
+
+    /// Picks the upright face of `family` closest to `weight`, following the
+    /// CSS font matching order: for weights up to 500 lighter faces are
+    /// preferred first, above 500 heavier ones.
+    pub fn resolve(&self, family: &str, weight: u16) -> Option<&Font> {
+        let candidates = self.fonts.iter().filter(|f| f.family.eq_ignore_ascii_case(family));
+        candidates.min_by_key(|f| {
+            let w = f.weight as i32;
+            let target = weight as i32;
+            let distance = (w - target).abs();
+            let wrong_side = match target {
+                400..=500 if (target..=500).contains(&w) => 0,
+                400..=500 => if w < target { 1 } else { 2 },
+                t if t < 400 => (w > t) as i32,
+                t => (w < t) as i32,
+            };
+            (f.italic, wrong_side, distance)
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn test_fonts() -> FontLibrary {
+        let mut fonts = FontLibrary::default();
+        fonts.load(include_bytes!("../../tests/fixtures/kinetic-test-regular.ttf").to_vec()).unwrap();
+        fonts.load(include_bytes!("../../tests/fixtures/kinetic-test-bold.ttf").to_vec()).unwrap();
+        fonts
+    }
+
+    #[test]
+    fn test_load_and_resolve() {
+        let fonts = test_fonts();
+        assert_eq!(fonts.families(), vec!["Kinetic Test".to_string()]);
+        assert_eq!(fonts.resolve("kinetic test", 300).unwrap().weight, 400);
+        assert_eq!(fonts.resolve("Kinetic Test", 600).unwrap().weight, 700);
+        assert!(fonts.resolve("Missing", 400).is_none());
+        assert!(FontLibrary::default().load(vec![0, 1, 2]).is_err());
+
+        let font = fonts.resolve("Kinetic Test", 400).unwrap();
+        let size = Scalar::from_num(10);
+        let metrics = font.line_metrics(size);
+        assert_eq!((metrics.ascent, metrics.descent), (Scalar::from_num(8), Scalar::from_num(2)));
+        let a = font.glyph_index('A').unwrap();
+        assert_eq!(font.advance(a, size), Scalar::from_num(6));
+        assert_eq!(font.ink_bounds(a, size), Some(Rect::new(0.0, -7.0, 6.0, 7.0)));
+    }
+}
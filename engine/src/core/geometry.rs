@@ -1,10 +1,84 @@
 use fixed::types::I48F16;
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Deserialize, Deserializer};
+use std::ops::RangeInclusive;
+use crate::core::path::{PathCommand, PathShape};
+use crate::core::text::Text;
 
 /// Fixed-point scalar type for deterministic space.
 /// Using I48F16: 48 bits for integer, 16 bits for fraction (approx 4 decimal places).
 pub type Scalar = I48F16;
 
+/// Sine and cosine of an angle in degrees, evaluated entirely in fixed point
+/// so rotated geometry is bit-identical on every platform.
+pub fn sin_cos_deg(degrees: Scalar) -> (Scalar, Scalar) {
+    let full = Scalar::from_num(360);
+    let mut d = degrees % full;
+    if d < Scalar::ZERO {
+        d += full;
+    }
+    let right = Scalar::from_num(90);
+    let quadrant = (d / right).int().to_num::<i32>() % 4;
+    let r = d - right * Scalar::from_num(quadrant);
+    // Keep the Taylor series argument within [0, 45] degrees.
+    let (s, c) = if r <= Scalar::from_num(45) {
+        sin_cos_small(r)
+    } else {
+        let (s, c) = sin_cos_small(right - r);
+        (c, s)
+    };
+    match quadrant {
+        0 => (s, c),
+        1 => (c, -s),
+        2 => (-s, -c),
+        _ => (-c, s),
+    }
+}
+
+fn sin_cos_small(degrees: Scalar) -> (Scalar, Scalar) {
+    let x = degrees * Scalar::PI / Scalar::from_num(180);
+    let x2 = x * x;
+    let sin = x * (Scalar::ONE - x2 / 6 * (Scalar::ONE - x2 / 20 * (Scalar::ONE - x2 / 42)));
+    let cos = Scalar::ONE - x2 / 2 * (Scalar::ONE - x2 / 12 * (Scalar::ONE - x2 / 30 * (Scalar::ONE - x2 / 56)));
+    (sin, cos)
+}
+
+/// Angle of `(x, y)` in degrees in (-180, 180], evaluated in fixed point
+/// like `sin_cos_deg`.
+pub fn atan2_deg(y: Scalar, x: Scalar) -> Scalar {
+    if x == Scalar::ZERO && y == Scalar::ZERO {
+        return Scalar::ZERO;
+    }
+    let (ax, ay) = (x.abs(), y.abs());
+    // Angle within the first octant, then mirrored out to the right quadrant.
+    let octant = if ay <= ax { atan_deg(ay / ax) } else { Scalar::from_num(90) - atan_deg(ax / ay) };
+    let half = Scalar::from_num(180);
+    match (x < Scalar::ZERO, y < Scalar::ZERO) {
+        (false, false) => octant,
+        (true, false) => half - octant,
+        (true, true) => octant - half,
+        (false, true) => -octant,
+    }
+}
+
+/// Arctangent in degrees for `z` in [0, 1].
+fn atan_deg(z: Scalar) -> Scalar {
+    // Above tan(22.5°), shift by 45° to keep the series argument small.
+    let (base, z) = if z > Scalar::lit("0.4142135624") {
+        (Scalar::from_num(45), (z - Scalar::ONE) / (z + Scalar::ONE))
+    } else {
+        (Scalar::ZERO, z)
+    };
+    let z2 = z * z;
+    let series = z * (Scalar::ONE - z2 * (Scalar::ONE / 3 - z2 * (Scalar::ONE / 5 - z2 * (Scalar::ONE / 7 - z2 * (Scalar::ONE / 9 - z2 / 11)))));
+    base + series * Scalar::from_num(180) / Scalar::PI
+}
+
+/// Magic number for approximating a quarter circle with one cubic bezier.
+pub const KAPPA: Scalar = Scalar::lit("0.5522847498");
+
+/// Distance within which a `Line` counts as hit, since it has no area.
+pub const LINE_HIT_TOLERANCE: Scalar = Scalar::lit("2");
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Point {
     pub x: Scalar,
@@ -25,6 +99,42 @@ impl Point {
             y: self.y + other.y,
         }
     }
+
+    pub fn sub(&self, other: &Point) -> Vector {
+        Vector {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        }
+    }
+
+    pub fn offset(&self, v: &Vector, scale: Scalar) -> Point {
+        Point {
+            x: self.x + v.x * scale,
+            y: self.y + v.y * scale,
+        }
+    }
+
+    /// Linear interpolation towards `other`, `t` in [0, 1].
+    pub fn lerp(&self, other: &Point, t: Scalar) -> Point {
+        Point {
+            x: self.x + (other.x - self.x) * t,
+            y: self.y + (other.y - self.y) * t,
+        }
+    }
+
+    pub fn distance(&self, other: &Point) -> Scalar {
+        self.sub(other).length()
+    }
+
+    pub fn distance_to_segment(&self, a: &Point, b: &Point) -> Scalar {
+        let ab = b.sub(a);
+        let len_sq = ab.dot(&ab);
+        if len_sq == Scalar::ZERO {
+            return self.distance(a);
+        }
+        let t = (self.sub(a).dot(&ab) / len_sq).clamp(Scalar::ZERO, Scalar::ONE);
+        self.distance(&a.offset(&ab, t))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -40,6 +150,48 @@ impl Vector {
             y: Scalar::from_num(y),
         }
     }
+
+    pub fn zero() -> Self {
+        Self { x: Scalar::ZERO, y: Scalar::ZERO }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.x == Scalar::ZERO && self.y == Scalar::ZERO
+    }
+
+    /// Squares that would overflow, from about 1.2e7 on, are avoided by
+    /// scaling the shorter side by the longer one first.
+    pub fn length(&self) -> Scalar {
+        let (x, y) = (self.x.saturating_abs(), self.y.saturating_abs());
+        if let Some(squared) = x.checked_mul(x).zip(y.checked_mul(y)).and_then(|(xx, yy)| xx.checked_add(yy)) {
+            return squared.sqrt();
+        }
+        let (long, short) = (x.max(y), x.min(y));
+        let ratio = short / long;
+        long.saturating_mul((Scalar::ONE + ratio * ratio).sqrt())
+    }
+
+    pub fn dot(&self, other: &Vector) -> Scalar {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// Z component of the 2D cross product.
+    pub fn cross(&self, other: &Vector) -> Scalar {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn scale(&self, factor: Scalar) -> Vector {
+        Vector { x: self.x * factor, y: self.y * factor }
+    }
+
+    /// Unit vector in the same direction, or zero for a zero vector.
+    pub fn normalize(&self) -> Vector {
+        let len = self.length();
+        if len == Scalar::ZERO {
+            return Vector::zero();
+        }
+        Vector { x: self.x / len, y: self.y / len }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -73,6 +225,56 @@ impl Rect {
         p.y >= self.origin.y && p.y <= self.origin.y + self.height
     }
 
+    /// Corners clockwise from the origin (y pointing down).
+    pub fn corners(&self) -> [Point; 4] {
+        let (x, y) = (self.origin.x, self.origin.y);
+        [self.origin, Point { x: x + self.width, y }, Point { x: x + self.width, y: y + self.height }, Point { x, y: y + self.height }]
+    }
+
+    /// Distance from `p` to the nearest point of the rect; zero inside it.
+    /// Saturates rather than overflowing across the whole `Scalar` range.
+    pub fn distance_to(&self, p: &Point) -> Scalar {
+        let (right, bottom) = (self.origin.x.saturating_add(self.width), self.origin.y.saturating_add(self.height));
+        let dx = self.origin.x.saturating_sub(p.x).max(p.x.saturating_sub(right)).max(Scalar::ZERO);
+        let dy = self.origin.y.saturating_sub(p.y).max(p.y.saturating_sub(bottom)).max(Scalar::ZERO);
+        Vector { x: dx, y: dy }.length()
+    }
+
+    /// Whether `other` lies entirely within this rect, edges included.
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        other.origin.x >= self.origin.x && other.origin.x + other.width <= self.origin.x + self.width &&
+        other.origin.y >= self.origin.y && other.origin.y + other.height <= self.origin.y + self.height
+    }
+
+    /// Grows the rect by `amount` on every side.
+    pub fn inflate(&self, amount: Scalar) -> Rect {
+        Rect {
+            origin: Point { x: self.origin.x - amount, y: self.origin.y - amount },
+            width: self.width + amount * Scalar::from_num(2),
+            height: self.height + amount * Scalar::from_num(2),
+        }
+    }
+
+    /// Smallest rect covering both.
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.origin.x.min(other.origin.x);
+        let y = self.origin.y.min(other.origin.y);
+        Rect {
+            origin: Point { x, y },
+            width: (self.origin.x + self.width).max(other.origin.x + other.width) - x,
+            height: (self.origin.y + self.height).max(other.origin.y + other.height) - y,
+        }
+    }
+
+    /// Overlap of the two rects, or `None` when they are disjoint.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x = self.origin.x.max(other.origin.x);
+        let y = self.origin.y.max(other.origin.y);
+        let right = (self.origin.x + self.width).min(other.origin.x + other.width);
+        let bottom = (self.origin.y + self.height).min(other.origin.y + other.height);
+        (right >= x && bottom >= y).then(|| Rect { origin: Point { x, y }, width: right - x, height: bottom - y })
+    }
+
     pub fn intersects(&self, other: &Rect) -> bool {
         !(other.origin.x > self.origin.x + self.width ||
           other.origin.x + other.width < self.origin.x ||
@@ -118,6 +320,339 @@ impl Circle {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ellipse {
+    pub center: Point,
+    pub radius_x: Scalar,
+    pub radius_y: Scalar,
+}
+
+impl Ellipse {
+    pub fn new(x: f32, y: f32, radius_x: f32, radius_y: f32) -> Self {
+        Self {
+            center: Point::new(x, y),
+            radius_x: Scalar::from_num(radius_x),
+            radius_y: Scalar::from_num(radius_y),
+        }
+    }
+
+    pub fn contains(&self, p: &Point) -> bool {
+        if self.radius_x <= Scalar::ZERO || self.radius_y <= Scalar::ZERO {
+            return false;
+        }
+        // Normalise first; the squared radii product would overflow for large ellipses.
+        let nx = (p.x - self.center.x) / self.radius_x;
+        let ny = (p.y - self.center.y) / self.radius_y;
+        nx * nx + ny * ny <= Scalar::ONE
+    }
+
+    pub fn get_bounding_box(&self) -> Rect {
+        Rect {
+            origin: Point {
+                x: self.center.x - self.radius_x,
+                y: self.center.y - self.radius_y,
+            },
+            width: self.radius_x * Scalar::from_num(2),
+            height: self.radius_y * Scalar::from_num(2),
+        }
+    }
+
+    pub fn to_path(&self) -> PathShape {
+        let (cx, cy) = (self.center.x, self.center.y);
+        let (rx, ry) = (self.radius_x, self.radius_y);
+        let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+        let pt = |x: Scalar, y: Scalar| Point { x, y };
+        let mut path = PathShape::new();
+        path.commands.push(PathCommand::MoveTo(pt(cx + rx, cy)));
+        path.commands.push(PathCommand::CurveTo(pt(cx + rx, cy + ky), pt(cx + kx, cy + ry), pt(cx, cy + ry)));
+        path.commands.push(PathCommand::CurveTo(pt(cx - kx, cy + ry), pt(cx - rx, cy + ky), pt(cx - rx, cy)));
+        path.commands.push(PathCommand::CurveTo(pt(cx - rx, cy - ky), pt(cx - kx, cy - ry), pt(cx, cy - ry)));
+        path.commands.push(PathCommand::CurveTo(pt(cx + kx, cy - ry), pt(cx + rx, cy - ky), pt(cx + rx, cy)));
+        path.commands.push(PathCommand::Close);
+        path
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CornerRadii {
+    pub top_left: Scalar,
+    pub top_right: Scalar,
+    pub bottom_right: Scalar,
+    pub bottom_left: Scalar,
+}
+
+impl CornerRadii {
+    pub fn uniform(radius: f32) -> Self {
+        let r = Scalar::from_num(radius);
+        Self { top_left: r, top_right: r, bottom_right: r, bottom_left: r }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundedRect {
+    pub origin: Point,
+    pub width: Scalar,
+    pub height: Scalar,
+    pub radii: CornerRadii,
+}
+
+impl RoundedRect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32, radii: CornerRadii) -> Self {
+        Self {
+            origin: Point::new(x, y),
+            width: Scalar::from_num(width),
+            height: Scalar::from_num(height),
+            radii,
+        }
+    }
+
+    pub fn get_bounding_box(&self) -> Rect {
+        Rect {
+            origin: self.origin,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Radii clamped to be non-negative and scaled down together when
+    /// adjacent corners would overlap, as CSS `border-radius` does.
+    pub fn effective_radii(&self) -> CornerRadii {
+        let r = self.radii;
+        let [tl, tr, br, bl] = [r.top_left, r.top_right, r.bottom_right, r.bottom_left].map(|v| v.max(Scalar::ZERO));
+        let mut factor = Scalar::ONE;
+        for (side, sum) in [(self.width, tl + tr), (self.width, bl + br), (self.height, tl + bl), (self.height, tr + br)] {
+            if sum > Scalar::ZERO && side < sum * factor {
+                factor = side.max(Scalar::ZERO) / sum;
+            }
+        }
+        CornerRadii {
+            top_left: tl * factor,
+            top_right: tr * factor,
+            bottom_right: br * factor,
+            bottom_left: bl * factor,
+        }
+    }
+
+    pub fn contains(&self, p: &Point) -> bool {
+        if !self.get_bounding_box().contains(p) {
+            return false;
+        }
+        let r = self.effective_radii();
+        let (left, top) = (self.origin.x, self.origin.y);
+        let (right, bottom) = (left + self.width, top + self.height);
+        // Corner centres paired with a test for whether `p` lies in that corner's square.
+        let corners = [
+            (r.top_left, Point { x: left + r.top_left, y: top + r.top_left }, p.x < left + r.top_left && p.y < top + r.top_left),
+            (r.top_right, Point { x: right - r.top_right, y: top + r.top_right }, p.x > right - r.top_right && p.y < top + r.top_right),
+            (r.bottom_right, Point { x: right - r.bottom_right, y: bottom - r.bottom_right }, p.x > right - r.bottom_right && p.y > bottom - r.bottom_right),
+            (r.bottom_left, Point { x: left + r.bottom_left, y: bottom - r.bottom_left }, p.x < left + r.bottom_left && p.y > bottom - r.bottom_left),
+        ];
+        corners.iter().all(|(radius, center, in_corner)| {
+            !in_corner || Circle { center: *center, radius: *radius }.contains(p)
+        })
+    }
+
+    pub fn to_path(&self) -> PathShape {
+        let r = self.effective_radii();
+        let (left, top) = (self.origin.x, self.origin.y);
+        let (right, bottom) = (left + self.width, top + self.height);
+        let pt = |x: Scalar, y: Scalar| Point { x, y };
+        let mut path = PathShape::new();
+        let cmds = &mut path.commands;
+        cmds.push(PathCommand::MoveTo(pt(left + r.top_left, top)));
+        cmds.push(PathCommand::LineTo(pt(right - r.top_right, top)));
+        if r.top_right > Scalar::ZERO {
+            let k = r.top_right * KAPPA;
+            cmds.push(PathCommand::CurveTo(pt(right - r.top_right + k, top), pt(right, top + r.top_right - k), pt(right, top + r.top_right)));
+        }
+        cmds.push(PathCommand::LineTo(pt(right, bottom - r.bottom_right)));
+        if r.bottom_right > Scalar::ZERO {
+            let k = r.bottom_right * KAPPA;
+            cmds.push(PathCommand::CurveTo(pt(right, bottom - r.bottom_right + k), pt(right - r.bottom_right + k, bottom), pt(right - r.bottom_right, bottom)));
+        }
+        cmds.push(PathCommand::LineTo(pt(left + r.bottom_left, bottom)));
+        if r.bottom_left > Scalar::ZERO {
+            let k = r.bottom_left * KAPPA;
+            cmds.push(PathCommand::CurveTo(pt(left + r.bottom_left - k, bottom), pt(left, bottom - r.bottom_left + k), pt(left, bottom - r.bottom_left)));
+        }
+        cmds.push(PathCommand::LineTo(pt(left, top + r.top_left)));
+        if r.top_left > Scalar::ZERO {
+            let k = r.top_left * KAPPA;
+            cmds.push(PathCommand::CurveTo(pt(left, top + r.top_left - k), pt(left + r.top_left - k, top), pt(left + r.top_left, top)));
+        }
+        cmds.push(PathCommand::Close);
+        path
+    }
+}
+
+/// Vertices on a circle, starting at the top and going clockwise on screen.
+fn radial_vertices(center: &Point, radii: &[Scalar], count: usize, rotation: Scalar) -> Vec<Point> {
+    let step = Scalar::from_num(360) / Scalar::from_num(count);
+    (0..count)
+        .map(|i| {
+            let (sin, cos) = sin_cos_deg(rotation - Scalar::from_num(90) + step * Scalar::from_num(i));
+            let r = radii[i % radii.len()];
+            Point { x: center.x + r * cos, y: center.y + r * sin }
+        })
+        .collect()
+}
+
+fn polygon_bounds(vertices: &[Point]) -> Rect {
+    let mut path = PathShape::new();
+    path.commands.extend(vertices.iter().map(|p| PathCommand::LineTo(*p)));
+    path.get_bounds()
+}
+
+fn polygon_path(vertices: &[Point]) -> PathShape {
+    let mut path = PathShape::new();
+    for (i, p) in vertices.iter().enumerate() {
+        path.commands.push(if i == 0 { PathCommand::MoveTo(*p) } else { PathCommand::LineTo(*p) });
+    }
+    path.commands.push(PathCommand::Close);
+    path
+}
+
+/// Side counts a polygon may have. Counts come straight from actions and
+/// keyframes, so they are clamped before any vertices are allocated.
+pub const POLYGON_SIDES: RangeInclusive<u32> = 3..=1000;
+/// Tip counts a star may have.
+pub const STAR_POINTS: RangeInclusive<u32> = 2..=1000;
+
+fn clamp_count(n: u32, range: &RangeInclusive<u32>) -> u32 {
+    n.clamp(*range.start(), *range.end())
+}
+
+/// Rounds a keyframed count to the nearest whole number within `range`.
+fn count_from(value: f32, range: &RangeInclusive<u32>) -> u32 {
+    // Float-to-int casts saturate, and NaN becomes zero.
+    clamp_count(value.round() as u32, range)
+}
+
+fn deserialize_sides<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    u32::deserialize(deserializer).map(|n| clamp_count(n, &POLYGON_SIDES))
+}
+
+fn deserialize_points<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    u32::deserialize(deserializer).map(|n| clamp_count(n, &STAR_POINTS))
+}
+
+/// Regular polygon inscribed in a circle of `radius`, `rotation` in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Polygon {
+    pub center: Point,
+    pub radius: Scalar,
+    #[serde(deserialize_with = "deserialize_sides")]
+    pub sides: u32,
+    pub rotation: Scalar,
+}
+
+impl Polygon {
+    pub fn new(x: f32, y: f32, radius: f32, sides: u32) -> Self {
+        Self {
+            center: Point::new(x, y),
+            radius: Scalar::from_num(radius),
+            sides: clamp_count(sides, &POLYGON_SIDES),
+            rotation: Scalar::ZERO,
+        }
+    }
+
+    pub fn vertices(&self) -> Vec<Point> {
+        radial_vertices(&self.center, &[self.radius], clamp_count(self.sides, &POLYGON_SIDES) as usize, self.rotation)
+    }
+
+    pub fn get_bounding_box(&self) -> Rect {
+        polygon_bounds(&self.vertices())
+    }
+
+    pub fn contains(&self, p: &Point) -> bool {
+        crate::core::polygon::winding_number(&[self.vertices()], p) != 0
+    }
+
+    pub fn to_path(&self) -> PathShape {
+        polygon_path(&self.vertices())
+    }
+}
+
+/// Star with `points` tips alternating between the outer and inner radius.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Star {
+    pub center: Point,
+    pub outer_radius: Scalar,
+    pub inner_radius: Scalar,
+    #[serde(deserialize_with = "deserialize_points")]
+    pub points: u32,
+    pub rotation: Scalar,
+}
+
+impl Star {
+    pub fn new(x: f32, y: f32, outer_radius: f32, inner_radius: f32, points: u32) -> Self {
+        Self {
+            center: Point::new(x, y),
+            outer_radius: Scalar::from_num(outer_radius),
+            inner_radius: Scalar::from_num(inner_radius),
+            points: clamp_count(points, &STAR_POINTS),
+            rotation: Scalar::ZERO,
+        }
+    }
+
+    pub fn vertices(&self) -> Vec<Point> {
+        let count = clamp_count(self.points, &STAR_POINTS) as usize * 2;
+        radial_vertices(&self.center, &[self.outer_radius, self.inner_radius], count, self.rotation)
+    }
+
+    pub fn get_bounding_box(&self) -> Rect {
+        polygon_bounds(&self.vertices())
+    }
+
+    pub fn contains(&self, p: &Point) -> bool {
+        crate::core::polygon::winding_number(&[self.vertices()], p) != 0
+    }
+
+    pub fn to_path(&self) -> PathShape {
+        polygon_path(&self.vertices())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Line {
+    pub start: Point,
+    pub end: Point,
+}
+
+impl Line {
+    pub fn new(x1: f32, y1: f32, x2: f32, y2: f32) -> Self {
+        Self {
+            start: Point::new(x1, y1),
+            end: Point::new(x2, y2),
+        }
+    }
+
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        let d = Vector::new(dx, dy);
+        self.start = self.start.offset(&d, Scalar::ONE);
+        self.end = self.end.offset(&d, Scalar::ONE);
+    }
+
+    pub fn get_bounding_box(&self) -> Rect {
+        Rect {
+            origin: Point { x: self.start.x.min(self.end.x), y: self.start.y.min(self.end.y) },
+            width: (self.end.x - self.start.x).abs(),
+            height: (self.end.y - self.start.y).abs(),
+        }
+    }
+
+    pub fn contains(&self, p: &Point) -> bool {
+        p.distance_to_segment(&self.start, &self.end) <= LINE_HIT_TOLERANCE
+    }
+
+    pub fn to_path(&self) -> PathShape {
+        let mut path = PathShape::new();
+        path.commands.push(PathCommand::MoveTo(self.start));
+        path.commands.push(PathCommand::LineTo(self.end));
+        path
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Group {
     pub children: Vec<String>,
@@ -153,6 +688,12 @@ pub enum Shape {
     Group(Group),
     Image(Image),
     Path(crate::core::path::PathShape),
+    Ellipse(Ellipse),
+    RoundedRect(RoundedRect),
+    Polygon(Polygon),
+    Star(Star),
+    Line(Line),
+    Text(Text),
 }
 
 impl Shape {
@@ -165,29 +706,25 @@ impl Shape {
                 i.origin.y += Scalar::from_num(dy);
             }
             Shape::Group(_) => {}
-            Shape::Path(p) => {
-                for cmd in &mut p.commands {
-                    match cmd {
-                        crate::core::path::PathCommand::MoveTo(pt) => {
-                            pt.x += Scalar::from_num(dx);
-                            pt.y += Scalar::from_num(dy);
-                        },
-                        crate::core::path::PathCommand::LineTo(pt) => {
-                            pt.x += Scalar::from_num(dx);
-                            pt.y += Scalar::from_num(dy);
-                        },
-                        crate::core::path::PathCommand::CurveTo(pt1, pt2, pt3) => {
-                            pt1.x += Scalar::from_num(dx);
-                            pt1.y += Scalar::from_num(dy);
-                            pt2.x += Scalar::from_num(dx);
-                            pt2.y += Scalar::from_num(dy);
-                            pt3.x += Scalar::from_num(dx);
-                            pt3.y += Scalar::from_num(dy);
-                        },
-                        crate::core::path::PathCommand::Close => {}
-                    }
-                }
+            Shape::Ellipse(e) => {
+                e.center.x += Scalar::from_num(dx);
+                e.center.y += Scalar::from_num(dy);
+            }
+            Shape::RoundedRect(r) => {
+                r.origin.x += Scalar::from_num(dx);
+                r.origin.y += Scalar::from_num(dy);
+            }
+            Shape::Polygon(p) => {
+                p.center.x += Scalar::from_num(dx);
+                p.center.y += Scalar::from_num(dy);
             }
+            Shape::Star(s) => {
+                s.center.x += Scalar::from_num(dx);
+                s.center.y += Scalar::from_num(dy);
+            }
+            Shape::Line(l) => l.translate(dx, dy),
+            Shape::Path(p) => p.translate(Scalar::from_num(dx), Scalar::from_num(dy)),
+            Shape::Text(t) => t.translate(dx, dy),
         }
     }
 
@@ -198,6 +735,12 @@ impl Shape {
             Shape::Image(i) => i.get_bounding_box(),
             Shape::Group(_) => Rect::new(0.0, 0.0, 0.0, 0.0), // Simplified, usually union of children
             Shape::Path(p) => p.get_bounds(),
+            Shape::Ellipse(e) => e.get_bounding_box(),
+            Shape::RoundedRect(r) => r.get_bounding_box(),
+            Shape::Polygon(p) => p.get_bounding_box(),
+            Shape::Star(s) => s.get_bounding_box(),
+            Shape::Line(l) => l.get_bounding_box(),
+            Shape::Text(t) => t.get_bounding_box(),
         }
     }
 
@@ -207,7 +750,224 @@ impl Shape {
             Shape::Circle(c) => c.contains(p),
             Shape::Image(i) => i.contains(p),
             Shape::Group(_) => false, // Group hit testing handled by recursion
-            Shape::Path(p) => p.get_bounds().contains(p), // Fallback to bounds for now
+            Shape::Path(path) => path.contains(p),
+            Shape::Ellipse(e) => e.contains(p),
+            Shape::RoundedRect(r) => r.contains(p),
+            Shape::Polygon(poly) => poly.contains(p),
+            Shape::Star(s) => s.contains(p),
+            Shape::Line(l) => l.contains(p),
+            Shape::Text(t) => t.contains(p),
+        }
+    }
+
+    /// Whether the shape's fill overlaps `rect`. Lines count within their
+    /// hit tolerance, as in `contains_point`.
+    pub fn intersects_rect(&self, rect: &Rect) -> bool {
+        match self {
+            Shape::Rect(r) => r.intersects(rect),
+            Shape::Group(_) => false, // Group hit testing handled by recursion
+            Shape::Text(t) => t.intersects_rect(rect),
+            Shape::Line(l) => l.to_path().intersects_rect(&rect.inflate(LINE_HIT_TOLERANCE)),
+            shape => shape.to_path().is_some_and(|p| p.intersects_rect(rect)),
+        }
+    }
+
+    /// Editable path tracing the outline of the shape, or `None` for groups,
+    /// which have no geometry of their own, and for text, whose outlines
+    /// come from its font. Images convert to their frame.
+    pub fn to_path(&self) -> Option<PathShape> {
+        match self {
+            Shape::Rect(r) => Some(RoundedRect { origin: r.origin, width: r.width, height: r.height, radii: CornerRadii::uniform(0.0) }.to_path()),
+            Shape::Circle(c) => Some(Ellipse { center: c.center, radius_x: c.radius, radius_y: c.radius }.to_path()),
+            Shape::Image(i) => Shape::Rect(i.get_bounding_box()).to_path(),
+            Shape::Group(_) => None,
+            Shape::Path(p) => Some(p.clone()),
+            Shape::Ellipse(e) => Some(e.to_path()),
+            Shape::RoundedRect(r) => Some(r.to_path()),
+            Shape::Polygon(p) => Some(p.to_path()),
+            Shape::Star(s) => Some(s.to_path()),
+            Shape::Line(l) => Some(l.to_path()),
+            Shape::Text(_) => None,
+        }
+    }
+
+    /// The point addressed by the `x` and `y` properties.
+    pub fn position(&self) -> Option<Point> {
+        match self {
+            Shape::Rect(r) => Some(r.origin),
+            Shape::Circle(c) => Some(c.center),
+            Shape::Image(i) => Some(i.origin),
+            Shape::Group(_) => None,
+            Shape::Path(p) => Some(p.get_bounds().origin),
+            Shape::Ellipse(e) => Some(e.center),
+            Shape::RoundedRect(r) => Some(r.origin),
+            Shape::Polygon(p) => Some(p.center),
+            Shape::Star(s) => Some(s.center),
+            Shape::Line(l) => Some(l.start),
+            Shape::Text(t) => Some(t.origin),
         }
     }
+
+    /// Sets an animatable parameter by name. Returns false when the shape has
+    /// no parameter of that name.
+    pub fn set_property(&mut self, prop: &str, value: f32) -> bool {
+        let v = Scalar::from_num(value);
+        let target = match (self, prop) {
+            (Shape::Rect(r), "x") => &mut r.origin.x,
+            (Shape::Rect(r), "y") => &mut r.origin.y,
+            (Shape::Rect(r), "width") => &mut r.width,
+            (Shape::Rect(r), "height") => &mut r.height,
+            (Shape::Circle(c), "x") => &mut c.center.x,
+            (Shape::Circle(c), "y") => &mut c.center.y,
+            (Shape::Circle(c), "radius") => &mut c.radius,
+            (Shape::Image(i), "x") => &mut i.origin.x,
+            (Shape::Image(i), "y") => &mut i.origin.y,
+            (Shape::Image(i), "width") => &mut i.width,
+            (Shape::Image(i), "height") => &mut i.height,
+            (Shape::Ellipse(e), "x") => &mut e.center.x,
+            (Shape::Ellipse(e), "y") => &mut e.center.y,
+            (Shape::Ellipse(e), "radius_x") => &mut e.radius_x,
+            (Shape::Ellipse(e), "radius_y") => &mut e.radius_y,
+            (Shape::RoundedRect(r), "x") => &mut r.origin.x,
+            (Shape::RoundedRect(r), "y") => &mut r.origin.y,
+            (Shape::RoundedRect(r), "width") => &mut r.width,
+            (Shape::RoundedRect(r), "height") => &mut r.height,
+            (Shape::RoundedRect(r), "corner_radius") => {
+                r.radii = CornerRadii { top_left: v, top_right: v, bottom_right: v, bottom_left: v };
+                return true;
+            }
+            (Shape::RoundedRect(r), "top_left_radius") => &mut r.radii.top_left,
+            (Shape::RoundedRect(r), "top_right_radius") => &mut r.radii.top_right,
+            (Shape::RoundedRect(r), "bottom_right_radius") => &mut r.radii.bottom_right,
+            (Shape::RoundedRect(r), "bottom_left_radius") => &mut r.radii.bottom_left,
+            (Shape::Polygon(p), "x") => &mut p.center.x,
+            (Shape::Polygon(p), "y") => &mut p.center.y,
+            (Shape::Polygon(p), "radius") => &mut p.radius,
+            (Shape::Polygon(p), "rotation") => &mut p.rotation,
+            (Shape::Polygon(p), "sides") => {
+                p.sides = count_from(value, &POLYGON_SIDES);
+                return true;
+            }
+            (Shape::Star(s), "x") => &mut s.center.x,
+            (Shape::Star(s), "y") => &mut s.center.y,
+            (Shape::Star(s), "radius") => &mut s.outer_radius,
+            (Shape::Star(s), "inner_radius") => &mut s.inner_radius,
+            (Shape::Star(s), "rotation") => &mut s.rotation,
+            (Shape::Star(s), "points") => {
+                s.points = count_from(value, &STAR_POINTS);
+                return true;
+            }
+            (Shape::Line(l), "x") => {
+                let dx = v - l.start.x;
+                l.start.x = v;
+                l.end.x += dx;
+                return true;
+            }
+            (Shape::Line(l), "y") => {
+                let dy = v - l.start.y;
+                l.start.y = v;
+                l.end.y += dy;
+                return true;
+            }
+            (Shape::Path(p), "x" | "y") => {
+                // Paths are positioned by the top-left of their bounds.
+                let origin = p.get_bounds().origin;
+                let (dx, dy) = if prop == "x" { (v - origin.x, Scalar::ZERO) } else { (Scalar::ZERO, v - origin.y) };
+                p.translate(dx, dy);
+                return true;
+            }
+            (Shape::Text(t), "x") => &mut t.origin.x,
+            (Shape::Text(t), "y") => &mut t.origin.y,
+            (Shape::Text(t), "font_size") => &mut t.font_size,
+            (Shape::Text(t), "line_height") => &mut t.line_height,
+            (Shape::Text(t), "letter_spacing") => &mut t.letter_spacing,
+            (Shape::Text(t), "width") => {
+                t.width = Some(v.max(Scalar::ZERO));
+                return true;
+            }
+            (Shape::Text(t), "path_offset") => match &mut t.path {
+                Some(path) => &mut path.offset,
+                None => return false,
+            },
+            (Shape::Line(l), "x2") => &mut l.end.x,
+            (Shape::Line(l), "y2") => &mut l.end.y,
+            _ => return false,
+        };
+        *target = v;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sin_cos_deg() {
+        for deg in [-450, -30, 0, 30, 45, 60, 90, 135, 210, 300, 359] {
+            let (s, c) = sin_cos_deg(Scalar::from_num(deg));
+            let rad = (deg as f32).to_radians();
+            assert!((s.to_num::<f32>() - rad.sin()).abs() < 1e-4, "sin {deg}");
+            assert!((c.to_num::<f32>() - rad.cos()).abs() < 1e-4, "cos {deg}");
+        }
+        assert_eq!(sin_cos_deg(Scalar::from_num(90)), (Scalar::ONE, Scalar::ZERO));
+    }
+
+    #[test]
+    fn test_atan2_deg() {
+        let deg = |y: f32, x: f32| atan2_deg(Scalar::from_num(y), Scalar::from_num(x)).to_num::<f32>();
+        for (y, x, expected) in [(0.0, 1.0, 0.0), (1.0, 1.0, 45.0), (1.0, 0.0, 90.0), (1.0, -1.0, 135.0), (0.0, -1.0, 180.0), (-1.0, -1.0, -135.0), (-1.0, 0.0, -90.0), (0.0, 0.0, 0.0)] {
+            assert!((deg(y, x) - expected).abs() < 0.01, "atan2({}, {})", y, x);
+        }
+        assert!((deg(1.0, 3.0) - 18.4349).abs() < 0.01);
+        assert!((deg(-3.0, 1.0) + 71.5651).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_rounded_rect_corners() {
+        let r = RoundedRect::new(0.0, 0.0, 100.0, 50.0, CornerRadii::uniform(40.0));
+        // Radii of 40 + 40 exceed the height of 50, so they scale down to 25.
+        assert_eq!(r.effective_radii(), CornerRadii::uniform(25.0));
+        assert!(!r.contains(&Point::new(2.0, 2.0)));
+        assert!(r.contains(&Point::new(25.0, 2.0)));
+        assert_eq!(r.to_path().get_bounds(), r.get_bounding_box());
+    }
+
+    #[test]
+    fn test_polygon_star_and_line() {
+        let square = Polygon { rotation: Scalar::from_num(45), ..Polygon::new(0.0, 0.0, 10.0, 4) };
+        let b = square.get_bounding_box();
+        assert!((b.width.to_num::<f32>() - 14.142).abs() < 0.01);
+        assert!(square.contains(&Point::new(6.0, 6.0)));
+
+        let star = Star::new(0.0, 0.0, 10.0, 4.0, 5);
+        assert_eq!(star.vertices().len(), 10);
+        assert_eq!(star.vertices()[0], Point::new(0.0, -10.0));
+        assert!(star.contains(&Point::new(0.0, -8.0)));
+        assert!(!star.contains(&Point::new(3.0, -8.0)));
+
+        let mut shape = Shape::Line(Line::new(0.0, 0.0, 10.0, 10.0));
+        assert!(shape.contains_point(&Point::new(6.0, 5.0)));
+        assert!(shape.set_property("x", 5.0));
+        assert_eq!(shape.get_bounding_box(), Rect::new(5.0, 0.0, 10.0, 10.0));
+        assert!(!shape.set_property("radius", 1.0));
+    }
+
+    #[test]
+    fn test_radial_counts_are_clamped() {
+        assert_eq!(Polygon::new(0.0, 0.0, 10.0, 4_000_000_000).sides, 1000);
+        assert_eq!(Star::new(0.0, 0.0, 10.0, 4.0, u32::MAX).vertices().len(), 2000);
+
+        let mut json = serde_json::to_value(Polygon::new(0.0, 0.0, 10.0, 5)).unwrap();
+        json["sides"] = 4_000_000_000u32.into();
+        assert_eq!(serde_json::from_value::<Polygon>(json).unwrap().sides, 1000);
+
+        let mut shape = Shape::Star(Star::new(0.0, 0.0, 10.0, 4.0, 5));
+        assert!(shape.set_property("points", 6.6));
+        assert!(matches!(shape, Shape::Star(Star { points: 7, .. })));
+        assert!(shape.set_property("points", 1e12));
+        assert!(matches!(shape, Shape::Star(Star { points: 1000, .. })));
+        assert!(shape.set_property("points", -3.0));
+        assert!(matches!(shape, Shape::Star(Star { points: 2, .. })));
+    }
 }
@@ -1,8 +1,15 @@
+use crate::core::color::Color;
+use crate::core::geometry::Scalar;
+use crate::core::morph;
+use crate::core::path::PathShape;
 use crate::core::state::Keyframe;
+use std::sync::Arc;
 
-pub fn interpolate(keyframes: &[Keyframe], time: f32) -> f32 {
+/// The keyframes bracketing `time` and the eased progress between them.
+/// Before the first or after the last keyframe both ends are that keyframe.
+pub fn bracket(keyframes: &[Keyframe], time: f32) -> Option<(&Keyframe, &Keyframe, f32)> {
     if keyframes.is_empty() {
-        return 0.0;
+        return None;
     }
 
     // Find the two keyframes that bracket the given time
@@ -10,10 +17,10 @@ pub fn interpolate(keyframes: &[Keyframe], time: f32) -> f32 {
     let mut after = &keyframes[keyframes.len() - 1];
 
     if time <= before.time {
-        return before.value;
+        return Some((before, before, 0.0));
     }
     if time >= after.time {
-        return after.value;
+        return Some((after, after, 1.0));
     }
 
     for i in 0..keyframes.len() - 1 {
@@ -26,12 +33,15 @@ pub fn interpolate(keyframes: &[Keyframe], time: f32) -> f32 {
 
     let duration = after.time - before.time;
     if duration == 0.0 {
-        return before.value;
+        return Some((before, before, 0.0));
     }
 
     let progress = (time - before.time) / duration;
-    
-    let eased_progress = match before.easing.as_str() {
+    Some((before, after, ease(&before.easing, progress)))
+}
+
+pub fn ease(easing: &str, progress: f32) -> f32 {
+    match easing {
         "linear" => progress,
         "ease-in" => progress * progress,
         "ease-out" => progress * (2.0 - progress),
@@ -42,9 +52,61 @@ pub fn interpolate(keyframes: &[Keyframe], time: f32) -> f32 {
             solve_cubic_bezier(easing, progress)
         },
         _ => progress,
+    }
+}
+
+pub fn interpolate(keyframes: &[Keyframe], time: f32) -> f32 {
+    match bracket(keyframes, time) {
+        Some((before, after, eased_progress)) => {
+            let from = before.value.as_number().unwrap_or(0.0);
+            let to = after.value.as_number().unwrap_or(from);
+            from + (to - from) * eased_progress
+        }
+        None => 0.0,
+    }
+}
+
+/// Blends color keyframes in the space chosen by the earlier keyframe, or
+/// `None` when the track holds no colors.
+pub fn interpolate_color(keyframes: &[Keyframe], time: f32) -> Option<Color> {
+    let (before, after, eased_progress) = bracket(keyframes, time)?;
+    let from = before.value.as_color()?;
+    let to = after.value.as_color().unwrap_or(from);
+    Some(from.interpolate(&to, Scalar::from_num(eased_progress), before.color_space))
+}
+
+/// Morphs between path keyframes, or `None` when the track holds no paths.
+pub fn interpolate_path(keyframes: &[Keyframe], time: f32) -> Option<PathShape> {
+    let (before, after, eased_progress) = bracket(keyframes, time)?;
+    let from = before.value.as_path()?;
+    let to = match after.value.as_path() {
+        Some(to) if !std::ptr::eq(before, after) => to,
+        _ => return Some(from.clone()),
     };
+    let t = Scalar::from_num(eased_progress);
+    Some(match &before.morph {
+        Some(pair) => morph::lerp_paths(&pair.0, &pair.1, t).unwrap_or_else(|| pair.0.clone()),
+        None => morph::morph(from, to, t),
+    })
+}
 
-    before.value + (after.value - before.value) * eased_progress
+/// Prepares the morph from path keyframe `index` to the one after it, or
+/// clears it when either is missing or not a path.
+pub fn prepare_morph_at(keyframes: &mut [Keyframe], index: usize) {
+    let pair = match (keyframes.get(index), keyframes.get(index + 1)) {
+        (Some(from), Some(to)) => from.value.as_path().zip(to.value.as_path()).map(|(a, b)| Arc::new(morph::prepare_morph(a, b))),
+        _ => None,
+    };
+    if let Some(kf) = keyframes.get_mut(index) {
+        kf.morph = pair;
+    }
+}
+
+/// Prepares the morph between every pair of neighbouring path keyframes.
+pub fn prepare_path_track(keyframes: &mut [Keyframe]) {
+    for index in 0..keyframes.len() {
+        prepare_morph_at(keyframes, index);
+    }
 }
 
 fn solve_cubic_bezier(config: &str, x: f32) -> f32 {
@@ -4,3 +4,17 @@ pub mod interpolation;
 pub mod physics;
 pub mod path;
 pub mod spatial;
+pub mod rtree;
+pub mod picking;
+pub mod path_edit;
+pub mod simplify;
+pub mod stroke;
+pub mod polygon;
+pub mod offset;
+pub mod morph;
+pub mod color;
+pub mod paint;
+pub mod effects;
+pub mod font;
+pub mod woff2;
+pub mod text;
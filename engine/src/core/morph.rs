@@ -0,0 +1,275 @@
+use crate::core::geometry::{Point, Scalar};
+use crate::core::path::{PathCommand, PathShape, Segment, DEFAULT_TOLERANCE};
+use crate::core::path_edit::Subpath;
+use crate::core::polygon::signed_area;
+
+/// A subpath as a chain of cubics, each stored as `[control1, control2, end]`.
+#[derive(Debug, Clone)]
+struct CubicChain {
+    start: Point,
+    curves: Vec<[Point; 3]>,
+    closed: bool,
+}
+
+impl CubicChain {
+    fn from_subpath(sub: &Subpath, closed: bool) -> Self {
+        let mut sub = sub.clone();
+        sub.closed = closed;
+        sub.make_closing_explicit();
+        let third = Scalar::ONE / Scalar::from_num(3);
+        let curves = (0..sub.segments.len())
+            .map(|i| match sub.segment(i) {
+                Segment::Line(p0, p1) => [p0.lerp(&p1, third), p1.lerp(&p0, third), p1],
+                Segment::Cubic(_, c1, c2, p) => [c1, c2, p],
+            })
+            .collect();
+        Self { start: sub.start, curves, closed }
+    }
+
+    fn degenerate(at: Point, closed: bool) -> Self {
+        Self { start: at, curves: Vec::new(), closed }
+    }
+
+    fn anchor(&self, i: usize) -> Point {
+        if i == 0 { self.start } else { self.curves[i - 1][2] }
+    }
+
+    fn segment(&self, i: usize) -> Segment {
+        let [c1, c2, p] = self.curves[i];
+        Segment::Cubic(self.anchor(i), c1, c2, p)
+    }
+
+    fn center(&self) -> Point {
+        let mut path = PathShape::new();
+        path.commands.push(PathCommand::MoveTo(self.start));
+        path.commands.extend(self.curves.iter().map(|[c1, c2, p]| PathCommand::CurveTo(*c1, *c2, *p)));
+        let b = path.get_bounds();
+        let half = Scalar::lit("0.5");
+        Point { x: b.origin.x + b.width * half, y: b.origin.y + b.height * half }
+    }
+
+    /// Splits the longest curves in half until the chain has `count` curves.
+    fn equalize(&mut self, count: usize) {
+        if self.curves.is_empty() && count > 0 {
+            self.curves = vec![[self.start; 3]; count];
+            return;
+        }
+        let mut lengths: Vec<Scalar> = (0..self.curves.len()).map(|i| self.segment(i).length(DEFAULT_TOLERANCE)).collect();
+        while self.curves.len() < count {
+            let mut longest = 0;
+            for (i, len) in lengths.iter().enumerate() {
+                if *len > lengths[longest] {
+                    longest = i;
+                }
+            }
+            let (a, b) = self.segment(longest).split(Scalar::lit("0.5"));
+            let piece = |s: Segment| match s {
+                Segment::Cubic(_, c1, c2, p) => [c1, c2, p],
+                Segment::Line(_, p) => [p, p, p],
+            };
+            self.curves[longest] = piece(a);
+            self.curves.insert(longest + 1, piece(b));
+            let half = lengths[longest] / Scalar::from_num(2);
+            lengths[longest] = half;
+            lengths.insert(longest + 1, half);
+        }
+    }
+
+    fn reverse(&mut self) {
+        let n = self.curves.len();
+        let mut reversed = Vec::with_capacity(n);
+        for i in (0..n).rev() {
+            let [c1, c2, _] = self.curves[i];
+            reversed.push([c2, c1, self.anchor(i)]);
+        }
+        self.start = self.anchor(n);
+        self.curves = reversed;
+    }
+
+    /// Makes anchor `r` the start of a closed chain.
+    fn rotate(&mut self, r: usize) {
+        if r == 0 || self.curves.is_empty() {
+            return;
+        }
+        self.start = self.anchor(r);
+        self.curves.rotate_left(r);
+    }
+
+    fn anchors(&self) -> Vec<Point> {
+        self.curves.iter().map(|c| c[2]).collect()
+    }
+
+    fn to_commands(&self, out: &mut Vec<PathCommand>) {
+        out.push(PathCommand::MoveTo(self.start));
+        out.extend(self.curves.iter().map(|[c1, c2, p]| PathCommand::CurveTo(*c1, *c2, *p)));
+        if self.closed {
+            out.push(PathCommand::Close);
+        }
+    }
+}
+
+fn distance_sq(a: &Point, b: &Point) -> Scalar {
+    let d = a.sub(b);
+    d.dot(&d)
+}
+
+/// Orients and rotates `to` so its anchors line up with those of `from`.
+fn align(from: &CubicChain, to: &mut CubicChain) {
+    if from.curves.is_empty() {
+        return;
+    }
+    if !from.closed {
+        let straight = distance_sq(&from.start, &to.start) + distance_sq(&from.anchor(from.curves.len()), &to.anchor(to.curves.len()));
+        let flipped = distance_sq(&from.start, &to.anchor(to.curves.len())) + distance_sq(&from.anchor(from.curves.len()), &to.start);
+        if flipped < straight {
+            to.reverse();
+        }
+        return;
+    }
+
+    let a = from.anchors();
+    let mut b = to.anchors();
+    let area_a = signed_area(&a);
+    let area_b = signed_area(&b);
+    if (area_a > Scalar::ZERO && area_b < Scalar::ZERO) || (area_a < Scalar::ZERO && area_b > Scalar::ZERO) {
+        to.reverse();
+        b = to.anchors();
+    }
+    let n = a.len();
+    let mut best = (Scalar::MAX, 0);
+    for r in 0..n {
+        let cost: Scalar = (0..n).map(|i| distance_sq(&a[i], &b[(i + r) % n])).sum();
+        if cost < best.0 {
+            best = (cost, r);
+        }
+    }
+    to.rotate(best.1);
+}
+
+/// Brings two paths to an identical command structure (same subpaths, all
+/// cubic, equal segment counts) with matching start points and winding, so
+/// they can be interpolated point by point.
+pub fn prepare_morph(from: &PathShape, to: &PathShape) -> (PathShape, PathShape) {
+    let subs_a = from.subpaths();
+    let subs_b = to.subpaths();
+    let count = subs_a.len().max(subs_b.len());
+    let mut out_a = PathShape::new();
+    let mut out_b = PathShape::new();
+
+    for i in 0..count {
+        let closed = subs_a.get(i).is_some_and(|s| s.closed) || subs_b.get(i).is_some_and(|s| s.closed);
+        let mut a = subs_a.get(i).map(|s| CubicChain::from_subpath(s, closed));
+        let mut b = subs_b.get(i).map(|s| CubicChain::from_subpath(s, closed));
+        // Subpaths without a partner grow from, or shrink to, the centre of their counterpart.
+        if a.is_none() {
+            a = b.as_ref().map(|b| CubicChain::degenerate(b.center(), closed));
+        }
+        if b.is_none() {
+            b = a.as_ref().map(|a| CubicChain::degenerate(a.center(), closed));
+        }
+        let (Some(mut a), Some(mut b)) = (a, b) else { continue };
+
+        let n = a.curves.len().max(b.curves.len());
+        a.equalize(n);
+        b.equalize(n);
+        align(&a, &mut b);
+        a.to_commands(&mut out_a.commands);
+        b.to_commands(&mut out_b.commands);
+    }
+    (out_a, out_b)
+}
+
+/// Point-wise interpolation of two paths with identical command structure.
+/// Returns `None` when the structures differ.
+pub fn lerp_paths(from: &PathShape, to: &PathShape, t: Scalar) -> Option<PathShape> {
+    if from.commands.len() != to.commands.len() {
+        return None;
+    }
+    let mut commands = Vec::with_capacity(from.commands.len());
+    for (a, b) in from.commands.iter().zip(&to.commands) {
+        commands.push(match (a, b) {
+            (PathCommand::MoveTo(p), PathCommand::MoveTo(q)) => PathCommand::MoveTo(p.lerp(q, t)),
+            (PathCommand::LineTo(p), PathCommand::LineTo(q)) => PathCommand::LineTo(p.lerp(q, t)),
+            (PathCommand::CurveTo(p1, p2, p3), PathCommand::CurveTo(q1, q2, q3)) => {
+                PathCommand::CurveTo(p1.lerp(q1, t), p2.lerp(q2, t), p3.lerp(q3, t))
+            }
+            (PathCommand::Close, PathCommand::Close) => PathCommand::Close,
+            _ => return None,
+        });
+    }
+    Some(PathShape { commands })
+}
+
+/// Shape at `t` of a morph from `from` to `to`. Paths are always aligned
+/// first, since matching command structures can still start at different
+/// nodes or wind in opposite directions.
+pub fn morph(from: &PathShape, to: &PathShape, t: Scalar) -> PathShape {
+    let (a, b) = prepare_morph(from, to);
+    lerp_paths(&a, &b, t).unwrap_or(a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(offset: usize) -> PathShape {
+        let corners = [(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0)];
+        let mut path = PathShape::new();
+        for i in 0..4 {
+            let (x, y) = corners[(i + offset) % 4];
+            if i == 0 { path.move_to(x, y) } else { path.line_to(x, y) }
+        }
+        path.close();
+        path
+    }
+
+    fn triangle() -> PathShape {
+        let mut path = PathShape::new();
+        path.move_to(50.0, 0.0);
+        path.line_to(0.0, 100.0);
+        path.line_to(100.0, 100.0);
+        path.close();
+        path
+    }
+
+    #[test]
+    fn test_equalizes_counts_and_keeps_endpoints() {
+        let (a, b) = prepare_morph(&square(0), &triangle());
+        assert_eq!(a.commands.len(), b.commands.len());
+
+        let start = morph(&square(0), &triangle(), Scalar::ZERO);
+        let end = morph(&square(0), &triangle(), Scalar::ONE);
+        assert_eq!(start.get_bounds(), square(0).get_bounds());
+        assert!(end.contains(&Point::new(50.0, 90.0)));
+        assert!(!end.contains(&Point::new(10.0, 10.0)));
+    }
+
+    #[test]
+    fn test_aligns_start_point_and_winding() {
+        let mut reversed = square(2);
+        reversed.reverse(None);
+        let (a, b) = prepare_morph(&square(0), &reversed);
+        // Identical shapes must not twist: every anchor maps onto itself.
+        for (p, q) in a.commands.iter().zip(&b.commands) {
+            match (p, q) {
+                (PathCommand::CurveTo(_, _, p), PathCommand::CurveTo(_, _, q)) => assert_eq!(p, q),
+                (PathCommand::MoveTo(p), PathCommand::MoveTo(q)) => assert_eq!(p, q),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_morph_aligns_matching_structures() {
+        let half = Scalar::lit("0.5");
+        let mut reversed = square(0);
+        reversed.reverse(None);
+        // Without alignment these collapse to a diamond and to a point mid-morph.
+        for other in [square(1), reversed] {
+            let mid = morph(&square(0), &other, half);
+            assert_eq!(mid.get_bounds(), square(0).get_bounds());
+            assert!(mid.contains(&Point::new(10.0, 10.0)));
+            assert!(mid.contains(&Point::new(90.0, 90.0)));
+        }
+    }
+}
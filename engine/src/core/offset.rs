@@ -0,0 +1,95 @@
+use crate::core::geometry::{Point, Scalar};
+use crate::core::path::{PathCommand, PathShape};
+use crate::core::polygon::resolve;
+use crate::core::stroke::{LineJoin, StrokeStyle};
+
+impl PathShape {
+    /// Grows (positive `distance`) or shrinks (negative) the filled region of
+    /// the path. Open subpaths are treated as closed, as when filling. The
+    /// result is polygonal, flattened to `tolerance`, and free of self-intersections.
+    pub fn offset(&self, distance: Scalar, join: LineJoin, miter_limit: Scalar, tolerance: Scalar) -> PathShape {
+        let polygons: Vec<Vec<Point>> = self.flatten(tolerance).into_iter().map(|l| l.points).collect();
+        // Normalised loops have the filled region on their left, holes included.
+        let mut loops = resolve(&polygons, |w| w != 0);
+
+        if distance != Scalar::ZERO {
+            let growing = distance > Scalar::ZERO;
+            let style = StrokeStyle {
+                width: distance.abs() * Scalar::from_num(2),
+                join,
+                miter_limit,
+                ..StrokeStyle::new(0.0)
+            };
+            let mut raw = Vec::new();
+            for mut ring in loops {
+                // The stroker offsets the left side; growing offsets away from the fill.
+                if growing {
+                    ring.reverse();
+                }
+                let corners = vec![true; ring.len()];
+                let mut cmds = Vec::new();
+                style.side(&ring, &corners, true, false, &mut cmds);
+                let offset_ring = PathShape { commands: cmds };
+                raw.extend(offset_ring.flatten(tolerance).into_iter().map(|l| l.points));
+            }
+            // Swallowtails and collapsed parts wind the wrong way; keep only the positive side.
+            loops = resolve(&raw, |w| if growing { w < 0 } else { w > 0 });
+        }
+
+        let mut result = PathShape::new();
+        for ring in loops {
+            result.commands.push(PathCommand::MoveTo(ring[0]));
+            result.commands.extend(ring[1..].iter().map(|p| PathCommand::LineTo(*p)));
+            result.commands.push(PathCommand::Close);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::geometry::Rect;
+    use crate::core::path::DEFAULT_TOLERANCE;
+
+    fn l_shape() -> PathShape {
+        let mut path = PathShape::new();
+        path.move_to(0.0, 0.0);
+        path.line_to(100.0, 0.0);
+        path.line_to(100.0, 40.0);
+        path.line_to(40.0, 40.0);
+        path.line_to(40.0, 100.0);
+        path.line_to(0.0, 100.0);
+        path.close();
+        path
+    }
+
+    #[test]
+    fn test_outset_and_inset_l_shape() {
+        let path = l_shape();
+        let grown = path.offset(Scalar::from_num(10), LineJoin::Miter, Scalar::from_num(4), DEFAULT_TOLERANCE);
+        assert_eq!(grown.get_bounds(), Rect::new(-10.0, -10.0, 120.0, 120.0));
+        assert!(grown.contains(&Point::new(45.0, 45.0)));
+        assert!(!grown.contains(&Point::new(55.0, 55.0)));
+
+        let shrunk = path.offset(Scalar::from_num(-10), LineJoin::Miter, Scalar::from_num(4), DEFAULT_TOLERANCE);
+        assert_eq!(shrunk.get_bounds(), Rect::new(10.0, 10.0, 80.0, 80.0));
+        assert!(shrunk.contains(&Point::new(20.0, 20.0)));
+        assert!(!shrunk.contains(&Point::new(35.0, 35.0)));
+        assert!(!shrunk.contains(&Point::new(5.0, 50.0)));
+    }
+
+    #[test]
+    fn test_round_joins_and_collapsing_inset() {
+        let shrunk = l_shape().offset(Scalar::from_num(-25), LineJoin::Round, Scalar::from_num(4), DEFAULT_TOLERANCE);
+        assert!(shrunk.commands.is_empty());
+
+        let shrunk = l_shape().offset(Scalar::from_num(-15), LineJoin::Round, Scalar::from_num(4), DEFAULT_TOLERANCE);
+        assert_eq!(shrunk.subpaths().len(), 1);
+        assert!(shrunk.contains(&Point::new(20.0, 20.0)));
+
+        let round = l_shape().offset(Scalar::from_num(10), LineJoin::Round, Scalar::from_num(4), DEFAULT_TOLERANCE);
+        assert!(!round.contains(&Point::new(-9.0, -9.0)));
+        assert!(round.contains(&Point::new(-6.0, -6.0)));
+    }
+}
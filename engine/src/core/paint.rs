@@ -0,0 +1,131 @@
+use serde::{Serialize, Deserialize, Deserializer};
+use crate::core::color::Color;
+use crate::core::geometry::{Point, Scalar};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColorStop {
+    /// Position along the gradient in [0, 1].
+    pub offset: Scalar,
+    pub color: Color,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImageFit {
+    Fill,
+    Fit,
+    Stretch,
+    Tile,
+}
+
+/// How an element composites onto what is beneath it. Names match CSS
+/// `mix-blend-mode` so they can be handed straight to renderers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+}
+
+/// How a fill or stroke is painted. Gradient geometry is in the element's
+/// bounding box space, where (0, 0) is its top-left and (1, 1) its
+/// bottom-right. A solid paint serializes as a bare CSS color string.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Paint {
+    LinearGradient { start: Point, end: Point, stops: Vec<ColorStop> },
+    RadialGradient { center: Point, radius: Scalar, stops: Vec<ColorStop> },
+    /// `angle` is the start direction in degrees, clockwise from up as in CSS.
+    ConicGradient { center: Point, angle: Scalar, stops: Vec<ColorStop> },
+    Image { src: String, fit: ImageFit },
+    #[serde(untagged)]
+    Solid(Color),
+}
+
+impl Default for Paint {
+    fn default() -> Self {
+        Paint::Solid(Color::BLACK)
+    }
+}
+
+impl Paint {
+    pub fn stops(&self) -> &[ColorStop] {
+        match self {
+            Paint::LinearGradient { stops, .. }
+            | Paint::RadialGradient { stops, .. }
+            | Paint::ConicGradient { stops, .. } => stops,
+            _ => &[],
+        }
+    }
+
+    /// Clamps stop offsets into [0, 1] and orders them, keeping the input
+    /// order of coincident stops so hard edges survive.
+    pub fn normalize(&mut self) {
+        if let Paint::LinearGradient { stops, .. }
+        | Paint::RadialGradient { stops, .. }
+        | Paint::ConicGradient { stops, .. } = self
+        {
+            for stop in stops.iter_mut() {
+                stop.offset = stop.offset.clamp(Scalar::ZERO, Scalar::ONE);
+            }
+            stops.sort_by_key(|s| s.offset);
+        }
+    }
+}
+
+/// Reads an element fill. Fills used to be free-form strings, so a string
+/// that is not a CSS color ("none", "var(--accent)", a typo) loads as
+/// transparent rather than failing the whole document.
+pub fn deserialize_fill<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Paint, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Paint(Paint),
+        Legacy(String),
+    }
+    Ok(match Stored::deserialize(deserializer)? {
+        Stored::Paint(paint) => paint,
+        Stored::Legacy(css) => Paint::Solid(Color::parse(&css).unwrap_or(Color::TRANSPARENT)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint_serde() {
+        let solid: Paint = serde_json::from_str("\"rgb(255, 0, 0)\"").unwrap();
+        assert_eq!(solid, Paint::Solid(Color::rgba8(255, 0, 0, 255)));
+        assert_eq!(serde_json::to_string(&solid).unwrap(), "\"#ff0000\"");
+        assert!(serde_json::from_str::<Paint>("\"not a color\"").is_err());
+
+        let mut gradient = Paint::LinearGradient {
+            start: Point::new(0.0, 0.0),
+            end: Point::new(1.0, 0.0),
+            stops: vec![
+                ColorStop { offset: Scalar::ONE, color: Color::BLACK },
+                ColorStop { offset: Scalar::from_num(-1), color: Color::TRANSPARENT },
+            ],
+        };
+        gradient.normalize();
+        assert_eq!(gradient.stops()[0].offset, Scalar::ZERO);
+        let json = serde_json::to_string(&gradient).unwrap();
+        assert!(json.contains("\"type\":\"LinearGradient\""));
+        assert_eq!(serde_json::from_str::<Paint>(&json).unwrap(), gradient);
+    }
+}
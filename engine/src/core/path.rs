@@ -1,7 +1,7 @@
-use crate::core::geometry::{Point, Scalar};
+use crate::core::geometry::{Point, Rect, Scalar, Vector};
 use serde::{Serialize, Deserialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PathCommand {
     MoveTo(Point),
     LineTo(Point),
@@ -14,6 +14,240 @@ pub struct PathShape {
     pub commands: Vec<PathCommand>,
 }
 
+/// A single drawable piece of a path with its start point resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    Line(Point, Point),
+    Cubic(Point, Point, Point, Point), // start, control1, control2, end
+}
+
+/// A flattened subpath.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Polyline {
+    pub points: Vec<Point>,
+    pub closed: bool,
+}
+
+/// Position and unit tangent at some location along a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathSample {
+    pub point: Point,
+    pub tangent: Vector,
+}
+
+/// A path flattened once for repeated lookups by arc length.
+#[derive(Debug, Clone)]
+pub struct PathMeasure {
+    segments: Vec<Segment>,
+    pieces: Vec<MeasuredPiece>,
+}
+
+/// One flattening step of a segment, ending at parameter `t` and at distance
+/// `end` from the start of the path.
+#[derive(Debug, Clone, Copy)]
+struct MeasuredPiece {
+    segment: usize,
+    t: Scalar,
+    step: Scalar,
+    end: Scalar,
+    length: Scalar,
+}
+
+/// Default flattening tolerance, in canvas units.
+pub const DEFAULT_TOLERANCE: Scalar = Scalar::lit("0.25");
+
+/// Upper bound on the pieces a single cubic is flattened into.
+const MAX_CURVE_STEPS: usize = 256;
+
+impl Segment {
+    pub fn start(&self) -> Point {
+        match self {
+            Segment::Line(p0, _) => *p0,
+            Segment::Cubic(p0, _, _, _) => *p0,
+        }
+    }
+
+    pub fn end(&self) -> Point {
+        match self {
+            Segment::Line(_, p1) => *p1,
+            Segment::Cubic(_, _, _, p3) => *p3,
+        }
+    }
+
+    pub fn point_at(&self, t: Scalar) -> Point {
+        match self {
+            Segment::Line(p0, p1) => p0.lerp(p1, t),
+            Segment::Cubic(p0, p1, p2, p3) => {
+                // De Casteljau keeps every step a convex combination, which behaves well in fixed point.
+                let a = p0.lerp(p1, t);
+                let b = p1.lerp(p2, t);
+                let c = p2.lerp(p3, t);
+                let d = a.lerp(&b, t);
+                let e = b.lerp(&c, t);
+                d.lerp(&e, t)
+            }
+        }
+    }
+
+    /// Splits the segment at `t` into two segments that trace the same curve.
+    pub fn split(&self, t: Scalar) -> (Segment, Segment) {
+        match self {
+            Segment::Line(p0, p1) => {
+                let m = p0.lerp(p1, t);
+                (Segment::Line(*p0, m), Segment::Line(m, *p1))
+            }
+            Segment::Cubic(p0, p1, p2, p3) => {
+                let a = p0.lerp(p1, t);
+                let b = p1.lerp(p2, t);
+                let c = p2.lerp(p3, t);
+                let d = a.lerp(&b, t);
+                let e = b.lerp(&c, t);
+                let m = d.lerp(&e, t);
+                (Segment::Cubic(*p0, a, d, m), Segment::Cubic(m, e, c, *p3))
+            }
+        }
+    }
+
+    /// The drawing command that produces this segment from its start point.
+    pub fn to_command(&self) -> PathCommand {
+        match self {
+            Segment::Line(_, p1) => PathCommand::LineTo(*p1),
+            Segment::Cubic(_, c1, c2, p3) => PathCommand::CurveTo(*c1, *c2, *p3),
+        }
+    }
+
+    /// Unit tangent at `t`, falling back to the chord when the derivative vanishes.
+    pub fn tangent_at(&self, t: Scalar) -> Vector {
+        match self {
+            Segment::Line(p0, p1) => p1.sub(p0).normalize(),
+            Segment::Cubic(p0, p1, p2, p3) => {
+                let a = p0.lerp(p1, t);
+                let b = p1.lerp(p2, t);
+                let c = p2.lerp(p3, t);
+                let d = a.lerp(&b, t);
+                let e = b.lerp(&c, t);
+                let derivative = e.sub(&d);
+                if !derivative.is_zero() {
+                    return derivative.normalize();
+                }
+                let fallback = if t < Scalar::lit("0.5") { p2.sub(p0) } else { p3.sub(p1) };
+                if !fallback.is_zero() {
+                    return fallback.normalize();
+                }
+                p3.sub(p0).normalize()
+            }
+        }
+    }
+
+    /// Number of uniform parameter steps needed to stay within `tolerance` of the curve.
+    pub fn flatten_steps(&self, tolerance: Scalar) -> usize {
+        match self {
+            Segment::Line(_, _) => 1,
+            Segment::Cubic(p0, p1, p2, p3) => {
+                // Wang's formula: n = sqrt(3/4 * max|second difference| / tolerance)
+                let dd1 = Vector { x: p0.x - p1.x * 2 + p2.x, y: p0.y - p1.y * 2 + p2.y };
+                let dd2 = Vector { x: p1.x - p2.x * 2 + p3.x, y: p1.y - p2.y * 2 + p3.y };
+                let m = dd1.length().max(dd2.length());
+                if m == Scalar::ZERO {
+                    return 1;
+                }
+                let tolerance = tolerance.max(Scalar::DELTA);
+                let n = (m * Scalar::lit("0.75") / tolerance).sqrt().ceil();
+                n.to_num::<usize>().clamp(1, MAX_CURVE_STEPS)
+            }
+        }
+    }
+
+    /// Appends the flattened points of this segment, excluding the start point.
+    pub fn flatten_into(&self, tolerance: Scalar, out: &mut Vec<Point>) {
+        let steps = self.flatten_steps(tolerance);
+        let step = Scalar::ONE / Scalar::from_num(steps);
+        for i in 1..steps {
+            out.push(self.point_at(step * Scalar::from_num(i)));
+        }
+        out.push(self.end());
+    }
+
+    pub fn length(&self, tolerance: Scalar) -> Scalar {
+        match self {
+            Segment::Line(p0, p1) => p0.distance(p1),
+            Segment::Cubic(..) => {
+                let mut pts = vec![self.start()];
+                self.flatten_into(tolerance, &mut pts);
+                pts.windows(2).map(|w| w[0].distance(&w[1])).sum()
+            }
+        }
+    }
+
+    /// Parameter `t` at which the arc length from the start reaches `distance`.
+    pub fn t_at_length(&self, distance: Scalar, tolerance: Scalar) -> Scalar {
+        if distance <= Scalar::ZERO {
+            return Scalar::ZERO;
+        }
+        let steps = self.flatten_steps(tolerance);
+        let step = Scalar::ONE / Scalar::from_num(steps);
+        let mut travelled = Scalar::ZERO;
+        let mut prev = self.start();
+        for i in 1..=steps {
+            let t = if i == steps { Scalar::ONE } else { step * Scalar::from_num(i) };
+            let p = self.point_at(t);
+            let piece = prev.distance(&p);
+            if travelled + piece >= distance && piece > Scalar::ZERO {
+                let frac = (distance - travelled) / piece;
+                return t - step + step * frac;
+            }
+            travelled += piece;
+            prev = p;
+        }
+        Scalar::ONE
+    }
+}
+
+impl PathMeasure {
+    /// Flattens `segments` with the same steps as `Segment::flatten_into`.
+    pub fn new(segments: Vec<Segment>, tolerance: Scalar) -> Self {
+        let mut pieces = Vec::new();
+        let mut travelled = Scalar::ZERO;
+        for (index, seg) in segments.iter().enumerate() {
+            let steps = seg.flatten_steps(tolerance);
+            let step = Scalar::ONE / Scalar::from_num(steps);
+            let mut prev = seg.start();
+            for i in 1..=steps {
+                let t = if i == steps { Scalar::ONE } else { step * Scalar::from_num(i) };
+                let p = seg.point_at(t);
+                let length = prev.distance(&p);
+                if length > Scalar::ZERO {
+                    travelled += length;
+                    pieces.push(MeasuredPiece { segment: index, t, step, end: travelled, length });
+                }
+                prev = p;
+            }
+        }
+        Self { segments, pieces }
+    }
+
+    pub fn length(&self) -> Scalar {
+        self.pieces.last().map_or(Scalar::ZERO, |p| p.end)
+    }
+
+    /// Point and tangent at `distance` along the path, clamped to its ends.
+    pub fn sample(&self, distance: Scalar) -> Option<PathSample> {
+        let first = self.segments.first()?;
+        if distance <= Scalar::ZERO {
+            return Some(PathSample { point: first.start(), tangent: first.tangent_at(Scalar::ZERO) });
+        }
+        let index = self.pieces.partition_point(|p| p.end < distance);
+        let Some(piece) = self.pieces.get(index) else {
+            let last = self.segments.last()?;
+            return Some(PathSample { point: last.end(), tangent: last.tangent_at(Scalar::ONE) });
+        };
+        let frac = (distance - (piece.end - piece.length)) / piece.length;
+        let t = piece.t - piece.step + piece.step * frac;
+        let seg = &self.segments[piece.segment];
+        Some(PathSample { point: seg.point_at(t), tangent: seg.tangent_at(t) })
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum BooleanOp {
     Union,
@@ -21,6 +255,12 @@ pub enum BooleanOp {
     Intersect,
 }
 
+impl Default for PathShape {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PathShape {
     pub fn new() -> Self {
         Self { commands: Vec::new() }
@@ -46,6 +286,24 @@ impl PathShape {
         self.commands.push(PathCommand::Close);
     }
 
+    pub fn translate(&mut self, dx: Scalar, dy: Scalar) {
+        for cmd in &mut self.commands {
+            match cmd {
+                PathCommand::MoveTo(pt) | PathCommand::LineTo(pt) => {
+                    pt.x += dx;
+                    pt.y += dy;
+                }
+                PathCommand::CurveTo(pt1, pt2, pt3) => {
+                    for pt in [pt1, pt2, pt3] {
+                        pt.x += dx;
+                        pt.y += dy;
+                    }
+                }
+                PathCommand::Close => {}
+            }
+        }
+    }
+
     pub fn combine(&mut self, other: &PathShape, op: BooleanOp) {
         // Deterministic Boolean Path Operations
         // Full implementation requires a library like 'clipper' or 'martinez'
@@ -97,4 +355,208 @@ impl PathShape {
             height: max_y - min_y,
         }
     }
+
+    /// Resolves the command list into drawable segments, including the implicit closing lines.
+    pub fn segments(&self) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        for sub in self.subpath_segments() {
+            segments.extend(sub.0);
+        }
+        segments
+    }
+
+    /// Segments grouped per subpath, each with its closed flag.
+    pub fn subpath_segments(&self) -> Vec<(Vec<Segment>, bool)> {
+        let mut result = Vec::new();
+        let mut current: Vec<Segment> = Vec::new();
+        let mut start: Option<Point> = None;
+        let mut cursor: Option<Point> = None;
+
+        for cmd in &self.commands {
+            match cmd {
+                PathCommand::MoveTo(p) => {
+                    if start.is_some() {
+                        result.push((std::mem::take(&mut current), false));
+                    }
+                    start = Some(*p);
+                    cursor = Some(*p);
+                }
+                PathCommand::LineTo(p) => {
+                    let from = cursor.unwrap_or(*p);
+                    if start.is_none() {
+                        start = Some(from);
+                    }
+                    current.push(Segment::Line(from, *p));
+                    cursor = Some(*p);
+                }
+                PathCommand::CurveTo(c1, c2, p) => {
+                    let from = cursor.unwrap_or(*c1);
+                    if start.is_none() {
+                        start = Some(from);
+                    }
+                    current.push(Segment::Cubic(from, *c1, *c2, *p));
+                    cursor = Some(*p);
+                }
+                PathCommand::Close => {
+                    if let (Some(s), Some(c)) = (start, cursor) {
+                        if s != c {
+                            current.push(Segment::Line(c, s));
+                        }
+                        result.push((std::mem::take(&mut current), true));
+                        // A subsequent draw command continues from the subpath start.
+                        cursor = Some(s);
+                        start = None;
+                    }
+                }
+            }
+        }
+        if start.is_some() {
+            result.push((current, false));
+        }
+        result
+    }
+
+    /// Flattens every subpath into a polyline that stays within `tolerance` of the curves.
+    pub fn flatten(&self, tolerance: Scalar) -> Vec<Polyline> {
+        let mut result = Vec::new();
+        for (segments, closed) in self.subpath_segments() {
+            let first = match segments.first() {
+                Some(seg) => seg.start(),
+                None => continue,
+            };
+            let mut points = vec![first];
+            for seg in &segments {
+                seg.flatten_into(tolerance, &mut points);
+            }
+            if closed && points.len() > 1 && points.first() == points.last() {
+                points.pop();
+            }
+            result.push(Polyline { points, closed });
+        }
+        result
+    }
+
+    /// Nonzero winding test against the flattened path. Open subpaths are
+    /// treated as implicitly closed, as they are when filled.
+    pub fn contains(&self, p: &Point) -> bool {
+        if !self.get_bounds().contains(p) {
+            return false;
+        }
+        let polygons: Vec<Vec<Point>> = self.flatten(DEFAULT_TOLERANCE).into_iter().map(|l| l.points).collect();
+        crate::core::polygon::winding_number(&polygons, p) != 0
+    }
+
+    /// Whether the filled path overlaps `rect`, with open subpaths closed as in `contains`.
+    pub fn intersects_rect(&self, rect: &Rect) -> bool {
+        if !self.get_bounds().intersects(rect) {
+            return false;
+        }
+        let polygons: Vec<Vec<Point>> = self.flatten(DEFAULT_TOLERANCE).into_iter().map(|l| l.points).collect();
+        crate::core::polygon::intersects_rect(&polygons, rect)
+    }
+
+    /// Arc length of each segment, in the order returned by `segments`.
+    pub fn segment_lengths(&self, tolerance: Scalar) -> Vec<Scalar> {
+        self.segments().iter().map(|s| s.length(tolerance)).collect()
+    }
+
+    pub fn length(&self, tolerance: Scalar) -> Scalar {
+        self.segment_lengths(tolerance).into_iter().sum()
+    }
+
+    /// Flattens the path once for sampling at many distances along it.
+    pub fn measure(&self, tolerance: Scalar) -> PathMeasure {
+        PathMeasure::new(self.segments(), tolerance)
+    }
+
+    /// Point and tangent at `distance` along the path, clamped to its ends.
+    pub fn sample_at_length(&self, distance: Scalar, tolerance: Scalar) -> Option<PathSample> {
+        self.measure(tolerance).sample(distance)
+    }
+
+    /// Point and tangent at a path parameter in `[0, segment count]`; the integer
+    /// part selects the segment and the fraction is the local `t`.
+    pub fn sample_at_t(&self, t: Scalar) -> Option<PathSample> {
+        let segments = self.segments();
+        if segments.is_empty() {
+            return None;
+        }
+        let t = t.clamp(Scalar::ZERO, Scalar::from_num(segments.len()));
+        let index = t.int().to_num::<usize>().min(segments.len() - 1);
+        let local = t - Scalar::from_num(index);
+        let seg = &segments[index];
+        Some(PathSample { point: seg.point_at(local), tangent: seg.tangent_at(local) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(v: f32) -> Scalar {
+        Scalar::from_num(v)
+    }
+
+    #[test]
+    fn test_square_length_and_sampling() {
+        let mut path = PathShape::new();
+        path.move_to(0.0, 0.0);
+        path.line_to(100.0, 0.0);
+        path.line_to(100.0, 100.0);
+        path.line_to(0.0, 100.0);
+        path.close();
+
+        assert_eq!(path.segments().len(), 4);
+        assert_eq!(path.length(DEFAULT_TOLERANCE), s(400.0));
+
+        let sample = path.sample_at_length(s(150.0), DEFAULT_TOLERANCE).unwrap();
+        assert_eq!(sample.point, Point::new(100.0, 50.0));
+        assert_eq!(sample.tangent, Vector::new(0.0, 1.0));
+
+        let sample = path.sample_at_t(s(2.5)).unwrap();
+        assert_eq!(sample.point, Point::new(50.0, 100.0));
+    }
+
+    #[test]
+    fn test_quarter_circle_arc_length() {
+        // Cubic approximation of a quarter circle with radius 100.
+        let k = 55.228475;
+        let mut path = PathShape::new();
+        path.move_to(100.0, 0.0);
+        path.cubic_to(100.0, k, k, 100.0, 0.0, 100.0);
+
+        let expected = std::f32::consts::PI * 50.0;
+        let len: f32 = path.length(s(0.01)).to_num();
+        assert!((len - expected).abs() < 0.1, "length {len} vs {expected}");
+
+        let flat = path.flatten(s(0.01));
+        assert_eq!(flat.len(), 1);
+        assert!(!flat[0].closed);
+        assert_eq!(flat[0].points.last(), Some(&Point::new(0.0, 100.0)));
+
+        let mid = path.sample_at_length(s(expected / 2.0), s(0.01)).unwrap();
+        let d = 100.0 * std::f32::consts::FRAC_1_SQRT_2;
+        assert!((mid.point.x.to_num::<f32>() - d).abs() < 0.5);
+        assert!((mid.point.y.to_num::<f32>() - d).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_measure_matches_segment_lookup() {
+        let mut path = PathShape::new();
+        path.move_to(0.0, 0.0);
+        path.cubic_to(50.0, -80.0, 120.0, 80.0, 200.0, 0.0);
+        path.line_to(200.0, 50.0);
+        let measure = path.measure(DEFAULT_TOLERANCE);
+        assert_eq!(measure.length(), path.length(DEFAULT_TOLERANCE));
+
+        let segments = path.segments();
+        let first = segments[0].length(DEFAULT_TOLERANCE);
+        for d in [0.0, 1.0, 37.5, 120.0, 199.0] {
+            let t = segments[0].t_at_length(s(d), DEFAULT_TOLERANCE);
+            assert_eq!(measure.sample(s(d)).unwrap().point, segments[0].point_at(t), "at {d}");
+        }
+        let t = segments[1].t_at_length(s(20.0), DEFAULT_TOLERANCE);
+        assert_eq!(measure.sample(first + s(20.0)).unwrap().point, segments[1].point_at(t));
+        assert_eq!(measure.sample(s(1e6)).unwrap().point, Point::new(200.0, 50.0));
+    }
 }
@@ -0,0 +1,584 @@
+use crate::core::geometry::{Point, Scalar, Vector};
+use crate::core::path::{PathCommand, PathShape, Segment, DEFAULT_TOLERANCE};
+use serde::{Serialize, Deserialize};
+
+/// Which bezier handle of an anchor is being edited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PathHandle {
+    In,
+    Out,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NodeType {
+    Corner,
+    Smooth,
+}
+
+/// One subpath in normalised form: a start anchor followed by `LineTo`/`CurveTo` commands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subpath {
+    pub start: Point,
+    pub segments: Vec<PathCommand>,
+    pub closed: bool,
+}
+
+fn command_end(cmd: &PathCommand) -> Option<Point> {
+    match cmd {
+        PathCommand::MoveTo(p) | PathCommand::LineTo(p) | PathCommand::CurveTo(_, _, p) => Some(*p),
+        PathCommand::Close => None,
+    }
+}
+
+fn third() -> Scalar {
+    Scalar::ONE / Scalar::from_num(3)
+}
+
+impl Subpath {
+    /// Anchor `k`, where anchor 0 is the start and anchor `k` ends segment `k - 1`.
+    pub fn anchor(&self, k: usize) -> Point {
+        if k == 0 {
+            self.start
+        } else {
+            command_end(&self.segments[k - 1]).unwrap_or(self.start)
+        }
+    }
+
+    pub fn end(&self) -> Point {
+        self.anchor(self.segments.len())
+    }
+
+    pub fn segment(&self, i: usize) -> Segment {
+        let from = self.anchor(i);
+        match self.segments[i] {
+            PathCommand::CurveTo(c1, c2, p) => Segment::Cubic(from, c1, c2, p),
+            PathCommand::LineTo(p) => Segment::Line(from, p),
+            _ => Segment::Line(from, from),
+        }
+    }
+
+    /// True when the last segment returns to the start, so anchors 0 and `len` coincide.
+    pub fn is_explicitly_closed(&self) -> bool {
+        self.closed && !self.segments.is_empty() && self.end() == self.start
+    }
+
+    pub fn make_closing_explicit(&mut self) {
+        if self.closed && self.end() != self.start {
+            self.segments.push(PathCommand::LineTo(self.start));
+        }
+    }
+
+    /// Segment whose second control point is the incoming handle of anchor `k`.
+    fn in_segment(&self, k: usize) -> Option<usize> {
+        if k > 0 {
+            Some(k - 1)
+        } else if self.is_explicitly_closed() {
+            Some(self.segments.len() - 1)
+        } else {
+            None
+        }
+    }
+
+    /// Segment whose first control point is the outgoing handle of anchor `k`.
+    fn out_segment(&self, k: usize) -> Option<usize> {
+        if k < self.segments.len() {
+            Some(k)
+        } else if self.is_explicitly_closed() {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn in_handle(&self, k: usize) -> Option<Point> {
+        match self.segments[self.in_segment(k)?] {
+            PathCommand::CurveTo(_, c2, _) => Some(c2),
+            _ => None,
+        }
+    }
+
+    fn out_handle(&self, k: usize) -> Option<Point> {
+        match self.segments[self.out_segment(k)?] {
+            PathCommand::CurveTo(c1, _, _) => Some(c1),
+            _ => None,
+        }
+    }
+
+    fn set_in_handle(&mut self, k: usize, to: Point) {
+        if let Some(i) = self.in_segment(k) {
+            if let PathCommand::CurveTo(_, c2, _) = &mut self.segments[i] {
+                *c2 = to;
+            }
+        }
+    }
+
+    fn set_out_handle(&mut self, k: usize, to: Point) {
+        if let Some(i) = self.out_segment(k) {
+            if let PathCommand::CurveTo(c1, _, _) = &mut self.segments[i] {
+                *c1 = to;
+            }
+        }
+    }
+
+    fn set_anchor(&mut self, k: usize, to: Point) {
+        let twin = self.is_explicitly_closed() && (k == 0 || k == self.segments.len());
+        if k == 0 || twin {
+            self.start = to;
+        }
+        if k > 0 || twin {
+            let last = if k == 0 { self.segments.len() } else { k };
+            match &mut self.segments[last - 1] {
+                PathCommand::LineTo(p) | PathCommand::CurveTo(_, _, p) => *p = to,
+                _ => {}
+            }
+        }
+    }
+
+    /// A node is smooth when both handles exist and point in opposite directions.
+    fn is_smooth(&self, k: usize) -> bool {
+        let anchor = self.anchor(k);
+        let (Some(h_in), Some(h_out)) = (self.in_handle(k), self.out_handle(k)) else {
+            return false;
+        };
+        let v_in = h_in.sub(&anchor);
+        let v_out = h_out.sub(&anchor);
+        if v_in.is_zero() || v_out.is_zero() {
+            return false;
+        }
+        let a = v_in.normalize();
+        let b = v_out.normalize();
+        a.cross(&b).abs() <= Scalar::lit("0.01") && a.dot(&b) < Scalar::ZERO
+    }
+
+    /// Reorders a closed subpath so that anchor `k` becomes its start.
+    fn rotate_to(&mut self, k: usize) {
+        self.make_closing_explicit();
+        let k = k % self.segments.len().max(1);
+        self.start = self.anchor(k);
+        self.segments.rotate_left(k);
+    }
+
+    pub fn reverse(&mut self) {
+        self.make_closing_explicit();
+        let n = self.segments.len();
+        let mut reversed = Vec::with_capacity(n);
+        for i in (0..n).rev() {
+            reversed.push(match self.segment(i) {
+                Segment::Line(p0, _) => PathCommand::LineTo(p0),
+                Segment::Cubic(p0, c1, c2, _) => PathCommand::CurveTo(c2, c1, p0),
+            });
+        }
+        self.start = self.end();
+        self.segments = reversed;
+        if self.closed && self.segments.last() == Some(&PathCommand::LineTo(self.start)) {
+            self.segments.pop();
+        }
+    }
+}
+
+/// Merges two consecutive segments into one cubic that follows both, by
+/// stretching the outer handles in proportion to the removed length.
+fn merge_segments(a: &Segment, b: &Segment) -> PathCommand {
+    if let (Segment::Line(_, _), Segment::Line(_, p2)) = (a, b) {
+        return PathCommand::LineTo(*p2);
+    }
+    let as_cubic = |s: &Segment| match *s {
+        Segment::Line(p0, p1) => (p0, p0.lerp(&p1, third()), p1.lerp(&p0, third()), p1),
+        Segment::Cubic(p0, c1, c2, p1) => (p0, c1, c2, p1),
+    };
+    let (p0, a1, _, _) = as_cubic(a);
+    let (_, _, b2, p3) = as_cubic(b);
+    let la = a.length(DEFAULT_TOLERANCE);
+    let lb = b.length(DEFAULT_TOLERANCE);
+    let total = la + lb;
+    let c1 = if la > Scalar::ZERO { p0.offset(&a1.sub(&p0), total / la) } else { a1 };
+    let c2 = if lb > Scalar::ZERO { p3.offset(&b2.sub(&p3), total / lb) } else { b2 };
+    PathCommand::CurveTo(c1, c2, p3)
+}
+
+impl PathShape {
+    /// Splits the command list into subpaths. Drawing commands that follow a
+    /// `Close` or start the path without a `MoveTo` begin a new subpath.
+    pub fn subpaths(&self) -> Vec<Subpath> {
+        let mut result: Vec<Subpath> = Vec::new();
+        let mut current: Option<Subpath> = None;
+        let mut last_start: Option<Point> = None;
+
+        for cmd in &self.commands {
+            match cmd {
+                PathCommand::MoveTo(p) => {
+                    result.extend(current.take());
+                    current = Some(Subpath { start: *p, segments: Vec::new(), closed: false });
+                }
+                PathCommand::LineTo(_) | PathCommand::CurveTo(..) => {
+                    let sub = current.get_or_insert_with(|| {
+                        let start = last_start.unwrap_or(match cmd {
+                            PathCommand::CurveTo(c1, _, _) => *c1,
+                            _ => command_end(cmd).unwrap(),
+                        });
+                        Subpath { start, segments: Vec::new(), closed: false }
+                    });
+                    sub.segments.push(*cmd);
+                }
+                PathCommand::Close => {
+                    if let Some(mut sub) = current.take() {
+                        sub.closed = true;
+                        last_start = Some(sub.start);
+                        result.push(sub);
+                    }
+                }
+            }
+        }
+        result.extend(current);
+        result
+    }
+
+    /// Replaces the commands with the given subpaths.
+    pub fn set_subpaths(&mut self, subpaths: &[Subpath]) {
+        self.commands.clear();
+        for sub in subpaths {
+            self.commands.push(PathCommand::MoveTo(sub.start));
+            self.commands.extend(sub.segments.iter().copied());
+            if sub.closed {
+                self.commands.push(PathCommand::Close);
+            }
+        }
+    }
+
+    /// Maps a command index to `(subpath, anchor)`. A `Close` command maps to
+    /// one past the last anchor, addressing the closing segment.
+    fn locate(subpaths: &[Subpath], index: usize) -> Option<(usize, usize)> {
+        let mut base = 0;
+        for (s, sub) in subpaths.iter().enumerate() {
+            let count = sub.segments.len() + 1 + usize::from(sub.closed);
+            if index < base + count {
+                return Some((s, index - base));
+            }
+            base += count;
+        }
+        None
+    }
+
+    /// Inserts an anchor at `t` on the segment drawn by command `index`
+    /// (or the closing segment when `index` is a `Close`). Returns the index of the new anchor.
+    pub fn insert_node(&mut self, index: usize, t: Scalar) -> Option<usize> {
+        let mut subs = self.subpaths();
+        let (s, k) = Self::locate(&subs, index)?;
+        let t = t.clamp(Scalar::ZERO, Scalar::ONE);
+        let sub = &mut subs[s];
+        let n = sub.segments.len();
+        if k == 0 || (k == n + 1 && sub.end() == sub.start) {
+            return None;
+        }
+        if k == n + 1 {
+            let mid = sub.end().lerp(&sub.start, t);
+            sub.segments.push(PathCommand::LineTo(mid));
+        } else {
+            let (a, b) = sub.segment(k - 1).split(t);
+            sub.segments[k - 1] = a.to_command();
+            sub.segments.insert(k, b.to_command());
+        }
+        self.set_subpaths(&subs);
+        Some(index)
+    }
+
+    /// Removes the anchor at command `index`, merging its neighbouring segments
+    /// into a single curve that keeps the overall shape.
+    pub fn delete_node(&mut self, index: usize) -> bool {
+        let mut subs = self.subpaths();
+        let Some((s, mut k)) = Self::locate(&subs, index) else {
+            return false;
+        };
+        let sub = &mut subs[s];
+        if k > sub.segments.len() {
+            return false;
+        }
+        if sub.closed && sub.segments.len() > 1 {
+            if sub.is_explicitly_closed() && k == sub.segments.len() {
+                k = 0;
+            }
+            if k == 0 {
+                // Move the start away from the deleted anchor so it sits between two segments.
+                sub.rotate_to(1);
+                k = sub.segments.len() - 1;
+            }
+        }
+
+        let n = sub.segments.len();
+        if k == 0 {
+            sub.start = sub.anchor(1.min(n));
+            if n > 0 {
+                sub.segments.remove(0);
+            }
+        } else if k == n {
+            sub.segments.pop();
+        } else {
+            let merged = merge_segments(&sub.segment(k - 1), &sub.segment(k));
+            sub.segments[k] = merged;
+            sub.segments.remove(k - 1);
+        }
+        if sub.closed && sub.segments.last() == Some(&PathCommand::LineTo(sub.start)) {
+            // `Close` already draws the line back to the start.
+            sub.segments.pop();
+        }
+        if sub.segments.is_empty() {
+            subs.remove(s);
+        }
+        self.set_subpaths(&subs);
+        true
+    }
+
+    /// Moves the anchor at command `index` together with its handles.
+    pub fn move_anchor(&mut self, index: usize, dx: Scalar, dy: Scalar) -> bool {
+        let mut subs = self.subpaths();
+        let Some((s, k)) = Self::locate(&subs, index) else {
+            return false;
+        };
+        let sub = &mut subs[s];
+        if k > sub.segments.len() {
+            return false;
+        }
+        let delta = Vector { x: dx, y: dy };
+        let anchor = sub.anchor(k);
+        if let Some(h) = sub.in_handle(k) {
+            sub.set_in_handle(k, h.offset(&delta, Scalar::ONE));
+        }
+        if let Some(h) = sub.out_handle(k) {
+            sub.set_out_handle(k, h.offset(&delta, Scalar::ONE));
+        }
+        sub.set_anchor(k, anchor.offset(&delta, Scalar::ONE));
+        self.set_subpaths(&subs);
+        true
+    }
+
+    /// Moves one handle of the anchor at command `index` to `to`. Smooth nodes
+    /// stay smooth: the opposite handle rotates to stay collinear, keeping its length.
+    pub fn move_handle(&mut self, index: usize, handle: PathHandle, to: Point) -> bool {
+        let mut subs = self.subpaths();
+        let Some((s, k)) = Self::locate(&subs, index) else {
+            return false;
+        };
+        let sub = &mut subs[s];
+        if k > sub.segments.len() {
+            return false;
+        }
+        let anchor = sub.anchor(k);
+        let smooth = sub.is_smooth(k);
+        let (current, opposite) = match handle {
+            PathHandle::In => (sub.in_handle(k), sub.out_handle(k)),
+            PathHandle::Out => (sub.out_handle(k), sub.in_handle(k)),
+        };
+        if current.is_none() {
+            return false;
+        }
+        let mirrored = match opposite {
+            Some(o) if smooth && to != anchor => {
+                let dir = to.sub(&anchor).normalize();
+                Some(anchor.offset(&dir, -o.distance(&anchor)))
+            }
+            _ => None,
+        };
+        match handle {
+            PathHandle::In => {
+                sub.set_in_handle(k, to);
+                if let Some(m) = mirrored { sub.set_out_handle(k, m); }
+            }
+            PathHandle::Out => {
+                sub.set_out_handle(k, to);
+                if let Some(m) = mirrored { sub.set_in_handle(k, m); }
+            }
+        }
+        self.set_subpaths(&subs);
+        true
+    }
+
+    /// Converts the anchor at command `index` to a corner (handles retracted)
+    /// or a smooth node (collinear handles along the neighbour direction).
+    pub fn set_node_type(&mut self, index: usize, node_type: NodeType) -> bool {
+        let mut subs = self.subpaths();
+        let Some((s, mut k)) = Self::locate(&subs, index) else {
+            return false;
+        };
+        let sub = &mut subs[s];
+        if k > sub.segments.len() {
+            return false;
+        }
+        let anchor = sub.anchor(k);
+        match node_type {
+            NodeType::Corner => {
+                sub.set_in_handle(k, anchor);
+                sub.set_out_handle(k, anchor);
+                for i in [sub.in_segment(k), sub.out_segment(k)].into_iter().flatten() {
+                    if let Segment::Cubic(p0, c1, c2, p3) = sub.segment(i) {
+                        if c1 == p0 && c2 == p3 {
+                            sub.segments[i] = PathCommand::LineTo(p3);
+                        }
+                    }
+                }
+            }
+            NodeType::Smooth => {
+                if sub.closed && (k == 0 || k == sub.segments.len()) {
+                    sub.make_closing_explicit();
+                    k = 0;
+                }
+                let (Some(i_in), Some(i_out)) = (sub.in_segment(k), sub.out_segment(k)) else {
+                    return false;
+                };
+                let prev = sub.segment(i_in).start();
+                let next = sub.segment(i_out).end();
+                let dir = next.sub(&prev).normalize();
+                if dir.is_zero() {
+                    return false;
+                }
+                let in_len = match sub.in_handle(k) {
+                    Some(h) if h != anchor => h.distance(&anchor),
+                    _ => prev.distance(&anchor) * third(),
+                };
+                let out_len = match sub.out_handle(k) {
+                    Some(h) if h != anchor => h.distance(&anchor),
+                    _ => next.distance(&anchor) * third(),
+                };
+                if let PathCommand::LineTo(p) = sub.segments[i_in] {
+                    sub.segments[i_in] = PathCommand::CurveTo(prev.lerp(&p, third()), p, p);
+                }
+                if let PathCommand::LineTo(p) = sub.segments[i_out] {
+                    sub.segments[i_out] = PathCommand::CurveTo(anchor, p.lerp(&anchor, third()), p);
+                }
+                sub.set_in_handle(k, anchor.offset(&dir, -in_len));
+                sub.set_out_handle(k, anchor.offset(&dir, out_len));
+            }
+        }
+        self.set_subpaths(&subs);
+        true
+    }
+
+    /// Joins the end of subpath `first` to the start of subpath `second` with a
+    /// straight segment. Joining a subpath with itself closes it.
+    pub fn join_subpaths(&mut self, first: usize, second: usize) -> bool {
+        let mut subs = self.subpaths();
+        if first >= subs.len() || second >= subs.len() || subs[first].closed || subs[second].closed {
+            return false;
+        }
+        if first == second {
+            subs[first].closed = true;
+        } else {
+            let tail = subs[second].clone();
+            let head = &mut subs[first];
+            if head.end() != tail.start {
+                head.segments.push(PathCommand::LineTo(tail.start));
+            }
+            head.segments.extend(tail.segments);
+            subs.remove(second);
+        }
+        self.set_subpaths(&subs);
+        true
+    }
+
+    /// Breaks the path at the anchor at command `index`. An open subpath becomes
+    /// two subpaths; a closed one is opened so it starts and ends at the anchor.
+    pub fn split_subpath(&mut self, index: usize) -> bool {
+        let mut subs = self.subpaths();
+        let Some((s, k)) = Self::locate(&subs, index) else {
+            return false;
+        };
+        let sub = &mut subs[s];
+        let n = sub.segments.len();
+        if k > n {
+            return false;
+        }
+        if sub.closed {
+            sub.rotate_to(k);
+            sub.closed = false;
+        } else {
+            if k == 0 || k == n {
+                return false;
+            }
+            let tail = Subpath { start: sub.anchor(k), segments: sub.segments.split_off(k), closed: false };
+            subs.insert(s + 1, tail);
+        }
+        self.set_subpaths(&subs);
+        true
+    }
+
+    /// Reverses the drawing direction of one subpath, or of every subpath when `subpath` is `None`.
+    pub fn reverse(&mut self, subpath: Option<usize>) -> bool {
+        let mut subs = self.subpaths();
+        match subpath {
+            Some(i) if i < subs.len() => subs[i].reverse(),
+            Some(_) => return false,
+            None => subs.iter_mut().for_each(Subpath::reverse),
+        }
+        self.set_subpaths(&subs);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_curve() -> PathShape {
+        let mut path = PathShape::new();
+        path.move_to(0.0, 0.0);
+        path.cubic_to(0.0, 50.0, 100.0, 50.0, 100.0, 0.0);
+        path
+    }
+
+    #[test]
+    fn test_insert_then_delete_preserves_shape() {
+        let mut path = open_curve();
+        let before = path.sample_at_t(Scalar::lit("0.25")).unwrap().point;
+
+        assert_eq!(path.insert_node(1, Scalar::lit("0.5")), Some(1));
+        assert_eq!(path.commands.len(), 3);
+        assert_eq!(path.sample_at_t(Scalar::ONE).unwrap().point, Point::new(50.0, 37.5));
+
+        assert!(path.delete_node(1));
+        assert_eq!(path.commands.len(), 2);
+        let after = path.sample_at_t(Scalar::lit("0.25")).unwrap().point;
+        assert!(before.distance(&after) < Scalar::ONE);
+    }
+
+    #[test]
+    fn test_smooth_handles_stay_collinear() {
+        let mut path = PathShape::new();
+        path.move_to(0.0, 0.0);
+        path.line_to(50.0, 50.0);
+        path.line_to(100.0, 0.0);
+
+        assert!(path.set_node_type(1, NodeType::Smooth));
+        assert!(path.move_handle(1, PathHandle::Out, Point::new(50.0, 80.0)));
+        match (path.commands[1], path.commands[2]) {
+            (PathCommand::CurveTo(_, c2, _), PathCommand::CurveTo(c1, _, _)) => {
+                assert_eq!(c1, Point::new(50.0, 80.0));
+                assert_eq!(c2.x, Scalar::from_num(50));
+                assert!(c2.y < Scalar::from_num(50));
+            }
+            other => panic!("expected curves, got {other:?}"),
+        }
+
+        assert!(path.set_node_type(1, NodeType::Corner));
+        assert!(matches!(path.commands[1], PathCommand::CurveTo(_, c2, p) if c2 == p));
+    }
+
+    #[test]
+    fn test_split_join_and_reverse() {
+        let mut path = PathShape::new();
+        path.move_to(0.0, 0.0);
+        path.line_to(100.0, 0.0);
+        path.line_to(100.0, 100.0);
+        path.close();
+
+        assert!(path.split_subpath(2));
+        assert_eq!(path.commands[0], PathCommand::MoveTo(Point::new(100.0, 100.0)));
+        assert!(!path.commands.contains(&PathCommand::Close));
+
+        assert!(path.join_subpaths(0, 0));
+        assert_eq!(path.commands.last(), Some(&PathCommand::Close));
+
+        assert!(path.reverse(None));
+        assert_eq!(path.commands[1], PathCommand::LineTo(Point::new(100.0, 0.0)));
+        let len: f32 = path.length(DEFAULT_TOLERANCE).to_num();
+        assert!((len - 341.42).abs() < 0.01);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::core::geometry::{Scalar, Point, Rect, Circle};
+use crate::core::geometry::{Rect, Circle};
 
 pub fn resolve_collision(moving: &mut Rect, obstacle: &Rect) -> bool {
     if !moving.intersects(obstacle) {
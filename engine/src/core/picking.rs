@@ -0,0 +1,164 @@
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+use crate::core::geometry::{Point, Rect, Scalar, Shape};
+use crate::core::polygon;
+use crate::core::state::EngineState;
+
+/// How `hit_test` filters and reports what lies under a point.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HitTestOptions {
+    /// Report every hit instead of only the topmost.
+    pub all: bool,
+    /// Report elements inside groups rather than the top-level group holding them.
+    pub deep: bool,
+    /// Also hit hidden elements and the contents of hidden groups.
+    pub include_hidden: bool,
+    /// Also hit locked elements and the contents of locked groups.
+    pub include_locked: bool,
+    /// Also hit masks, which are never painted, by their own geometry.
+    pub include_masks: bool,
+}
+
+/// Elements painted at `p`, bottom to top, picked from `candidates`, which
+/// must include every element whose bounds contain `p` (a spatial index
+/// query does). Only the topmost is kept unless `options.all` is set.
+pub fn hit_test(state: &EngineState, candidates: &[String], p: &Point, options: &HitTestOptions) -> Vec<String> {
+    let mut hits: Vec<String> = candidates
+        .iter()
+        .filter(|id| is_pickable(state, id, options.include_hidden, options.include_locked, options.include_masks) && state.element_contains(id, p))
+        .map(|id| if options.deep { id.clone() } else { state.ancestors(id).pop().unwrap_or_else(|| id.clone()) })
+        .collect();
+    let keys = state.paint_keys(&hits);
+    hits.sort_by(|a, b| keys[a].cmp(&keys[b]));
+    hits.dedup();
+    if !options.all {
+        hits = hits.pop().into_iter().collect();
+    }
+    hits
+}
+
+/// Groups are picked through their contents. Masks are never painted, so
+/// they and the contents of mask groups are only picked on request. Hiding
+/// or locking a group applies to everything in it.
+fn is_pickable(state: &EngineState, id: &str, include_hidden: bool, include_locked: bool, include_masks: bool) -> bool {
+    let Some(el) = state.elements.get(id) else {
+        return false;
+    };
+    if matches!(el.shape, Shape::Group(_)) {
+        return false;
+    }
+    std::iter::once(el)
+        .chain(state.ancestors(id).iter().filter_map(|a| state.elements.get(a)))
+        .all(|el| (include_hidden || el.visible) && (include_locked || !el.locked) && (include_masks || el.mask.is_none()))
+}
+
+/// Area swept out by a marquee selection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SelectionRegion {
+    /// A drag rectangle; negative sizes extend up or left from `x`, `y`.
+    Rect { x: f32, y: f32, width: f32, height: f32 },
+    /// A freehand outline, closed back to its first point.
+    Lasso { points: Vec<Point> },
+}
+
+impl SelectionRegion {
+    /// Bounds of the region, or `None` for a lasso too short to enclose anything.
+    pub fn bounds(&self) -> Option<Rect> {
+        match self {
+            SelectionRegion::Rect { x, y, width, height } => {
+                Some(Rect::new(x.min(x + width), y.min(y + height), width.abs(), height.abs()))
+            }
+            SelectionRegion::Lasso { points } if points.len() >= 3 => {
+                let first = Rect { origin: points[0], width: Scalar::ZERO, height: Scalar::ZERO };
+                Some(points.iter().fold(first, |r, p| r.union(&Rect { origin: *p, width: Scalar::ZERO, height: Scalar::ZERO })))
+            }
+            SelectionRegion::Lasso { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarqueeMode {
+    /// Select anything the region touches.
+    #[default]
+    Intersect,
+    /// Select only what lies wholly inside the region.
+    Contain,
+}
+
+/// How `marquee_select` matches elements against the region.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarqueeOptions {
+    pub mode: MarqueeMode,
+    /// Select elements inside groups rather than the top-level group holding them.
+    pub deep: bool,
+    pub include_hidden: bool,
+    pub include_locked: bool,
+    pub include_masks: bool,
+}
+
+/// Elements `region` selects, bottom to top, picked from `candidates`,
+/// which must include every element whose bounds meet the region's bounds.
+/// Without `options.deep`, a top-level group is selected when the region
+/// touches any of its contents, or in contain mode, holds all of them.
+pub fn marquee_select(state: &EngineState, candidates: &[String], region: &SelectionRegion, options: &MarqueeOptions) -> Vec<String> {
+    let Some(bounds) = region.bounds() else {
+        return Vec::new();
+    };
+    let pickable = |id: &str| is_pickable(state, id, options.include_hidden, options.include_locked, options.include_masks);
+    let selects = |id: &str| match options.mode {
+        MarqueeMode::Intersect => intersects(state, id, region, &bounds),
+        MarqueeMode::Contain => contained(state, id, region, &bounds),
+    };
+
+    let mut selected = HashSet::new();
+    let mut groups: HashMap<String, bool> = HashMap::new();
+    for id in candidates.iter().filter(|id| pickable(id) && selects(id)) {
+        let top = state.ancestors(id).pop().filter(|_| !options.deep);
+        let Some(group) = top else {
+            selected.insert(id.clone());
+            continue;
+        };
+        let whole = *groups.entry(group.clone()).or_insert_with(|| {
+            options.mode == MarqueeMode::Intersect
+                || state.descendants(&group).iter().filter(|d| pickable(d)).all(|d| selects(d))
+        });
+        if whole {
+            selected.insert(group);
+        }
+    }
+    let mut selected: Vec<String> = selected.into_iter().collect();
+    let keys = state.paint_keys(&selected);
+    selected.sort_by(|a, b| keys[a].cmp(&keys[b]));
+    selected
+}
+
+fn intersects(state: &EngineState, id: &str, region: &SelectionRegion, bounds: &Rect) -> bool {
+    match region {
+        SelectionRegion::Rect { .. } => state.element_intersects(id, bounds),
+        SelectionRegion::Lasso { points } => state.element_intersects_polygons(id, std::slice::from_ref(points)),
+    }
+}
+
+/// Either the element's own geometry or, where masks clip it, what is left
+/// visible must fall inside the region.
+fn contained(state: &EngineState, id: &str, region: &SelectionRegion, bounds: &Rect) -> bool {
+    let (Some(el), Some(clipped)) = (state.elements.get(id), state.clipped_bounds(id)) else {
+        return false;
+    };
+    let polygons = state.at_current_time(el).painted_polygons();
+    match region {
+        SelectionRegion::Rect { .. } => {
+            bounds.contains_rect(&clipped) || (!polygons.is_empty() && polygons.iter().flatten().all(|p| bounds.contains(p)))
+        }
+        SelectionRegion::Lasso { points } => {
+            let lasso = std::slice::from_ref(points);
+            polygon::polygons_within(&[clipped.corners().to_vec()], lasso)
+                || (!polygons.is_empty() && polygon::polygons_within(&polygons, lasso))
+        }
+    }
+}
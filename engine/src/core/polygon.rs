@@ -0,0 +1,380 @@
+use crate::core::geometry::{Point, Rect, Scalar, Vector};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Intersection points closer than this to an existing vertex snap onto it.
+const SNAP: Scalar = Scalar::lit("0.0002");
+
+type PointKey = (i64, i64);
+
+fn key(p: &Point) -> PointKey {
+    (p.x.to_bits(), p.y.to_bits())
+}
+
+/// Nonzero winding number of `p` with respect to closed polygons.
+pub fn winding_number(polygons: &[Vec<Point>], p: &Point) -> i32 {
+    let mut winding = 0;
+    for poly in polygons {
+        let n = poly.len();
+        for i in 0..n {
+            let a = poly[i];
+            let b = poly[(i + 1) % n];
+            let side = b.sub(&a).cross(&p.sub(&a));
+            if a.y <= p.y {
+                if b.y > p.y && side > Scalar::ZERO {
+                    winding += 1;
+                }
+            } else if b.y <= p.y && side < Scalar::ZERO {
+                winding -= 1;
+            }
+        }
+    }
+    winding
+}
+
+/// Whether `polygons`, filled with the nonzero rule, overlap `rect`.
+/// Touching counts, as it does for `Rect::intersects`.
+pub fn intersects_rect(polygons: &[Vec<Point>], rect: &Rect) -> bool {
+    let crosses = polygons.iter().any(|poly| {
+        let n = poly.len();
+        (0..n).any(|i| segment_intersects_rect(&poly[i], &poly[(i + 1) % n], rect))
+    });
+    // With no edge reaching it, the rect is either wholly inside the fill or wholly outside.
+    crosses || winding_number(polygons, &rect.origin) != 0
+}
+
+/// Liang-Barsky clip of the segment `a`-`b` against `rect`.
+fn segment_intersects_rect(a: &Point, b: &Point, rect: &Rect) -> bool {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let (mut t0, mut t1) = (Scalar::ZERO, Scalar::ONE);
+    let edges = [
+        (-dx, a.x - rect.origin.x),
+        (dx, rect.origin.x + rect.width - a.x),
+        (-dy, a.y - rect.origin.y),
+        (dy, rect.origin.y + rect.height - a.y),
+    ];
+    for (p, q) in edges {
+        if p == Scalar::ZERO {
+            if q < Scalar::ZERO {
+                return false;
+            }
+            continue;
+        }
+        let r = q / p;
+        if p < Scalar::ZERO {
+            if r > t1 {
+                return false;
+            }
+            t0 = t0.max(r);
+        } else {
+            if r < t0 {
+                return false;
+            }
+            t1 = t1.min(r);
+        }
+    }
+    true
+}
+
+/// Whether two sets of polygons, filled with the nonzero rule, overlap.
+/// Two-point polygons act as plain segments. Touching counts.
+pub fn polygons_intersect(a: &[Vec<Point>], b: &[Vec<Point>]) -> bool {
+    edges(a).any(|(p0, p1)| edges(b).any(|(q0, q1)| segments_cross(p0, p1, q0, q1, true)))
+        // With no edges meeting, each polygon lies wholly inside the other set or wholly outside.
+        || a.iter().filter_map(|poly| poly.first()).any(|p| winding_number(b, p) != 0)
+        || b.iter().filter_map(|poly| poly.first()).any(|p| winding_number(a, p) != 0)
+}
+
+/// Whether every point of `polygons` lies inside `region`. Points on the
+/// region's boundary may fall either way.
+pub fn polygons_within(polygons: &[Vec<Point>], region: &[Vec<Point>]) -> bool {
+    polygons.iter().flatten().all(|p| winding_number(region, p) != 0)
+        && !edges(polygons).any(|(p0, p1)| edges(region).any(|(q0, q1)| segments_cross(p0, p1, q0, q1, false)))
+}
+
+/// Edges of closed polygons; a two-point polygon yields its segment once.
+fn edges(polygons: &[Vec<Point>]) -> impl Iterator<Item = (&Point, &Point)> {
+    polygons.iter().flat_map(|poly| {
+        let n = poly.len();
+        let count = if n == 2 { 1 } else { n };
+        (0..count).map(move |i| (&poly[i], &poly[(i + 1) % n]))
+    })
+}
+
+/// Which side of the line `a -> b` the point `p` lies on.
+fn side(a: &Point, b: &Point, p: &Point) -> i32 {
+    let cross = b.sub(a).cross(&p.sub(a));
+    if cross > Scalar::ZERO { 1 } else if cross < Scalar::ZERO { -1 } else { 0 }
+}
+
+/// Whether segments `a0 -> a1` and `b0 -> b1` meet. Unless `touching`, only
+/// crossings through the interiors of both count.
+fn segments_cross(a0: &Point, a1: &Point, b0: &Point, b1: &Point, touching: bool) -> bool {
+    let (d1, d2) = (side(b0, b1, a0), side(b0, b1, a1));
+    let (d3, d4) = (side(a0, a1, b0), side(a0, a1, b1));
+    if !touching {
+        return d1 * d2 < 0 && d3 * d4 < 0;
+    }
+    if d1 == 0 && d2 == 0 && d3 == 0 && d4 == 0 {
+        return overlaps(a0, a1, b0, b1);
+    }
+    d1 * d2 <= 0 && d3 * d4 <= 0
+}
+
+/// Twice the signed area; positive when the interior lies to the left of the edges.
+pub fn signed_area(points: &[Point]) -> Scalar {
+    let n = points.len();
+    (0..n)
+        .map(|i| {
+            let a = points[i];
+            let b = points[(i + 1) % n];
+            a.x * b.y - b.x * a.y
+        })
+        .sum()
+}
+
+fn near(a: &Point, b: &Point) -> bool {
+    (a.x - b.x).abs() <= SNAP && (a.y - b.y).abs() <= SNAP
+}
+
+fn overlaps(a0: &Point, a1: &Point, b0: &Point, b1: &Point) -> bool {
+    a0.x.min(a1.x) <= b0.x.max(b1.x) && b0.x.min(b1.x) <= a0.x.max(a1.x) &&
+    a0.y.min(a1.y) <= b0.y.max(b1.y) && b0.y.min(b1.y) <= a0.y.max(a1.y)
+}
+
+/// Projection of `p` onto the direction of edge `a -> b`, used to order split points.
+fn along(a: &Point, b: &Point, p: &Point) -> Scalar {
+    p.sub(a).dot(&b.sub(a))
+}
+
+/// Point where segment `a0 -> a1` crosses `b0 -> b1`, snapped to nearby endpoints.
+fn intersection(a0: &Point, a1: &Point, b0: &Point, b1: &Point) -> Option<Point> {
+    let r = a1.sub(a0);
+    let s = b1.sub(b0);
+    let denom = r.cross(&s);
+    if denom == Scalar::ZERO {
+        return None;
+    }
+    let qp = b0.sub(a0);
+    let t_num = qp.cross(&s);
+    let u_num = qp.cross(&r);
+    let in_range = |num: Scalar| {
+        if denom > Scalar::ZERO { num >= Scalar::ZERO && num <= denom } else { num <= Scalar::ZERO && num >= denom }
+    };
+    if !in_range(t_num) || !in_range(u_num) {
+        return None;
+    }
+    // Multiply before dividing so long edges keep their precision.
+    let project = |base: Scalar, d: Scalar| {
+        d.checked_mul(t_num)
+            .and_then(|v| v.checked_div(denom))
+            .map_or_else(|| base + d * (t_num / denom), |v| base + v)
+    };
+    let p = Point { x: project(a0.x, r.x), y: project(a0.y, r.y) };
+    Some([*a0, *a1, *b0, *b1].into_iter().find(|e| near(e, &p)).unwrap_or(p))
+}
+
+/// Rebuilds the region `{ p : keep(winding_number(polygons, p)) }` as simple
+/// loops with the kept region on their left. Self-intersections and overlaps
+/// between polygons are resolved by splitting edges at every crossing and
+/// keeping the pieces that separate a kept region from a discarded one.
+pub fn resolve(polygons: &[Vec<Point>], keep: impl Fn(i32) -> bool) -> Vec<Vec<Point>> {
+    let mut edges: Vec<(Point, Point)> = Vec::new();
+    for poly in polygons {
+        let n = poly.len();
+        for i in 0..n {
+            let (a, b) = (poly[i], poly[(i + 1) % n]);
+            if a != b {
+                edges.push((a, b));
+            }
+        }
+    }
+
+    let mut splits: Vec<Vec<Point>> = edges.iter().map(|(a, b)| vec![*a, *b]).collect();
+    for i in 0..edges.len() {
+        let (a0, a1) = edges[i];
+        for j in i + 1..edges.len() {
+            let (b0, b1) = edges[j];
+            if !overlaps(&a0, &a1, &b0, &b1) {
+                continue;
+            }
+            // Distinct lines meet at most once, so edges sharing a vertex only
+            // need the collinear check; near-parallel neighbours are ill-conditioned.
+            let shared = a0 == b0 || a0 == b1 || a1 == b0 || a1 == b1;
+            let crossing = if shared { None } else { intersection(&a0, &a1, &b0, &b1) };
+            if let Some(p) = crossing {
+                splits[i].push(p);
+                splits[j].push(p);
+            } else if a1.sub(&a0).cross(&b0.sub(&a0)) == Scalar::ZERO {
+                // Collinear overlap: each edge is split at the other's endpoints.
+                for (target, (p0, p1), others) in [(i, (a0, a1), [b0, b1]), (j, (b0, b1), [a0, a1])] {
+                    let len = along(&p0, &p1, &p1);
+                    for o in others {
+                        let t = along(&p0, &p1, &o);
+                        if t > Scalar::ZERO && t < len && p1.sub(&p0).cross(&o.sub(&p0)) == Scalar::ZERO {
+                            splits[target].push(o);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut pieces: BTreeSet<(PointKey, PointKey)> = BTreeSet::new();
+    let mut unique: Vec<(Point, Point)> = Vec::new();
+    for (i, (a, b)) in edges.iter().enumerate() {
+        let pts = &mut splits[i];
+        pts.sort_by_key(|p| along(a, b, p));
+        pts.dedup();
+        for w in pts.windows(2) {
+            let (ka, kb) = (key(&w[0]), key(&w[1]));
+            if ka != kb && pieces.insert((ka.min(kb), ka.max(kb))) {
+                unique.push((w[0], w[1]));
+            }
+        }
+    }
+
+    let mut boundary: Vec<(Point, Point)> = Vec::new();
+    for (a, b) in unique {
+        let d = b.sub(&a);
+        let len = d.length();
+        if len == Scalar::ZERO {
+            continue;
+        }
+        let eps = (len / Scalar::from_num(4)).min(Scalar::lit("0.03125"));
+        let n = Vector { x: -d.y, y: d.x }.normalize();
+        let mid = a.lerp(&b, Scalar::lit("0.5"));
+        let left = keep(winding_number(polygons, &mid.offset(&n, eps)));
+        let right = keep(winding_number(polygons, &mid.offset(&n, -eps)));
+        if left && !right {
+            boundary.push((a, b));
+        } else if right && !left {
+            boundary.push((b, a));
+        }
+    }
+
+    let mut outgoing: BTreeMap<PointKey, Vec<usize>> = BTreeMap::new();
+    for (i, (a, _)) in boundary.iter().enumerate() {
+        outgoing.entry(key(a)).or_default().push(i);
+    }
+    let mut used = vec![false; boundary.len()];
+    let mut loops = Vec::new();
+    for first in 0..boundary.len() {
+        if used[first] {
+            continue;
+        }
+        let start = boundary[first].0;
+        let mut ring = vec![start];
+        let mut current = first;
+        let closed = loop {
+            used[current] = true;
+            let end = boundary[current].1;
+            if end == start {
+                break true;
+            }
+            ring.push(end);
+            let next = outgoing.get(&key(&end)).and_then(|c| c.iter().copied().find(|e| !used[*e]));
+            match next {
+                Some(e) => current = e,
+                None => break false,
+            }
+        };
+        if closed {
+            let ring = remove_collinear(ring);
+            if ring.len() >= 3 {
+                loops.push(ring);
+            }
+        }
+    }
+    loops
+}
+
+fn remove_collinear(mut ring: Vec<Point>) -> Vec<Point> {
+    let mut i = 0;
+    while ring.len() >= 3 && i < ring.len() {
+        let n = ring.len();
+        let prev = ring[(i + n - 1) % n];
+        let next = ring[(i + 1) % n];
+        let d0 = ring[i].sub(&prev);
+        let d1 = next.sub(&ring[i]);
+        if d0.cross(&d1) == Scalar::ZERO && d0.dot(&d1) >= Scalar::ZERO {
+            ring.remove(i);
+            i = i.saturating_sub(1);
+        } else {
+            i += 1;
+        }
+    }
+    ring
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f32, y: f32, size: f32) -> Vec<Point> {
+        vec![Point::new(x, y), Point::new(x + size, y), Point::new(x + size, y + size), Point::new(x, y + size)]
+    }
+
+    #[test]
+    fn test_intersects_rect() {
+        let triangle = vec![vec![Point::new(0.0, 0.0), Point::new(100.0, 0.0), Point::new(0.0, 100.0)]];
+        assert!(intersects_rect(&triangle, &Rect::new(10.0, 10.0, 5.0, 5.0)));
+        assert!(intersects_rect(&triangle, &Rect::new(40.0, 40.0, 30.0, 30.0)));
+        assert!(intersects_rect(&triangle, &Rect::new(-10.0, -10.0, 200.0, 200.0)));
+        // Inside the bounding box, past the diagonal.
+        assert!(!intersects_rect(&triangle, &Rect::new(60.0, 60.0, 30.0, 30.0)));
+
+        let mut hole = square(25.0, 25.0, 50.0);
+        hole.reverse();
+        let ring = vec![square(0.0, 0.0, 100.0), hole];
+        assert!(!intersects_rect(&ring, &Rect::new(40.0, 40.0, 20.0, 20.0)));
+        assert!(intersects_rect(&ring, &Rect::new(10.0, 40.0, 20.0, 20.0)));
+    }
+
+    #[test]
+    fn test_polygons_intersect_and_within() {
+        let lasso = vec![vec![Point::new(0.0, 0.0), Point::new(100.0, 0.0), Point::new(0.0, 100.0)]];
+        assert!(polygons_within(&[square(10.0, 10.0, 20.0)], &lasso));
+        assert!(!polygons_within(&[square(40.0, 40.0, 20.0)], &lasso));
+        assert!(polygons_intersect(&[square(40.0, 40.0, 20.0)], &lasso));
+        assert!(!polygons_intersect(&[square(60.0, 60.0, 20.0)], &lasso));
+        // One polygon wholly inside the other, with no edges crossing.
+        assert!(polygons_intersect(&lasso, &[square(10.0, 10.0, 5.0)]));
+
+        let segment = vec![vec![Point::new(-10.0, 50.0), Point::new(10.0, 50.0)]];
+        assert!(polygons_intersect(&segment, &lasso));
+        assert!(!polygons_within(&segment, &lasso));
+
+        // Both vertices inside a concave region, but the edge leaves it.
+        let notch = vec![vec![
+            Point::new(0.0, 0.0), Point::new(100.0, 0.0), Point::new(100.0, 100.0),
+            Point::new(50.0, 10.0), Point::new(0.0, 100.0),
+        ]];
+        let across = vec![vec![Point::new(10.0, 50.0), Point::new(90.0, 50.0)]];
+        assert!(!polygons_within(&across, &notch));
+    }
+
+    #[test]
+    fn test_union_of_overlapping_squares() {
+        let polys = vec![square(0.0, 0.0, 10.0), square(5.0, 5.0, 10.0)];
+        let loops = resolve(&polys, |w| w != 0);
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].len(), 8);
+        assert_eq!(signed_area(&loops[0]), Scalar::from_num(2 * 175));
+    }
+
+    #[test]
+    fn test_bowtie_keeps_positive_lobe() {
+        let bowtie = vec![Point::new(0.0, 0.0), Point::new(10.0, 10.0), Point::new(10.0, 0.0), Point::new(0.0, 10.0)];
+        let polys = vec![bowtie];
+        let positive = resolve(&polys, |w| w > 0);
+        let negative = resolve(&polys, |w| w < 0);
+        assert_eq!(positive.len(), 1);
+        assert_eq!(negative.len(), 1);
+        assert!(signed_area(&positive[0]) > Scalar::ZERO);
+        assert!(signed_area(&negative[0]) > Scalar::ZERO);
+
+        let (l, r) = (Point::new(2.0, 5.0), Point::new(8.0, 5.0));
+        assert_ne!(winding_number(&positive, &l), winding_number(&positive, &r));
+        assert_eq!(winding_number(&positive, &l), winding_number(&negative, &r));
+    }
+}
@@ -0,0 +1,300 @@
+use crate::core::geometry::{Point, Rect, Scalar};
+use crate::core::spatial::{self, Opened, SpatialIndex};
+
+/// An R-tree packed with Sort-Tile-Recursive bulk loading. Nodes are sized
+/// by their contents rather than by a fixed grid, so a few huge elements
+/// among many tiny ones do not drag every query through the huge ones'
+/// cells. Incremental inserts pick the child needing least enlargement and
+/// split overfull nodes along their longer side; removals never rebalance,
+/// so after heavy editing a `load` repacks the tree.
+#[derive(Debug, Clone)]
+pub struct RTree {
+    root: Node,
+    max_entries: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    bounds: Rect,
+    leaf: bool,
+    /// Elements, in leaves only.
+    entries: Vec<(String, Rect)>,
+    /// Subtrees, in branches only.
+    children: Vec<Node>,
+}
+
+impl RTree {
+    /// `max_entries` caps the elements per leaf and subtrees per branch.
+    pub fn new(max_entries: usize) -> Self {
+        Self { root: Node::leaf(Vec::new()), max_entries: max_entries.max(2) }
+    }
+
+    /// Levels from the root down to the leaves.
+    pub fn height(&self) -> usize {
+        let mut height = 1;
+        let mut node = &self.root;
+        while let Some(child) = node.children.first() {
+            height += 1;
+            node = child;
+        }
+        height
+    }
+}
+
+impl SpatialIndex for RTree {
+    fn insert(&mut self, id: &str, bounds: &Rect) {
+        if let Some(sibling) = self.root.insert((id.to_string(), *bounds), self.max_entries) {
+            let old = std::mem::replace(&mut self.root, Node::leaf(Vec::new()));
+            self.root = Node::branch(vec![old, sibling]);
+        }
+    }
+
+    fn remove(&mut self, id: &str, bounds: &Rect) -> bool {
+        let removed = self.root.remove(id, bounds);
+        // Shed roots left with a single subtree.
+        while !self.root.leaf && self.root.children.len() <= 1 {
+            self.root = self.root.children.pop().unwrap_or_else(|| Node::leaf(Vec::new()));
+        }
+        removed
+    }
+
+    fn query(&self, range: &Rect, found: &mut Vec<String>) {
+        let mut hits = Vec::new();
+        self.root.query(range, &mut hits);
+        spatial::extend_unique(found, hits);
+    }
+
+    fn nearest(&self, p: &Point, k: usize) -> Vec<(String, Scalar)> {
+        spatial::by_distance(&self.root, p, k, Scalar::MAX, Node::open)
+    }
+
+    fn within_radius(&self, p: &Point, radius: Scalar) -> Vec<(String, Scalar)> {
+        spatial::by_distance(&self.root, p, usize::MAX, radius, Node::open)
+    }
+
+    fn clear(&mut self) {
+        self.root = Node::leaf(Vec::new());
+    }
+
+    fn load(&mut self, entries: Vec<(String, Rect)>) {
+        let mut level: Vec<Node> = pack(entries, |(_, b)| *b, self.max_entries).into_iter().map(Node::leaf).collect();
+        while level.len() > self.max_entries {
+            level = pack(level, |n| n.bounds, self.max_entries).into_iter().map(Node::branch).collect();
+        }
+        self.root = match level.len() {
+            0 => Node::leaf(Vec::new()),
+            1 => level.pop().unwrap(),
+            _ => Node::branch(level),
+        };
+    }
+}
+
+/// Sort-Tile-Recursive grouping: sorts by centre x into vertical slices,
+/// then each slice by centre y into runs of at most `max` items.
+fn pack<T>(mut items: Vec<T>, bounds: impl Fn(&T) -> Rect, max: usize) -> Vec<Vec<T>> {
+    let groups = items.len().div_ceil(max);
+    let mut slices = groups.isqrt();
+    if slices * slices < groups {
+        slices += 1;
+    }
+    let slice_len = (slices * max).max(1);
+    let centre_x = |r: Rect| r.origin.x + r.width / Scalar::from_num(2);
+    let centre_y = |r: Rect| r.origin.y + r.height / Scalar::from_num(2);
+
+    items.sort_by_key(|item| centre_x(bounds(item)));
+    let mut packed = Vec::with_capacity(groups);
+    let mut items = items.into_iter().peekable();
+    while items.peek().is_some() {
+        let mut slice: Vec<T> = items.by_ref().take(slice_len).collect();
+        slice.sort_by_key(|item| centre_y(bounds(item)));
+        let mut slice = slice.into_iter().peekable();
+        while slice.peek().is_some() {
+            packed.push(slice.by_ref().take(max).collect());
+        }
+    }
+    packed
+}
+
+fn area(r: &Rect) -> Scalar {
+    r.width.saturating_mul(r.height)
+}
+
+impl Node {
+    fn leaf(entries: Vec<(String, Rect)>) -> Self {
+        let mut node = Node { bounds: Rect::new(0.0, 0.0, 0.0, 0.0), leaf: true, entries, children: Vec::new() };
+        node.refresh();
+        node
+    }
+
+    fn branch(children: Vec<Node>) -> Self {
+        let mut node = Node { bounds: Rect::new(0.0, 0.0, 0.0, 0.0), leaf: false, entries: Vec::new(), children };
+        node.refresh();
+        node
+    }
+
+    fn len(&self) -> usize {
+        if self.leaf { self.entries.len() } else { self.children.len() }
+    }
+
+    /// Recomputes the bounds from the contents. Empty nodes keep theirs.
+    fn refresh(&mut self) {
+        let mut all = self.entries.iter().map(|(_, b)| *b).chain(self.children.iter().map(|c| c.bounds));
+        if let Some(first) = all.next() {
+            self.bounds = all.fold(first, |acc, b| acc.union(&b));
+        }
+    }
+
+    /// Adds the entry below this node, returning the new sibling if the
+    /// node had to split.
+    fn insert(&mut self, entry: (String, Rect), max: usize) -> Option<Node> {
+        let bounds = entry.1;
+        self.bounds = if self.len() == 0 { bounds } else { self.bounds.union(&bounds) };
+        if self.leaf {
+            self.entries.push(entry);
+        } else {
+            let growth = |c: &Node| (area(&c.bounds.union(&bounds)) - area(&c.bounds), area(&c.bounds));
+            let best = (0..self.children.len()).min_by_key(|i| growth(&self.children[*i])).unwrap();
+            if let Some(split) = self.children[best].insert(entry, max) {
+                self.children.push(split);
+            }
+        }
+        (self.len() > max).then(|| self.split())
+    }
+
+    /// Moves the far half of the contents, along the longer side, into a new node.
+    fn split(&mut self) -> Node {
+        let wide = self.bounds.width >= self.bounds.height;
+        let key = |r: &Rect| if wide { r.origin.x + r.width / Scalar::from_num(2) } else { r.origin.y + r.height / Scalar::from_num(2) };
+        let sibling = if self.leaf {
+            self.entries.sort_by_key(|(_, b)| key(b));
+            Node::leaf(self.entries.split_off(self.entries.len() / 2))
+        } else {
+            self.children.sort_by_key(|c| key(&c.bounds));
+            Node::branch(self.children.split_off(self.children.len() / 2))
+        };
+        self.refresh();
+        sibling
+    }
+
+    fn remove(&mut self, id: &str, bounds: &Rect) -> bool {
+        let removed = if self.leaf {
+            let index = self.entries.iter().position(|(e, _)| e == id);
+            index.map(|i| self.entries.remove(i)).is_some()
+        } else {
+            let index = (0..self.children.len())
+                .find(|i| self.children[*i].bounds.contains_rect(bounds) && self.children[*i].remove(id, bounds));
+            if let Some(i) = index.filter(|i| self.children[*i].len() == 0) {
+                self.children.remove(i);
+            }
+            index.is_some()
+        };
+        if removed {
+            self.refresh();
+        }
+        removed
+    }
+
+    fn query(&self, range: &Rect, found: &mut Vec<String>) {
+        for (id, bounds) in &self.entries {
+            if bounds.intersects(range) {
+                found.push(id.clone());
+            }
+        }
+        for child in &self.children {
+            if child.bounds.intersects(range) {
+                child.query(range, found);
+            }
+        }
+    }
+
+    fn open(&self) -> Opened<'_, Node> {
+        (&self.entries, self.children.iter().map(|c| (c.bounds, c)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::spatial::tests::{brute_force, near_or_far_rect, rect, sorted_by_distance, sorted_query};
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_bulk_load_and_edit() {
+        let mut tree = RTree::new(4);
+        let mut entries: Vec<(String, Rect)> = (0..100)
+            .map(|i| (format!("icon{:02}", i), Rect::new((i % 10) as f32 * 20.0, (i / 10) as f32 * 20.0, 4.0, 4.0)))
+            .collect();
+        entries.push(("background".to_string(), Rect::new(-1000.0, -1000.0, 4000.0, 4000.0)));
+        tree.load(entries);
+        // 101 entries in leaves of 4 need three levels of branches.
+        assert_eq!(tree.height(), 4);
+
+        let mut found = Vec::new();
+        tree.query(&Rect::new(41.0, 41.0, 1.0, 1.0), &mut found);
+        found.sort();
+        assert_eq!(found, vec!["background", "icon22"]);
+
+        assert!(tree.remove("icon22", &Rect::new(40.0, 40.0, 4.0, 4.0)));
+        assert!(!tree.remove("icon22", &Rect::new(40.0, 40.0, 4.0, 4.0)));
+        tree.insert("icon22", &Rect::new(500.0, 500.0, 4.0, 4.0));
+        assert_eq!(tree.nearest(&Point::new(502.0, 490.0), 2), vec![
+            ("background".to_string(), Scalar::ZERO),
+            ("icon22".to_string(), Scalar::from_num(10)),
+        ]);
+
+        for i in 0..100 {
+            let id = format!("icon{:02}", i);
+            let bounds = if i == 22 { Rect::new(500.0, 500.0, 4.0, 4.0) } else { Rect::new((i % 10) as f32 * 20.0, (i / 10) as f32 * 20.0, 4.0, 4.0) };
+            assert!(tree.remove(&id, &bounds));
+        }
+        assert_eq!(tree.height(), 1);
+        assert_eq!(tree.within_radius(&Point::new(0.0, 0.0), Scalar::ONE), vec![("background".to_string(), Scalar::ZERO)]);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_edits_match_brute_force(
+            rects in prop::collection::vec(near_or_far_rect(), 0..80),
+            removed in prop::collection::vec(any::<bool>(), 80),
+            moved in prop::collection::vec(rect(), 80),
+            ranges in prop::collection::vec(rect(), 1..10),
+            max_entries in 2usize..6,
+            bulk in any::<bool>(),
+        ) {
+            let elements: Vec<(String, Rect)> = rects.iter().enumerate().map(|(i, b)| (format!("e{}", i), *b)).collect();
+            let mut tree = RTree::new(max_entries);
+            if bulk {
+                tree.load(elements.clone());
+            } else {
+                for (id, bounds) in &elements {
+                    tree.insert(id, bounds);
+                }
+            }
+
+            // Remove some elements and move every third survivor, so later
+            // queries run against nodes that shrank and split after loading.
+            let mut kept = Vec::new();
+            for (i, ((id, bounds), remove)) in elements.iter().zip(&removed).enumerate() {
+                if *remove {
+                    prop_assert!(tree.remove(id, bounds));
+                    prop_assert!(!tree.remove(id, bounds));
+                } else if i % 3 == 0 {
+                    prop_assert!(tree.remove(id, bounds));
+                    tree.insert(id, &moved[i]);
+                    kept.push((id.clone(), moved[i]));
+                } else {
+                    kept.push((id.clone(), *bounds));
+                }
+            }
+
+            for range in &ranges {
+                prop_assert_eq!(sorted_query(&tree, range), brute_force(&kept, range));
+                let p = range.origin;
+                let expected = sorted_by_distance(&kept, &p);
+                prop_assert_eq!(tree.nearest(&p, max_entries * 3), expected.iter().take(max_entries * 3).cloned().collect::<Vec<_>>());
+                let radius = range.width;
+                prop_assert_eq!(tree.within_radius(&p, radius), expected.iter().filter(|(_, d)| *d <= radius).cloned().collect::<Vec<_>>());
+            }
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use crate::core::geometry::{Point, Scalar, Vector};
+use crate::core::path::{PathCommand, PathShape, Polyline, DEFAULT_TOLERANCE};
+
+/// Newton-Raphson passes tried before a poorly fitting curve is split.
+const MAX_REPARAMETERIZE: usize = 4;
+
+/// Ramer–Douglas–Peucker simplification. Keeps the first and last points and
+/// every point that deviates more than `tolerance` from the simplified line.
+pub fn simplify_polyline(points: &[Point], tolerance: Scalar) -> Vec<Point> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    // Explicit stack instead of recursion: pencil strokes can hold thousands of points.
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let mut max_dist = Scalar::ZERO;
+        let mut index = first;
+        for (i, p) in points.iter().enumerate().take(last).skip(first + 1) {
+            let d = p.distance_to_segment(&points[first], &points[last]);
+            if d > max_dist {
+                max_dist = d;
+                index = i;
+            }
+        }
+        if max_dist > tolerance {
+            keep[index] = true;
+            stack.push((first, index));
+            stack.push((index, last));
+        }
+    }
+
+    points.iter().zip(keep).filter(|(_, k)| *k).map(|(p, _)| *p).collect()
+}
+
+/// Fits a chain of cubic beziers through `points` (Schneider, "An Algorithm for
+/// Automatically Fitting Digitized Curves", Graphics Gems 1990). Returns the
+/// `CurveTo` commands that follow a `MoveTo` at the first point.
+pub fn fit_cubics(points: &[Point], tolerance: Scalar) -> Vec<PathCommand> {
+    let mut pts: Vec<Point> = Vec::with_capacity(points.len());
+    for p in points {
+        if pts.last() != Some(p) {
+            pts.push(*p);
+        }
+    }
+    let mut out = Vec::new();
+    if pts.len() < 2 {
+        return out;
+    }
+    let n = pts.len();
+    let closed = n > 2 && pts[0] == pts[n - 1];
+    let (t_start, t_end) = if closed {
+        let seam = pts[1].sub(&pts[n - 2]).normalize();
+        (seam, seam.scale(-Scalar::ONE))
+    } else {
+        (pts[1].sub(&pts[0]).normalize(), pts[n - 2].sub(&pts[n - 1]).normalize())
+    };
+    fit_cubic(&pts, t_start, t_end, tolerance * tolerance, &mut out);
+    out
+}
+
+fn fit_cubic(d: &[Point], t_hat1: Vector, t_hat2: Vector, error_sq: Scalar, out: &mut Vec<PathCommand>) {
+    let first = d[0];
+    let last = d[d.len() - 1];
+    if d.len() == 2 {
+        let dist = first.distance(&last) / Scalar::from_num(3);
+        out.push(PathCommand::CurveTo(first.offset(&t_hat1, dist), last.offset(&t_hat2, dist), last));
+        return;
+    }
+
+    let mut u = chord_length_parameterize(d);
+    let mut bez = generate_bezier(d, &u, t_hat1, t_hat2);
+    let (mut max_error, mut split) = compute_max_error(d, &bez, &u);
+    if max_error < error_sq {
+        out.push(PathCommand::CurveTo(bez[1], bez[2], bez[3]));
+        return;
+    }
+
+    // Close fits are often fixed by reparameterizing rather than splitting.
+    if max_error < error_sq * Scalar::from_num(4) {
+        for _ in 0..MAX_REPARAMETERIZE {
+            u = reparameterize(d, &u, &bez);
+            bez = generate_bezier(d, &u, t_hat1, t_hat2);
+            (max_error, split) = compute_max_error(d, &bez, &u);
+            if max_error < error_sq {
+                out.push(PathCommand::CurveTo(bez[1], bez[2], bez[3]));
+                return;
+            }
+        }
+    }
+
+    let center = d[split - 1].sub(&d[split + 1]).normalize();
+    fit_cubic(&d[..=split], t_hat1, center, error_sq, out);
+    fit_cubic(&d[split..], center.scale(-Scalar::ONE), t_hat2, error_sq, out);
+}
+
+fn chord_length_parameterize(d: &[Point]) -> Vec<Scalar> {
+    let mut u = Vec::with_capacity(d.len());
+    let mut total = Scalar::ZERO;
+    u.push(total);
+    for w in d.windows(2) {
+        total += w[0].distance(&w[1]);
+        u.push(total);
+    }
+    if total > Scalar::ZERO {
+        for v in &mut u {
+            *v /= total;
+        }
+    }
+    u
+}
+
+fn bernstein(t: Scalar) -> [Scalar; 4] {
+    let mt = Scalar::ONE - t;
+    [mt * mt * mt, Scalar::from_num(3) * t * mt * mt, Scalar::from_num(3) * t * t * mt, t * t * t]
+}
+
+fn bezier_point(bez: &[Point; 4], t: Scalar) -> Point {
+    let b = bernstein(t);
+    Point {
+        x: bez[0].x * b[0] + bez[1].x * b[1] + bez[2].x * b[2] + bez[3].x * b[3],
+        y: bez[0].y * b[0] + bez[1].y * b[1] + bez[2].y * b[2] + bez[3].y * b[3],
+    }
+}
+
+/// Least-squares placement of the two inner control points along the given end tangents.
+fn generate_bezier(d: &[Point], u: &[Scalar], t_hat1: Vector, t_hat2: Vector) -> [Point; 4] {
+    let first = d[0];
+    let last = d[d.len() - 1];
+    let mut c = [[Scalar::ZERO; 2]; 2];
+    let mut x = [Scalar::ZERO; 2];
+
+    for (p, t) in d.iter().zip(u) {
+        let b = bernstein(*t);
+        let a0 = t_hat1.scale(b[1]);
+        let a1 = t_hat2.scale(b[2]);
+        c[0][0] += a0.dot(&a0);
+        c[0][1] += a0.dot(&a1);
+        c[1][1] += a1.dot(&a1);
+        let on_chord = Point {
+            x: first.x * (b[0] + b[1]) + last.x * (b[2] + b[3]),
+            y: first.y * (b[0] + b[1]) + last.y * (b[2] + b[3]),
+        };
+        let tmp = p.sub(&on_chord);
+        x[0] += a0.dot(&tmp);
+        x[1] += a1.dot(&tmp);
+    }
+    c[1][0] = c[0][1];
+
+    let det_c0_c1 = c[0][0] * c[1][1] - c[1][0] * c[0][1];
+    let det_c0_x = c[0][0] * x[1] - c[1][0] * x[0];
+    let det_x_c1 = x[0] * c[1][1] - x[1] * c[0][1];
+    let alpha_l = det_x_c1.checked_div(det_c0_c1).unwrap_or(Scalar::ZERO);
+    let alpha_r = det_c0_x.checked_div(det_c0_c1).unwrap_or(Scalar::ZERO);
+
+    // Degenerate solutions fall back to the Wu/Barsky heuristic of a third of the chord.
+    let seg_length = first.distance(&last);
+    let epsilon = seg_length / Scalar::from_num(1000);
+    if alpha_l < epsilon || alpha_r < epsilon {
+        let dist = seg_length / Scalar::from_num(3);
+        return [first, first.offset(&t_hat1, dist), last.offset(&t_hat2, dist), last];
+    }
+    [first, first.offset(&t_hat1, alpha_l), last.offset(&t_hat2, alpha_r), last]
+}
+
+fn compute_max_error(d: &[Point], bez: &[Point; 4], u: &[Scalar]) -> (Scalar, usize) {
+    let mut max_dist = Scalar::ZERO;
+    let mut split = d.len() / 2;
+    for i in 1..d.len() - 1 {
+        let v = bezier_point(bez, u[i]).sub(&d[i]);
+        let dist = v.dot(&v);
+        if dist >= max_dist {
+            max_dist = dist;
+            split = i;
+        }
+    }
+    (max_dist, split)
+}
+
+/// One Newton-Raphson step per point towards the closest parameter on the curve.
+fn reparameterize(d: &[Point], u: &[Scalar], bez: &[Point; 4]) -> Vec<Scalar> {
+    let three = Scalar::from_num(3);
+    let q1 = [bez[1].sub(&bez[0]).scale(three), bez[2].sub(&bez[1]).scale(three), bez[3].sub(&bez[2]).scale(three)];
+    let two = Scalar::from_num(2);
+    let q2 = [
+        Vector { x: (q1[1].x - q1[0].x) * two, y: (q1[1].y - q1[0].y) * two },
+        Vector { x: (q1[2].x - q1[1].x) * two, y: (q1[2].y - q1[1].y) * two },
+    ];
+
+    d.iter()
+        .zip(u)
+        .map(|(p, t)| {
+            let t = *t;
+            let mt = Scalar::ONE - t;
+            let q = bezier_point(bez, t).sub(p);
+            let d1 = Vector {
+                x: q1[0].x * mt * mt + q1[1].x * two * mt * t + q1[2].x * t * t,
+                y: q1[0].y * mt * mt + q1[1].y * two * mt * t + q1[2].y * t * t,
+            };
+            let d2 = Vector { x: q2[0].x * mt + q2[1].x * t, y: q2[0].y * mt + q2[1].y * t };
+            let numerator = q.dot(&d1);
+            let denominator = d1.dot(&d1) + q.dot(&d2);
+            match numerator.checked_div(denominator) {
+                Some(step) if denominator != Scalar::ZERO => (t - step).clamp(Scalar::ZERO, Scalar::ONE),
+                _ => t,
+            }
+        })
+        .collect()
+}
+
+impl PathShape {
+    fn flatten_for_simplify(&self, tolerance: Scalar) -> Vec<Polyline> {
+        self.flatten(tolerance.min(DEFAULT_TOLERANCE))
+    }
+
+    /// Reduces every subpath to straight segments using Ramer–Douglas–Peucker.
+    pub fn simplify(&self, tolerance: Scalar) -> PathShape {
+        let mut result = PathShape::new();
+        for line in self.flatten_for_simplify(tolerance) {
+            let mut points = line.points.clone();
+            if line.closed {
+                points.push(points[0]);
+            }
+            let simplified = simplify_polyline(&points, tolerance);
+            let count = if line.closed { simplified.len() - 1 } else { simplified.len() };
+            result.commands.push(PathCommand::MoveTo(simplified[0]));
+            result.commands.extend(simplified[1..count.max(1)].iter().map(|p| PathCommand::LineTo(*p)));
+            if line.closed {
+                result.commands.push(PathCommand::Close);
+            }
+        }
+        result
+    }
+
+    /// Replaces every subpath with a chain of cubic beziers within `tolerance` of the original.
+    pub fn smooth(&self, tolerance: Scalar) -> PathShape {
+        let mut result = PathShape::new();
+        for line in self.flatten_for_simplify(tolerance) {
+            let mut points = line.points.clone();
+            if line.closed {
+                points.push(points[0]);
+            }
+            result.commands.push(PathCommand::MoveTo(points[0]));
+            result.commands.extend(fit_cubics(&points, tolerance));
+            if line.closed {
+                result.commands.push(PathCommand::Close);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zigzag() -> PathShape {
+        let mut path = PathShape::new();
+        path.move_to(0.0, 0.0);
+        for i in 1..=200 {
+            let x = i as f32;
+            let jitter = if i % 2 == 0 { 0.1 } else { -0.1 };
+            path.line_to(x, (x / 200.0 * std::f32::consts::PI).sin() * 50.0 + jitter);
+        }
+        path
+    }
+
+    #[test]
+    fn test_rdp_drops_collinear_points() {
+        let points: Vec<Point> = (0..=10).map(|i| Point::new(i as f32, 0.0)).collect();
+        let simplified = simplify_polyline(&points, Scalar::lit("0.5"));
+        assert_eq!(simplified, vec![Point::new(0.0, 0.0), Point::new(10.0, 0.0)]);
+
+        let simplified = zigzag().simplify(Scalar::ONE);
+        assert!(simplified.commands.len() < 30, "{} commands", simplified.commands.len());
+    }
+
+    #[test]
+    fn test_curve_fit_stays_within_tolerance() {
+        let path = zigzag();
+        let tolerance = Scalar::ONE;
+        let fitted = path.smooth(tolerance);
+        assert!(fitted.commands.len() <= 6, "{} commands", fitted.commands.len());
+        assert!(matches!(fitted.commands[1], PathCommand::CurveTo(..)));
+        let end = match path.commands.last() { Some(PathCommand::LineTo(p)) => *p, _ => unreachable!() };
+        assert!(matches!(fitted.commands.last(), Some(PathCommand::CurveTo(_, _, p)) if *p == end));
+
+        let flat = fitted.flatten(Scalar::lit("0.1"));
+        let original = path.flatten(Scalar::lit("0.1"));
+        for p in flat[0].points.iter().step_by(5) {
+            let nearest = original[0].points.windows(2)
+                .map(|w| p.distance_to_segment(&w[0], &w[1]))
+                .min()
+                .unwrap();
+            assert!(nearest <= tolerance * Scalar::from_num(2), "deviation {nearest}");
+        }
+    }
+}
@@ -1,10 +1,158 @@
+use serde::{Serialize, Deserialize};
 use crate::core::geometry::{Rect, Scalar, Point};
+use crate::core::rtree::RTree;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
 
+/// Elements a node holds before it splits.
+pub const DEFAULT_CAPACITY: usize = 4;
+/// Times a node may be halved before it keeps everything regardless of capacity.
+pub const DEFAULT_MAX_DEPTH: usize = 16;
+
+/// Operations the engine needs from a spatial index over element bounds.
+/// Ids are expected to be unique; results never repeat one.
+pub trait SpatialIndex {
+    /// Adds an element under `bounds`.
+    fn insert(&mut self, id: &str, bounds: &Rect);
+
+    /// Removes an element inserted with `bounds`. Returns false if it was not found.
+    fn remove(&mut self, id: &str, bounds: &Rect) -> bool;
+
+    /// Appends the ids of elements whose bounds intersect `range`.
+    fn query(&self, range: &Rect, found: &mut Vec<String>);
+
+    /// The `k` elements whose bounds lie nearest to `p`, nearest first, ties
+    /// going to the smaller id.
+    fn nearest(&self, p: &Point, k: usize) -> Vec<(String, Scalar)>;
+
+    /// Elements whose bounds come within `radius` of `p`, nearest first.
+    fn within_radius(&self, p: &Point, radius: Scalar) -> Vec<(String, Scalar)>;
+
+    fn clear(&mut self);
+
+    /// Replaces the contents with `entries`. Backends that build better
+    /// structures in bulk override this.
+    fn load(&mut self, entries: Vec<(String, Rect)>) {
+        self.clear();
+        for (id, bounds) in &entries {
+            self.insert(id, bounds);
+        }
+    }
+}
+
+/// Which structure backs the engine's spatial index.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpatialBackend {
+    /// A growing region quadtree. Cheap to update, and suits documents whose
+    /// elements are of similar size.
+    #[default]
+    Quadtree,
+    /// A bulk-loaded R-tree, for documents mixing very large and very small elements.
+    RTree,
+}
+
+/// How the engine builds its spatial index.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexOptions {
+    pub backend: SpatialBackend,
+    /// Elements per quadtree node before it splits, or entries per R-tree node.
+    pub capacity: usize,
+    /// Quadtree subdivision limit; the R-tree balances itself and ignores it.
+    pub max_depth: usize,
+}
+
+impl Default for IndexOptions {
+    fn default() -> Self {
+        Self { backend: SpatialBackend::default(), capacity: DEFAULT_CAPACITY, max_depth: DEFAULT_MAX_DEPTH }
+    }
+}
+
+impl IndexOptions {
+    /// An empty index of the chosen kind.
+    pub fn build(&self) -> Box<dyn SpatialIndex> {
+        let capacity = self.capacity.max(1);
+        match self.backend {
+            SpatialBackend::Quadtree => {
+                // A starting size only; the root grows to fit what is inserted.
+                let bounds = Rect::new(-5000.0, -5000.0, 10000.0, 10000.0);
+                Box::new(Quadtree::new(bounds, capacity, self.max_depth))
+            }
+            SpatialBackend::RTree => Box::new(RTree::new(capacity)),
+        }
+    }
+}
+
+/// Appends `hits` to `found`, skipping ids already seen among the hits.
+pub(crate) fn extend_unique(found: &mut Vec<String>, hits: Vec<String>) {
+    let mut seen = HashSet::new();
+    found.extend(hits.into_iter().filter(|id| seen.insert(id.clone())));
+}
+
+/// A node's own elements, and its children with their bounds.
+pub(crate) type Opened<'a, N> = (&'a [(String, Rect)], Vec<(Rect, &'a N)>);
+
+/// Best-first search over a tree whose nodes `open` into their elements and
+/// their children with bounds. Nodes and elements share one queue ordered
+/// by distance, so elements come off it in order once every node that could
+/// hold something nearer has been opened. The root is always opened, as a
+/// quadtree root can hold elements beyond its bounds.
+pub(crate) fn by_distance<'a, N: 'a>(
+    root: &'a N,
+    p: &Point,
+    limit: usize,
+    radius: Scalar,
+    open: impl Fn(&'a N) -> Opened<'a, N>,
+) -> Vec<(String, Scalar)> {
+    // Nodes sort before elements at the same distance, so an equally near
+    // element in an unopened node still gets its id tie-break.
+    #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    enum Entry<'a> {
+        Node(usize),
+        Element(&'a str),
+    }
+
+    let mut nodes = vec![root];
+    let mut queue = BinaryHeap::from([Reverse((Scalar::ZERO, Entry::Node(0)))]);
+    let mut found: Vec<(String, Scalar)> = Vec::new();
+    let mut seen = HashSet::new();
+    while let Some(Reverse((distance, entry))) = queue.pop() {
+        if found.len() >= limit || distance > radius {
+            break;
+        }
+        match entry {
+            Entry::Element(id) => {
+                if seen.insert(id) {
+                    found.push((id.to_string(), distance));
+                }
+            }
+            Entry::Node(index) => {
+                let (elements, children) = open(nodes[index]);
+                for (id, bounds) in elements {
+                    queue.push(Reverse((bounds.distance_to(p), Entry::Element(id))));
+                }
+                for (bounds, child) in children {
+                    queue.push(Reverse((bounds.distance_to(p), Entry::Node(nodes.len()))));
+                    nodes.push(child);
+                }
+            }
+        }
+    }
+    found
+}
+
+/// A region quadtree whose root grows to take in whatever is inserted, so
+/// no element is ever left out of the index.
 #[derive(Debug, Clone)]
 pub struct Quadtree {
     pub bounds: Rect,
     pub capacity: usize,
-    pub elements: Vec<String>, // Element IDs
+    /// Levels of subdivision allowed below this node. Growing the root adds
+    /// levels above the old one instead of using up this budget, so cells
+    /// never get finer than the initial bounds allow.
+    pub max_depth: usize,
+    pub elements: Vec<(String, Rect)>, // Element IDs with the bounds they were inserted with
     pub divided: bool,
     pub north_west: Option<Box<Quadtree>>,
     pub north_east: Option<Box<Quadtree>>,
@@ -13,10 +161,11 @@ pub struct Quadtree {
 }
 
 impl Quadtree {
-    pub fn new(bounds: Rect, capacity: usize) -> Self {
+    pub fn new(bounds: Rect, capacity: usize, max_depth: usize) -> Self {
         Self {
             bounds,
             capacity,
+            max_depth,
             elements: Vec::new(),
             divided: false,
             north_west: None,
@@ -32,48 +181,195 @@ impl Quadtree {
         let w = self.bounds.width / Scalar::from_num(2);
         let h = self.bounds.height / Scalar::from_num(2);
 
-        self.north_west = Some(Box::new(Quadtree::new(Rect { origin: Point { x, y }, width: w, height: h }, self.capacity)));
-        self.north_east = Some(Box::new(Quadtree::new(Rect { origin: Point { x: x + w, y }, width: w, height: h }, self.capacity)));
-        self.south_west = Some(Box::new(Quadtree::new(Rect { origin: Point { x, y: y + h }, width: w, height: h }, self.capacity)));
-        self.south_east = Some(Box::new(Quadtree::new(Rect { origin: Point { x: x + w, y: y + h }, width: w, height: h }, self.capacity)));
+        self.north_west = Some(Box::new(Quadtree::new(Rect { origin: Point { x, y }, width: w, height: h }, self.capacity, self.max_depth.saturating_sub(1))));
+        self.north_east = Some(Box::new(Quadtree::new(Rect { origin: Point { x: x + w, y }, width: w, height: h }, self.capacity, self.max_depth.saturating_sub(1))));
+        self.south_west = Some(Box::new(Quadtree::new(Rect { origin: Point { x, y: y + h }, width: w, height: h }, self.capacity, self.max_depth.saturating_sub(1))));
+        self.south_east = Some(Box::new(Quadtree::new(Rect { origin: Point { x: x + w, y: y + h }, width: w, height: h }, self.capacity, self.max_depth.saturating_sub(1))));
         
         self.divided = true;
     }
 
-    pub fn insert(&mut self, element_id: &str, element_bounds: &Rect) -> bool {
-        if !self.bounds.intersects(element_bounds) {
-            return false;
+    /// Inserts an element, first growing the root until it contains the
+    /// element's bounds. Elements are kept in the deepest node wholly
+    /// containing them, so those straddling a split stay with the parent.
+    /// One the root cannot grow to contain stays with the root.
+    pub fn insert(&mut self, element_id: &str, element_bounds: &Rect) {
+        while !self.bounds.contains_rect(element_bounds) {
+            if !self.grow_toward(element_bounds) {
+                self.elements.push((element_id.to_string(), *element_bounds));
+                return;
+            }
         }
+        self.insert_node(element_id.to_string(), *element_bounds);
+    }
 
-        if self.elements.len() < self.capacity {
-            self.elements.push(element_id.to_string());
-            return true;
+    fn insert_node(&mut self, element_id: String, element_bounds: Rect) {
+        if !self.divided && self.elements.len() >= self.capacity && self.can_subdivide() {
+            self.subdivide();
+            for (id, bounds) in std::mem::take(&mut self.elements) {
+                self.place(id, bounds);
+            }
         }
+        self.place(element_id, element_bounds);
+    }
 
-        if !self.divided {
-            self.subdivide();
+    /// Stores the element in the child wholly containing it, or here if
+    /// there is none.
+    fn place(&mut self, element_id: String, element_bounds: Rect) {
+        if let Some(child) = self.child_containing(&element_bounds) {
+            child.insert_node(element_id, element_bounds);
+        } else {
+            self.elements.push((element_id, element_bounds));
+        }
+    }
+
+    /// Doubles the root in the direction of `target`, making the current
+    /// root one of the new root's quadrants. Returns false, leaving the root
+    /// alone, when the doubled root would reach past what `Scalar` holds.
+    fn grow_toward(&mut self, target: &Rect) -> bool {
+        // A degenerate root can hold no children, so it is widened in place.
+        let w = self.bounds.width.max(Scalar::ONE);
+        let h = self.bounds.height.max(Scalar::ONE);
+        let west = target.origin.x < self.bounds.origin.x;
+        let north = target.origin.y < self.bounds.origin.y;
+        let x = if west { self.bounds.origin.x.checked_sub(w) } else { Some(self.bounds.origin.x) };
+        let y = if north { self.bounds.origin.y.checked_sub(h) } else { Some(self.bounds.origin.y) };
+        let two = Scalar::from_num(2);
+        let (Some(x), Some(y), Some(width), Some(height)) = (x, y, w.checked_mul(two), h.checked_mul(two)) else {
+            return false;
+        };
+        // The far edges are computed whenever the root is tested against bounds.
+        if x.checked_add(width).is_none() || y.checked_add(height).is_none() {
+            return false;
         }
 
-        (self.north_west.as_mut().unwrap().insert(element_id, element_bounds)) ||
-        (self.north_east.as_mut().unwrap().insert(element_id, element_bounds)) ||
-        (self.south_west.as_mut().unwrap().insert(element_id, element_bounds)) ||
-        (self.south_east.as_mut().unwrap().insert(element_id, element_bounds))
+        let root = Rect { origin: Point { x, y }, width, height };
+        let mut old = std::mem::replace(self, Quadtree::new(root, self.capacity, self.max_depth + 1));
+        old.bounds = Rect { origin: Point { x: if west { x + w } else { x }, y: if north { y + h } else { y } }, width: w, height: h };
+        self.subdivide();
+        let slot = match (west, north) {
+            (false, false) => &mut self.north_west,
+            (true, false) => &mut self.north_east,
+            (false, true) => &mut self.south_west,
+            (true, true) => &mut self.south_east,
+        };
+        // Elements left at the old root past its bounds stay at the root.
+        let (kept, beyond) = std::mem::take(&mut old.elements).into_iter().partition(|(_, b)| old.bounds.contains_rect(b));
+        old.elements = kept;
+        self.elements = beyond;
+        *slot = Some(Box::new(old));
+        true
     }
 
+    /// Appends the ids of elements whose bounds intersect `range`, each
+    /// once, in the order they are found.
     pub fn query(&self, range: &Rect, found: &mut Vec<String>) {
-        if !self.bounds.intersects(range) {
-            return;
-        }
+        let mut hits = Vec::new();
+        self.query_node(range, &mut hits);
+        extend_unique(found, hits);
+    }
 
-        for id in &self.elements {
-            found.push(id.clone());
+    fn query_node(&self, range: &Rect, found: &mut Vec<String>) {
+        for (id, bounds) in &self.elements {
+            if bounds.intersects(range) {
+                found.push(id.clone());
+            }
         }
 
         if self.divided {
-            self.north_west.as_ref().unwrap().query(range, found);
-            self.north_east.as_ref().unwrap().query(range, found);
-            self.south_west.as_ref().unwrap().query(range, found);
-            self.south_east.as_ref().unwrap().query(range, found);
+            for child in self.children() {
+                if child.bounds.intersects(range) {
+                    child.query_node(range, found);
+                }
+            }
+        }
+    }
+
+    /// The `k` elements whose bounds lie nearest to `p`, nearest first, with
+    /// their distances. Bounds containing `p` are at distance zero, and ties
+    /// go to the smaller id.
+    pub fn nearest(&self, p: &Point, k: usize) -> Vec<(String, Scalar)> {
+        self.by_distance(p, k, Scalar::MAX)
+    }
+
+    /// Elements whose bounds come within `radius` of `p`, nearest first.
+    pub fn within_radius(&self, p: &Point, radius: Scalar) -> Vec<(String, Scalar)> {
+        self.by_distance(p, usize::MAX, radius)
+    }
+
+    fn by_distance(&self, p: &Point, limit: usize, radius: Scalar) -> Vec<(String, Scalar)> {
+        by_distance(self, p, limit, radius, |node| {
+            let children = if node.divided { node.children().map(|c| (c.bounds, c)).to_vec() } else { Vec::new() };
+            (&node.elements, children)
+        })
+    }
+
+    /// Removes an element inserted with `element_bounds`. Only the nodes on
+    /// the path `insert` took are visited. Returns false if it was not found.
+    pub fn remove(&mut self, element_id: &str, element_bounds: &Rect) -> bool {
+        if let Some(index) = self.elements.iter().position(|(id, _)| id == element_id) {
+            self.elements.remove(index);
+            return true;
+        }
+
+        // Bounds on a shared edge fit more than one child, and growing the
+        // root may have filed them under a later one than `insert` would pick.
+        let removed = self.divided
+            && self.children_mut().into_iter().any(|c| c.bounds.contains_rect(element_bounds) && c.remove(element_id, element_bounds));
+        if removed {
+            self.collapse();
+        }
+        removed
+    }
+
+    /// Moves an element from `old_bounds` to `new_bounds`. Returns false if
+    /// it was not indexed under `old_bounds`; it is inserted either way.
+    pub fn update(&mut self, element_id: &str, old_bounds: &Rect, new_bounds: &Rect) -> bool {
+        let removed = self.remove(element_id, old_bounds);
+        self.insert(element_id, new_bounds);
+        removed
+    }
+
+    /// Halving also stops once the fixed-point resolution runs out.
+    fn can_subdivide(&self) -> bool {
+        self.max_depth > 0
+            && self.bounds.width / Scalar::from_num(2) > Scalar::ZERO && self.bounds.height / Scalar::from_num(2) > Scalar::ZERO
+    }
+
+    fn child_containing(&mut self, bounds: &Rect) -> Option<&mut Quadtree> {
+        if !self.divided {
+            return None;
+        }
+        self.children_mut().into_iter().find(|c| c.bounds.contains_rect(bounds))
+    }
+
+    fn children_mut(&mut self) -> [&mut Quadtree; 4] {
+        [
+            self.north_west.as_deref_mut().unwrap(),
+            self.north_east.as_deref_mut().unwrap(),
+            self.south_west.as_deref_mut().unwrap(),
+            self.south_east.as_deref_mut().unwrap(),
+        ]
+    }
+
+    fn children(&self) -> [&Quadtree; 4] {
+        [
+            self.north_west.as_deref().unwrap(),
+            self.north_east.as_deref().unwrap(),
+            self.south_west.as_deref().unwrap(),
+            self.south_east.as_deref().unwrap(),
+        ]
+    }
+
+    /// Drops the children once they are empty leaves, so trees shrink back
+    /// after elements leave an area.
+    fn collapse(&mut self) {
+        if self.divided && self.children().iter().all(|c| !c.divided && c.elements.is_empty()) {
+            self.divided = false;
+            self.north_west = None;
+            self.north_east = None;
+            self.south_west = None;
+            self.south_east = None;
         }
     }
 
@@ -86,3 +382,237 @@ impl Quadtree {
         self.south_east = None;
     }
 }
+
+impl SpatialIndex for Quadtree {
+    fn insert(&mut self, id: &str, bounds: &Rect) {
+        Quadtree::insert(self, id, bounds)
+    }
+
+    fn remove(&mut self, id: &str, bounds: &Rect) -> bool {
+        Quadtree::remove(self, id, bounds)
+    }
+
+    fn query(&self, range: &Rect, found: &mut Vec<String>) {
+        Quadtree::query(self, range, found)
+    }
+
+    fn nearest(&self, p: &Point, k: usize) -> Vec<(String, Scalar)> {
+        Quadtree::nearest(self, p, k)
+    }
+
+    fn within_radius(&self, p: &Point, radius: Scalar) -> Vec<(String, Scalar)> {
+        Quadtree::within_radius(self, p, radius)
+    }
+
+    fn clear(&mut self) {
+        Quadtree::clear(self)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    pub(crate) fn rect() -> impl Strategy<Value = Rect> {
+        (-20i32..120, -20i32..120, 0i32..60, 0i32..60).prop_map(|(x, y, w, h)| Rect::new(x as f32, y as f32, w as f32, h as f32))
+    }
+
+    /// Mostly `rect`s, with the odd one far enough out that squaring its
+    /// distance would overflow.
+    pub(crate) fn near_or_far_rect() -> impl Strategy<Value = Rect> {
+        let far = (-5000i32..5000, -5000i32..5000, 0i32..60, 0i32..60)
+            .prop_map(|(x, y, w, h)| Rect::new(x as f32 * 1e9, y as f32 * 1e9, w as f32, h as f32));
+        prop_oneof![9 => rect(), 1 => far]
+    }
+
+    pub(crate) fn brute_force(elements: &[(String, Rect)], range: &Rect) -> Vec<String> {
+        let mut ids: Vec<String> = elements.iter().filter(|(_, b)| b.intersects(range)).map(|(id, _)| id.clone()).collect();
+        ids.sort();
+        ids
+    }
+
+    /// Every element with its distance from `p`, nearest first and ties by id.
+    pub(crate) fn sorted_by_distance(elements: &[(String, Rect)], p: &Point) -> Vec<(String, Scalar)> {
+        let mut found: Vec<(String, Scalar)> = elements.iter().map(|(id, b)| (id.clone(), b.distance_to(p))).collect();
+        found.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        found
+    }
+
+    pub(crate) fn sorted_query(tree: &dyn SpatialIndex, range: &Rect) -> Vec<String> {
+        let mut found = Vec::new();
+        tree.query(range, &mut found);
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn test_remove_and_update() {
+        let mut tree = Quadtree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 1, DEFAULT_MAX_DEPTH);
+        let a = Rect::new(10.0, 10.0, 5.0, 5.0);
+        let b = Rect::new(60.0, 60.0, 5.0, 5.0);
+        tree.insert("a", &a);
+        tree.insert("b", &b);
+        assert!(tree.divided);
+
+        assert!(tree.remove("b", &b));
+        assert!(!tree.remove("b", &b));
+        assert!(tree.divided);
+
+        // Leaving its child empties every child node, so the tree collapses.
+        let moved = Rect::new(80.0, 80.0, 5.0, 5.0);
+        assert!(tree.update("a", &a, &moved));
+        assert!(!tree.divided);
+        assert_eq!(tree.elements, vec![("a".to_string(), moved)]);
+    }
+
+    #[test]
+    fn test_query_is_exact_and_deduplicated() {
+        let mut tree = Quadtree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 1, DEFAULT_MAX_DEPTH);
+        tree.insert("a", &Rect::new(10.0, 10.0, 5.0, 5.0));
+        tree.insert("b", &Rect::new(20.0, 20.0, 5.0, 5.0));
+        tree.insert("b", &Rect::new(20.0, 20.0, 5.0, 5.0));
+
+        let mut found = Vec::new();
+        tree.query(&Rect::new(18.0, 18.0, 4.0, 4.0), &mut found);
+        // The root node reaches the range but "a" itself does not.
+        assert_eq!(found, vec!["b".to_string()]);
+    }
+
+    #[test]
+    fn test_straddling_elements_stay_at_parent() {
+        let mut tree = Quadtree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 1, DEFAULT_MAX_DEPTH);
+        tree.insert("small", &Rect::new(10.0, 10.0, 5.0, 5.0));
+        let wide = Rect::new(10.0, 60.0, 80.0, 10.0);
+        tree.insert("wide", &wide);
+        assert_eq!(tree.elements, vec![("wide".to_string(), wide)]);
+
+        // Only the eastern half of the split is in range.
+        let mut found = Vec::new();
+        tree.query(&Rect::new(80.0, 62.0, 2.0, 2.0), &mut found);
+        assert_eq!(found, vec!["wide".to_string()]);
+    }
+
+    #[test]
+    fn test_root_grows_to_fit_elements() {
+        let mut tree = Quadtree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 1, 2);
+        tree.insert("a", &Rect::new(10.0, 10.0, 5.0, 5.0));
+        tree.insert("far", &Rect::new(-250.0, 330.0, 10.0, 10.0));
+        assert!(tree.bounds.contains_rect(&Rect::new(-250.0, 330.0, 10.0, 10.0)));
+        assert!(tree.bounds.contains_rect(&Rect::new(0.0, 0.0, 100.0, 100.0)));
+        assert_eq!(tree.bounds.width, tree.bounds.height);
+
+        let mut found = Vec::new();
+        tree.query(&Rect::new(-300.0, 300.0, 100.0, 100.0), &mut found);
+        assert_eq!(found, vec!["far".to_string()]);
+
+        // Past the depth limit, nodes hold everything instead of splitting.
+        let mut tree = Quadtree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 1, 0);
+        for id in ["a", "b", "c"] {
+            tree.insert(id, &Rect::new(10.0, 10.0, 1.0, 1.0));
+        }
+        assert!(!tree.divided);
+        assert_eq!(tree.elements.len(), 3);
+    }
+
+    #[test]
+    fn test_nearest_and_within_radius() {
+        let mut tree = Quadtree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 1, DEFAULT_MAX_DEPTH);
+        tree.insert("under", &Rect::new(40.0, 40.0, 20.0, 20.0));
+        tree.insert("far", &Rect::new(85.0, 45.0, 10.0, 10.0));
+        tree.insert("b_left", &Rect::new(20.0, 45.0, 10.0, 10.0));
+        tree.insert("a_right", &Rect::new(70.0, 45.0, 10.0, 10.0));
+        tree.insert("corner", &Rect::new(0.0, 0.0, 5.0, 5.0));
+
+        let p = Point::new(50.0, 50.0);
+        let ids = |found: Vec<(String, Scalar)>| found.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+        assert_eq!(tree.nearest(&p, 1), vec![("under".to_string(), Scalar::ZERO)]);
+        // Both sides are 20 away, so the smaller id comes first.
+        assert_eq!(ids(tree.nearest(&p, 3)), vec!["under", "a_right", "b_left"]);
+        assert_eq!(ids(tree.nearest(&p, 10)), vec!["under", "a_right", "b_left", "far", "corner"]);
+        assert_eq!(ids(tree.within_radius(&p, Scalar::from_num(35))), vec!["under", "a_right", "b_left", "far"]);
+        assert!(tree.within_radius(&Point::new(-50.0, 50.0), Scalar::from_num(10)).is_empty());
+    }
+
+    #[test]
+    fn test_growth_stops_at_representable_range() {
+        let mut tree = Quadtree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 1, DEFAULT_MAX_DEPTH);
+        let elements = [
+            ("near", Rect::new(10.0, 10.0, 10.0, 10.0)),
+            ("east", Rect::new(1.3e14, 0.0, 10.0, 10.0)),
+            ("west", Rect::new(-1.3e14, 0.0, 10.0, 10.0)),
+            ("south", Rect::new(0.0, 1e14, 10.0, 10.0)),
+            ("north", Rect::new(0.0, -1e14, 10.0, 10.0)),
+        ];
+        for (id, bounds) in &elements {
+            tree.insert(id, bounds);
+        }
+        // Past the east and west elements the root would no longer fit in a `Scalar`.
+        assert!(!tree.bounds.contains_rect(&elements[1].1) && !tree.bounds.contains_rect(&elements[2].1));
+        for (id, bounds) in &elements {
+            assert_eq!(sorted_query(&tree, bounds), vec![id.to_string()]);
+            assert_eq!(tree.nearest(&bounds.origin, 1), vec![(id.to_string(), Scalar::ZERO)]);
+        }
+        for (id, bounds) in &elements {
+            assert!(tree.remove(id, bounds));
+            assert!(sorted_query(&tree, bounds).is_empty());
+        }
+
+        // A root that can no longer grow east still grows west, keeping what it could not contain.
+        let mut tree = Quadtree::new(Rect::new(1.2e14, 0.0, 100.0, 100.0), 1, DEFAULT_MAX_DEPTH);
+        let east = Rect::new(1.4e14, 0.0, 10.0, 10.0);
+        let west = Rect::new(3e13, 0.0, 10.0, 10.0);
+        tree.insert("east", &east);
+        tree.insert("west", &west);
+        assert!(tree.bounds.contains_rect(&west));
+        assert_eq!(sorted_query(&tree, &east), vec!["east"]);
+        assert!(tree.remove("east", &east));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_query_matches_brute_force(
+            rects in prop::collection::vec(near_or_far_rect(), 0..60),
+            removed in prop::collection::vec(any::<bool>(), 60),
+            ranges in prop::collection::vec(rect(), 1..10),
+            capacity in 1usize..5,
+            max_depth in 0usize..4,
+        ) {
+            let elements: Vec<(String, Rect)> = rects.iter().enumerate().map(|(i, b)| (format!("e{}", i), *b)).collect();
+            let indexes: [(Box<dyn SpatialIndex>, bool); 3] = [
+                (Box::new(Quadtree::new(Rect::new(0.0, 0.0, 100.0, 100.0), capacity, max_depth)), false),
+                (Box::new(RTree::new(capacity)), false),
+                (Box::new(RTree::new(capacity)), true),
+            ];
+            for (mut tree, bulk) in indexes {
+                if bulk {
+                    tree.load(elements.clone());
+                } else {
+                    for (id, bounds) in &elements {
+                        tree.insert(id, bounds);
+                    }
+                }
+                for range in &ranges {
+                    prop_assert_eq!(sorted_query(tree.as_ref(), range), brute_force(&elements, range));
+                }
+                let p = ranges[0].origin;
+                let by_distance = sorted_by_distance(&elements, &p);
+                prop_assert_eq!(tree.nearest(&p, capacity * 3), by_distance.iter().take(capacity * 3).cloned().collect::<Vec<_>>());
+                let radius = ranges[0].width;
+                prop_assert_eq!(tree.within_radius(&p, radius), by_distance.iter().filter(|(_, d)| *d <= radius).cloned().collect::<Vec<_>>());
+
+                let mut kept = Vec::new();
+                for ((id, bounds), remove) in elements.iter().zip(&removed) {
+                    if *remove {
+                        prop_assert!(tree.remove(id, bounds));
+                    } else {
+                        kept.push((id.clone(), *bounds));
+                    }
+                }
+                for range in &ranges {
+                    prop_assert_eq!(sorted_query(tree.as_ref(), range), brute_force(&kept, range));
+                }
+            }
+        }
+    }
+}
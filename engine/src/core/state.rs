@@ -1,12 +1,62 @@
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
-use crate::core::geometry::{Shape, Rect, Circle, Point};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use crate::core::geometry::{Group, Shape, Point, Rect, Scalar};
+use crate::core::path::{PathShape, DEFAULT_TOLERANCE};
+use crate::core::stroke::{Stroke, StrokeStyle, StrokeAlignment, LineJoin, LineCap};
+use crate::core::path_edit::{PathHandle, NodeType};
+use crate::core::paint::{BlendMode, Paint};
+use crate::core::color::{Color, ColorSpace};
+use crate::core::effects::{self, Effect};
+use crate::core::font::FontLibrary;
+use crate::core::picking::{MarqueeOptions, SelectionRegion};
+use crate::core::text::{self, SpanStyle, Text, TextAlign, TextPath};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Keyframe {
     pub time: f32,
-    pub value: f32, // Simplified for now, will expand to generic values
+    pub value: KeyframeValue,
     pub easing: String,
+    /// Space used when a color track interpolates from this keyframe.
+    #[serde(default)]
+    pub color_space: ColorSpace,
+    /// This path keyframe and the next one brought to a common structure,
+    /// so frames between them only interpolate points.
+    #[serde(skip)]
+    pub morph: Option<Arc<(PathShape, PathShape)>>,
+}
+
+/// Animated value; numbers keep their plain JSON form.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KeyframeValue {
+    Number(f32),
+    Color(Color),
+    Path(PathShape),
+}
+
+impl KeyframeValue {
+    pub fn as_number(&self) -> Option<f32> {
+        match self {
+            KeyframeValue::Number(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_color(&self) -> Option<Color> {
+        match self {
+            KeyframeValue::Color(c) => Some(*c),
+            _ => None,
+        }
+    }
+
+    pub fn as_path(&self) -> Option<&PathShape> {
+        match self {
+            KeyframeValue::Path(p) => Some(p),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,48 +73,200 @@ pub struct Presence {
     pub color: String,
 }
 
+/// How a mask element limits the siblings above it. Both hide everything
+/// outside the mask's shape; an alpha mask also fades content by the mask's
+/// own alpha, which renderers apply but hit-testing and bounds ignore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MaskMode {
+    Alpha,
+    Clip,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Element {
     pub id: String,
     pub name: String,
     pub shape: Shape,
-    pub fill: String,
+    #[serde(deserialize_with = "crate::core::paint::deserialize_fill")]
+    pub fill: Paint,
     pub opacity: f32,
     pub visible: bool,
     pub parent_id: Option<String>,
     pub animations: HashMap<String, Vec<Keyframe>>,
+    #[serde(default)]
+    pub stroke: Option<Stroke>,
+    #[serde(default)]
+    pub effects: Vec<Effect>,
+    #[serde(default)]
+    pub blend_mode: BlendMode,
+    /// Makes this element mask its siblings above it, up to the next mask.
+    /// A mask is not painted itself.
+    #[serde(default)]
+    pub mask: Option<MaskMode>,
+    /// Locked elements are still painted but cannot be picked on the canvas.
+    #[serde(default)]
+    pub locked: bool,
+}
+
+impl Element {
+    pub fn new(id: String, name: String, shape: Shape, fill: Paint) -> Self {
+        Self {
+            id,
+            name,
+            shape,
+            fill,
+            opacity: 1.0,
+            visible: true,
+            parent_id: None,
+            animations: HashMap::new(),
+            stroke: None,
+            effects: Vec::new(),
+            blend_mode: BlendMode::Normal,
+            mask: None,
+            locked: false,
+        }
+    }
+
+    /// Bounds of the painted element, including its stroke and effects.
+    pub fn get_bounds(&self) -> Rect {
+        let painted = match &self.stroke {
+            Some(stroke) => stroke.bounds(&self.shape),
+            None => self.shape.get_bounding_box(),
+        };
+        self.effects.iter().fold(painted, |bounds, effect| effect.expand_bounds(&bounds))
+    }
+
+    /// Removes an effect, dropping its keyframe tracks and renumbering the
+    /// tracks of the effects after it.
+    pub fn remove_effect(&mut self, index: usize) {
+        if index >= self.effects.len() {
+            return;
+        }
+        self.effects.remove(index);
+        let tracks = std::mem::take(&mut self.animations);
+        for (prop, keyframes) in tracks {
+            let prop = match effects::parse_track(&prop) {
+                Some((i, _)) if i == index => continue,
+                Some((i, param)) if i > index => format!("effects.{}.{}", i - 1, param),
+                _ => prop,
+            };
+            self.animations.insert(prop, keyframes);
+        }
+    }
+
+    /// Hit-tests the fill and the stroke.
+    pub fn contains_point(&self, p: &Point) -> bool {
+        self.shape.contains_point(p) || self.stroke.as_ref().is_some_and(|s| s.contains(&self.shape, p))
+    }
+
+    /// Whether the fill or the stroke overlaps `rect`.
+    pub fn intersects_rect(&self, rect: &Rect) -> bool {
+        self.get_bounds().intersects(rect)
+            && (self.shape.intersects_rect(rect)
+                || self.stroke.as_ref().and_then(|s| s.band(&self.shape)).is_some_and(|band| band.intersects_rect(rect)))
+    }
+
+    /// Polygons covering everything the fill and stroke paint, flattened at
+    /// the default tolerance. Unstroked lines give their bare segment, and
+    /// groups nothing, as their children paint for them.
+    pub fn painted_polygons(&self) -> Vec<Vec<Point>> {
+        let mut polygons: Vec<Vec<Point>> = match &self.shape {
+            Shape::Group(_) => Vec::new(),
+            Shape::Text(text) => text.painted_rects().iter().map(|r| r.corners().to_vec()).collect(),
+            shape => shape.to_path().map(|p| p.flatten(DEFAULT_TOLERANCE).into_iter().map(|l| l.points).collect()).unwrap_or_default(),
+        };
+        if let Some(band) = self.stroke.as_ref().and_then(|s| s.band(&self.shape)) {
+            polygons.extend(band.flatten(DEFAULT_TOLERANCE).into_iter().map(|l| l.points));
+        }
+        polygons
+    }
+
+    /// Replaces the shape with an equivalent editable path. Position keyframes
+    /// are rebased onto the path's bounds; keyframes for parameters a path does
+    /// not have (radius, width, ...) are dropped. Text becomes its glyph
+    /// outlines. Returns false for groups and for text whose font is not loaded.
+    pub fn convert_to_path(&mut self, fonts: &FontLibrary) -> bool {
+        let path = match &self.shape {
+            Shape::Text(text) => text.to_outlines(fonts),
+            shape => shape.to_path(),
+        };
+        let Some(path) = path else {
+            return false;
+        };
+        let converted = Shape::Path(path);
+        let offset = match (self.shape.position(), converted.position()) {
+            (Some(from), Some(to)) => to.sub(&from),
+            _ => crate::core::geometry::Vector::zero(),
+        };
+
+        self.animations.retain(|prop, _| {
+            let had = self.shape.clone().set_property(prop, 0.0);
+            let has = converted.clone().set_property(prop, 0.0);
+            has || !had
+        });
+        for (prop, delta) in [("x", offset.x), ("y", offset.y)] {
+            if let Some(track) = self.animations.get_mut(prop) {
+                for kf in track.iter_mut() {
+                    if let KeyframeValue::Number(v) = &mut kf.value {
+                        *v += delta.to_num::<f32>();
+                    }
+                }
+            }
+        }
+        self.shape = converted;
+        true
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EngineState {
     pub elements: HashMap<String, Element>,
+    /// Top-level element ids, bottom to top. Groups order their own children.
+    #[serde(default)]
+    pub root: Vec<String>,
     pub selection: Vec<String>,
     pub transform: Transform,
     pub presence: HashMap<String, Presence>,
     pub current_time: f32,
     pub duration: f32,
     pub is_playing: bool,
+    /// Fonts loaded by the host. Shared between snapshots and never serialized.
+    #[serde(skip)]
+    pub fonts: Arc<FontLibrary>,
+    /// Positions within the sibling lists, kept so masks are found without
+    /// scanning long lists. Rebuilt after loading by `index_siblings`.
+    #[serde(skip)]
+    siblings: SiblingIndex,
 }
 
 impl EngineState {
     pub fn new() -> Self {
         Self {
             elements: HashMap::new(),
+            root: Vec::new(),
             selection: Vec::new(),
             transform: Transform { x: 0.0, y: 0.0, scale: 1.0 },
             presence: HashMap::new(),
             current_time: 0.0,
             duration: 5000.0,
             is_playing: false,
+            fonts: Arc::new(FontLibrary::default()),
+            siblings: SiblingIndex { root: Some(Arc::default()), ..SiblingIndex::default() },
         }
     }
 }
 
+impl Default for EngineState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "payload")]
 pub enum Action {
     #[serde(rename = "ADD_ELEMENT")]
-    AddElement { id: String, name: String, shape: Shape, fill: String },
+    AddElement { id: String, name: String, shape: Shape, fill: Paint },
     
     #[serde(rename = "REMOVE_ELEMENT")]
     RemoveElement { id: String },
@@ -73,7 +275,79 @@ pub enum Action {
     MoveElement { id: String, dx: f32, dy: f32 },
     
     #[serde(rename = "SET_FILL")]
-    SetFill { id: String, fill: String },
+    SetFill { id: String, fill: Paint },
+
+    #[serde(rename = "SET_TEXT_CONTENT")]
+    SetTextContent { id: String, content: String },
+
+    /// Updates the given text style fields, leaving omitted ones unchanged.
+    #[serde(rename = "SET_TEXT_STYLE")]
+    SetTextStyle {
+        id: String,
+        font_family: Option<String>,
+        font_size: Option<f32>,
+        font_weight: Option<u16>,
+        line_height: Option<f32>,
+        letter_spacing: Option<f32>,
+        align: Option<TextAlign>,
+    },
+
+    /// Fixes the text box width so lines wrap, or with `None` lets it grow.
+    #[serde(rename = "SET_TEXT_WIDTH")]
+    SetTextWidth { id: String, width: Option<f32> },
+
+    /// Applies the given style fields to characters `start..end` of a text,
+    /// leaving omitted ones as they are.
+    #[serde(rename = "SET_TEXT_SPAN_STYLE")]
+    SetTextSpanStyle {
+        id: String,
+        start: usize,
+        end: usize,
+        font_family: Option<String>,
+        font_size: Option<f32>,
+        font_weight: Option<u16>,
+        fill: Option<Paint>,
+    },
+
+    /// Returns characters `start..end` to the text's own style.
+    #[serde(rename = "CLEAR_TEXT_SPAN_STYLE")]
+    ClearTextSpanStyle { id: String, start: usize, end: usize },
+
+    /// Replaces characters `start..end` with `text`; an empty range inserts.
+    #[serde(rename = "REPLACE_TEXT")]
+    ReplaceText { id: String, start: usize, end: usize, text: String },
+
+    /// Sets the text along a path in its local space, starting `offset`
+    /// along it, or with `None` back on straight lines.
+    #[serde(rename = "SET_TEXT_PATH")]
+    SetTextPath { id: String, path: Option<PathShape>, offset: f32 },
+
+    #[serde(rename = "SET_OPACITY")]
+    SetOpacity { id: String, opacity: f32 },
+
+    #[serde(rename = "SET_VISIBILITY")]
+    SetVisibility { id: String, visible: bool },
+
+    #[serde(rename = "SET_LOCKED")]
+    SetLocked { id: String, locked: bool },
+
+    #[serde(rename = "SET_SELECTION")]
+    SetSelection { ids: Vec<String> },
+
+    /// Selects what `region` picks out; `extend` adds to the selection
+    /// instead of replacing it. Resolved by the engine, as the reducer alone
+    /// has no spatial index to pick with.
+    #[serde(rename = "MARQUEE_SELECT")]
+    MarqueeSelect {
+        region: SelectionRegion,
+        #[serde(default)]
+        options: MarqueeOptions,
+        #[serde(default)]
+        extend: bool,
+    },
+
+    #[serde(rename = "SET_BLEND_MODE")]
+    SetBlendMode { id: String, blend_mode: BlendMode },
 
     #[serde(rename = "SET_TIME")]
     SetTime { time: f32 },
@@ -89,36 +363,265 @@ pub enum Action {
 
     #[serde(rename = "UPDATE_PRESENCE")]
     UpdatePresence { presence: Presence },
+
+    #[serde(rename = "INSERT_PATH_NODE")]
+    InsertPathNode { id: String, index: usize, t: f32 },
+
+    #[serde(rename = "DELETE_PATH_NODE")]
+    DeletePathNode { id: String, index: usize },
+
+    #[serde(rename = "MOVE_PATH_ANCHOR")]
+    MovePathAnchor { id: String, index: usize, dx: f32, dy: f32 },
+
+    #[serde(rename = "MOVE_PATH_HANDLE")]
+    MovePathHandle { id: String, index: usize, handle: PathHandle, x: f32, y: f32 },
+
+    #[serde(rename = "SET_PATH_NODE_TYPE")]
+    SetPathNodeType { id: String, index: usize, node_type: NodeType },
+
+    #[serde(rename = "JOIN_SUBPATHS")]
+    JoinSubpaths { id: String, first: usize, second: usize },
+
+    #[serde(rename = "SPLIT_SUBPATH")]
+    SplitSubpath { id: String, index: usize },
+
+    #[serde(rename = "REVERSE_PATH")]
+    ReversePath { id: String, subpath: Option<usize> },
+
+    /// Simplifies a path within `tolerance`, refitting it with curves when `smooth` is set.
+    #[serde(rename = "SIMPLIFY_PATH")]
+    SimplifyPath { id: String, tolerance: f32, smooth: bool },
+
+    #[serde(rename = "OUTLINE_STROKE")]
+    OutlineStroke { id: String, style: StrokeStyle },
+
+    #[serde(rename = "OFFSET_PATH")]
+    OffsetPath { id: String, distance: f32, join: LineJoin },
+
+    #[serde(rename = "CONVERT_TO_PATH")]
+    ConvertToPath { id: String },
+
+    /// Replaces the whole stroke; `None` removes it.
+    #[serde(rename = "SET_STROKE")]
+    SetStroke { id: String, stroke: Option<Stroke> },
+
+    #[serde(rename = "SET_STROKE_PAINT")]
+    SetStrokePaint { id: String, paint: Paint },
+
+    #[serde(rename = "SET_STROKE_WIDTH")]
+    SetStrokeWidth { id: String, width: f32 },
+
+    #[serde(rename = "SET_STROKE_ALIGNMENT")]
+    SetStrokeAlignment { id: String, alignment: StrokeAlignment },
+
+    #[serde(rename = "SET_STROKE_JOIN")]
+    SetStrokeJoin { id: String, join: LineJoin, miter_limit: f32 },
+
+    #[serde(rename = "SET_STROKE_CAP")]
+    SetStrokeCap { id: String, cap: LineCap },
+
+    #[serde(rename = "SET_STROKE_DASH")]
+    SetStrokeDash { id: String, dash_array: Vec<f32>, dash_offset: f32 },
+
+    #[serde(rename = "SET_MASK")]
+    SetMask { id: String, mask: Option<MaskMode> },
+
+    /// Wraps `children` in a new group placed where the topmost of them was.
+    #[serde(rename = "GROUP_ELEMENTS")]
+    GroupElements { group_id: String, children: Vec<String> },
+
+    #[serde(rename = "UNGROUP")]
+    Ungroup { group_id: String },
+
+    #[serde(rename = "ADD_EFFECT")]
+    AddEffect { id: String, effect: Effect },
+
+    #[serde(rename = "UPDATE_EFFECT")]
+    UpdateEffect { id: String, index: usize, effect: Effect },
+
+    #[serde(rename = "REMOVE_EFFECT")]
+    RemoveEffect { id: String, index: usize },
+}
+
+impl Action {
+    /// Elements whose geometry or place in the hierarchy the action can
+    /// change. Empty for actions that leave every element's bounds alone,
+    /// such as paint and view changes, and for setting the time, which only
+    /// moves the elements that have keyframes.
+    pub fn target_ids(&self) -> Vec<String> {
+        match self {
+            Action::SetFill { .. }
+            | Action::SetOpacity { .. }
+            | Action::SetVisibility { .. }
+            | Action::SetLocked { .. }
+            | Action::SetSelection { .. }
+            | Action::MarqueeSelect { .. }
+            | Action::SetBlendMode { .. }
+            | Action::SetStrokePaint { .. }
+            | Action::SetTime { .. }
+            | Action::TogglePlayback {}
+            | Action::SetView { .. }
+            | Action::UpdatePresence { .. } => Vec::new(),
+            Action::GroupElements { group_id, children } => std::iter::once(group_id).chain(children).cloned().collect(),
+            Action::Ungroup { group_id } => vec![group_id.clone()],
+            Action::AddKeyframe { element_id, .. } => vec![element_id.clone()],
+            Action::AddElement { id, .. }
+            | Action::RemoveElement { id }
+            | Action::MoveElement { id, .. }
+            | Action::SetTextContent { id, .. }
+            | Action::SetTextStyle { id, .. }
+            | Action::SetTextWidth { id, .. }
+            | Action::SetTextSpanStyle { id, .. }
+            | Action::ClearTextSpanStyle { id, .. }
+            | Action::ReplaceText { id, .. }
+            | Action::SetTextPath { id, .. }
+            | Action::InsertPathNode { id, .. }
+            | Action::DeletePathNode { id, .. }
+            | Action::MovePathAnchor { id, .. }
+            | Action::MovePathHandle { id, .. }
+            | Action::SetPathNodeType { id, .. }
+            | Action::JoinSubpaths { id, .. }
+            | Action::SplitSubpath { id, .. }
+            | Action::ReversePath { id, .. }
+            | Action::SimplifyPath { id, .. }
+            | Action::OutlineStroke { id, .. }
+            | Action::OffsetPath { id, .. }
+            | Action::ConvertToPath { id }
+            | Action::SetStroke { id, .. }
+            | Action::SetStrokeWidth { id, .. }
+            | Action::SetStrokeAlignment { id, .. }
+            | Action::SetStrokeJoin { id, .. }
+            | Action::SetStrokeCap { id, .. }
+            | Action::SetStrokeDash { id, .. }
+            | Action::SetMask { id, .. }
+            | Action::AddEffect { id, .. }
+            | Action::UpdateEffect { id, .. }
+            | Action::RemoveEffect { id, .. } => vec![id.clone()],
+        }
+    }
+}
+
+fn edit_path(state: &mut EngineState, id: &str, edit: impl FnOnce(&mut PathShape)) {
+    if let Some(Element { shape: Shape::Path(path), .. }) = state.elements.get_mut(id) {
+        edit(path);
+    }
+}
+
+fn edit_text(state: &mut EngineState, id: &str, edit: impl FnOnce(&mut Text)) {
+    if let Some(Element { shape: Shape::Text(text), .. }) = state.elements.get_mut(id) {
+        edit(text);
+    }
+}
+
+/// Edits an element's stroke, giving it a default one first if it has none.
+fn edit_stroke(state: &mut EngineState, id: &str, edit: impl FnOnce(&mut Stroke)) {
+    if let Some(el) = state.elements.get_mut(id) {
+        edit(el.stroke.get_or_insert_with(Stroke::default));
+    }
 }
 
 pub fn reducer(state: &mut EngineState, action: Action) {
+    let targets = action.target_ids();
     match action {
-        Action::AddElement { id, name, shape, fill } => {
-            state.elements.insert(id.clone(), Element { 
-                id, 
-                name, 
-                shape, 
-                fill, 
-                opacity: 1.0, 
-                visible: true, 
-                parent_id: None, 
-                animations: HashMap::new() 
-            });
+        Action::AddElement { id, name, shape, mut fill } => {
+            fill.normalize();
+            // A replaced group takes its contents with it, as on removal.
+            state.remove(&id);
+            state.root.push(id.clone());
+            if let Some(list) = &mut state.siblings.root {
+                Arc::make_mut(list).position.insert(id.clone(), state.root.len() - 1);
+            }
+            state.elements.insert(id.clone(), Element::new(id, name, shape, fill));
         }
         Action::RemoveElement { id } => {
-            state.elements.remove(&id);
-            state.selection.retain(|s| s != &id);
+            state.remove(&id);
         }
         Action::MoveElement { id, dx, dy } => {
             if let Some(el) = state.elements.get_mut(&id) {
                 el.shape.translate(dx, dy);
             }
         }
-        Action::SetFill { id, fill } => {
+        Action::SetFill { id, mut fill } => {
+            fill.normalize();
             if let Some(el) = state.elements.get_mut(&id) {
                 el.fill = fill;
             }
         }
+        Action::SetTextContent { id, content } => {
+            edit_text(state, &id, |t| t.replace_range(0, t.char_count(), &content));
+        }
+        Action::SetTextStyle { id, font_family, font_size, font_weight, line_height, letter_spacing, align } => {
+            edit_text(state, &id, |t| {
+                if let Some(family) = font_family {
+                    t.font_family = family;
+                }
+                if let Some(size) = font_size {
+                    t.font_size = Scalar::from_num(size.max(0.0));
+                }
+                if let Some(weight) = font_weight {
+                    t.font_weight = weight.clamp(1, 1000);
+                }
+                if let Some(line_height) = line_height {
+                    t.line_height = Scalar::from_num(line_height.max(0.0));
+                }
+                if let Some(spacing) = letter_spacing {
+                    t.letter_spacing = Scalar::from_num(spacing);
+                }
+                if let Some(align) = align {
+                    t.align = align;
+                }
+            });
+        }
+        Action::SetTextWidth { id, width } => {
+            edit_text(state, &id, |t| t.width = width.map(|w| Scalar::from_num(w.max(0.0))));
+        }
+        Action::SetTextSpanStyle { id, start, end, font_family, font_size, font_weight, mut fill } => {
+            if let Some(fill) = &mut fill {
+                fill.normalize();
+            }
+            let style = SpanStyle {
+                font_family,
+                font_size: font_size.map(|size| Scalar::from_num(size.max(0.0))),
+                font_weight: font_weight.map(|weight| weight.clamp(1, 1000)),
+                fill,
+            };
+            edit_text(state, &id, |t| t.set_span_style(start, end, &style));
+        }
+        Action::ClearTextSpanStyle { id, start, end } => {
+            edit_text(state, &id, |t| t.clear_span_style(start, end));
+        }
+        Action::ReplaceText { id, start, end, text } => {
+            edit_text(state, &id, |t| t.replace_range(start, end, &text));
+        }
+        Action::SetTextPath { id, path, offset } => {
+            edit_text(state, &id, |t| t.path = path.map(|path| TextPath { path, offset: Scalar::from_num(offset) }));
+        }
+        Action::SetOpacity { id, opacity } => {
+            if let Some(el) = state.elements.get_mut(&id) {
+                el.opacity = opacity.clamp(0.0, 1.0);
+            }
+        }
+        Action::SetVisibility { id, visible } => {
+            if let Some(el) = state.elements.get_mut(&id) {
+                el.visible = visible;
+            }
+        }
+        Action::SetLocked { id, locked } => {
+            if let Some(el) = state.elements.get_mut(&id) {
+                el.locked = locked;
+            }
+        }
+        Action::SetSelection { ids } => {
+            state.selection = ids.into_iter().filter(|id| state.elements.contains_key(id)).collect();
+        }
+        // Picking needs the spatial index, so `KineticEngine::apply` turns
+        // this into the `SetSelection` it makes before the reducer runs.
+        Action::MarqueeSelect { .. } => {}
+        Action::SetBlendMode { id, blend_mode } => {
+            if let Some(el) = state.elements.get_mut(&id) {
+                el.blend_mode = blend_mode;
+            }
+        }
         Action::SetTime { time } => {
             state.current_time = time;
         }
@@ -127,9 +630,16 @@ pub fn reducer(state: &mut EngineState, action: Action) {
         }
         Action::AddKeyframe { element_id, property, keyframe } => {
             if let Some(el) = state.elements.get_mut(&element_id) {
-                let animation = el.animations.entry(property).or_insert_with(Vec::new());
+                let is_path = property == "path";
+                let animation = el.animations.entry(property).or_insert_with(Vec::new);
+                let time = keyframe.time;
                 animation.push(keyframe);
                 animation.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+                if is_path {
+                    let at = animation.partition_point(|k| k.time <= time) - 1;
+                    crate::core::interpolation::prepare_morph_at(animation, at.saturating_sub(1));
+                    crate::core::interpolation::prepare_morph_at(animation, at);
+                }
             }
         }
         Action::SetView { transform } => {
@@ -138,43 +648,785 @@ pub fn reducer(state: &mut EngineState, action: Action) {
         Action::UpdatePresence { presence } => {
             state.presence.insert(presence.user_id.clone(), presence);
         }
+        Action::InsertPathNode { id, index, t } => {
+            edit_path(state, &id, |p| { p.insert_node(index, Scalar::from_num(t)); });
+        }
+        Action::DeletePathNode { id, index } => {
+            edit_path(state, &id, |p| { p.delete_node(index); });
+        }
+        Action::MovePathAnchor { id, index, dx, dy } => {
+            edit_path(state, &id, |p| { p.move_anchor(index, Scalar::from_num(dx), Scalar::from_num(dy)); });
+        }
+        Action::MovePathHandle { id, index, handle, x, y } => {
+            edit_path(state, &id, |p| { p.move_handle(index, handle, Point::new(x, y)); });
+        }
+        Action::SetPathNodeType { id, index, node_type } => {
+            edit_path(state, &id, |p| { p.set_node_type(index, node_type); });
+        }
+        Action::JoinSubpaths { id, first, second } => {
+            edit_path(state, &id, |p| { p.join_subpaths(first, second); });
+        }
+        Action::SplitSubpath { id, index } => {
+            edit_path(state, &id, |p| { p.split_subpath(index); });
+        }
+        Action::ReversePath { id, subpath } => {
+            edit_path(state, &id, |p| { p.reverse(subpath); });
+        }
+        Action::SimplifyPath { id, tolerance, smooth } => {
+            let tolerance = Scalar::from_num(tolerance).max(Scalar::DELTA);
+            edit_path(state, &id, |p| {
+                *p = if smooth { p.smooth(tolerance) } else { p.simplify(tolerance) };
+            });
+        }
+        Action::OutlineStroke { id, style } => {
+            edit_path(state, &id, |p| *p = p.stroke_outline(&style, DEFAULT_TOLERANCE));
+        }
+        Action::OffsetPath { id, distance, join } => {
+            let miter_limit = StrokeStyle::new(0.0).miter_limit;
+            edit_path(state, &id, |p| *p = p.offset(Scalar::from_num(distance), join, miter_limit, DEFAULT_TOLERANCE));
+        }
+        Action::ConvertToPath { id } => {
+            if let Some(el) = state.elements.get_mut(&id) {
+                el.convert_to_path(&state.fonts);
+            }
+        }
+        Action::SetStroke { id, stroke } => {
+            if let Some(el) = state.elements.get_mut(&id) {
+                el.stroke = stroke;
+            }
+        }
+        Action::SetStrokePaint { id, mut paint } => {
+            paint.normalize();
+            edit_stroke(state, &id, |s| s.paint = paint);
+        }
+        Action::SetStrokeWidth { id, width } => {
+            edit_stroke(state, &id, |s| s.style.width = Scalar::from_num(width.max(0.0)));
+        }
+        Action::SetStrokeAlignment { id, alignment } => {
+            edit_stroke(state, &id, |s| s.alignment = alignment);
+        }
+        Action::SetStrokeJoin { id, join, miter_limit } => {
+            edit_stroke(state, &id, |s| {
+                s.style.join = join;
+                s.style.miter_limit = Scalar::from_num(miter_limit.max(1.0));
+            });
+        }
+        Action::SetStrokeCap { id, cap } => {
+            edit_stroke(state, &id, |s| s.style.cap = cap);
+        }
+        Action::SetStrokeDash { id, dash_array, dash_offset } => {
+            edit_stroke(state, &id, |s| {
+                s.style.dash_array = dash_array.iter().map(|d| Scalar::from_num(d.max(0.0))).collect();
+                s.style.dash_offset = Scalar::from_num(dash_offset);
+            });
+        }
+        Action::SetMask { id, mask } => {
+            if let Some(el) = state.elements.get_mut(&id) {
+                el.mask = mask;
+                let parent = el.parent_id.clone();
+                state.siblings.invalidate(parent.as_deref());
+            }
+        }
+        Action::GroupElements { group_id, children } => {
+            state.group(group_id, children);
+        }
+        Action::Ungroup { group_id } => {
+            state.ungroup(&group_id);
+        }
+        Action::AddEffect { id, effect } => {
+            if let Some(el) = state.elements.get_mut(&id) {
+                el.effects.push(effect);
+            }
+        }
+        Action::UpdateEffect { id, index, effect } => {
+            if let Some(slot) = state.elements.get_mut(&id).and_then(|el| el.effects.get_mut(index)) {
+                *slot = effect;
+            }
+        }
+        Action::RemoveEffect { id, index } => {
+            if let Some(el) = state.elements.get_mut(&id) {
+                el.remove_effect(index);
+            }
+        }
     }
+    state.layout_text_of(&targets);
+    state.refresh_siblings();
 }
 
 impl EngineState {
     pub fn get_computed_state(&self) -> EngineState {
         let mut computed = self.clone();
+        // Text keeps the base state's cached layout unless a track moves it.
+        let mut relayout = Vec::new();
         for el in computed.elements.values_mut() {
-            for (prop, keyframes) in &el.animations {
-                let interpolated_value = crate::core::interpolation::interpolate(keyframes, self.current_time);
+            if self.animate(el) {
+                relayout.push(el.id.clone());
+            }
+        }
+        computed.layout_text_of(&relayout);
+        computed
+    }
+
+    /// The element as painted at `current_time`: the stored one unless it
+    /// has keyframes, otherwise a copy with its tracks applied.
+    pub fn at_current_time<'a>(&self, el: &'a Element) -> Cow<'a, Element> {
+        if el.animations.is_empty() {
+            return Cow::Borrowed(el);
+        }
+        let mut el = el.clone();
+        if self.animate(&mut el) {
+            if let Shape::Text(text) = &mut el.shape {
+                text.relayout(&self.fonts);
+            }
+        }
+        Cow::Owned(el)
+    }
+
+    /// Applies the element's tracks at `current_time`, returning whether it
+    /// is text that needs laying out again.
+    fn animate(&self, el: &mut Element) -> bool {
+        // The path track replaces the shape, so it runs before tracks that adjust it.
+        if let Some(path) = el.animations.get("path").and_then(|k| crate::core::interpolation::interpolate_path(k, self.current_time)) {
+            el.shape = Shape::Path(path);
+        }
+        let mut props: Vec<&String> = el.animations.keys().filter(|p| p.as_str() != "path").collect();
+        props.sort();
+        let relayout = matches!(el.shape, Shape::Text(_)) && props.iter().any(|p| text::LAYOUT_PROPERTIES.contains(&p.as_str()));
+        for prop in props {
+            let keyframes = &el.animations[prop];
+            if let Some(color) = crate::core::interpolation::interpolate_color(keyframes, self.current_time) {
                 match prop.as_str() {
-                    "x" => {
-                        match el.shape {
-                            Shape::Rect(ref mut r) => r.origin.x = crate::core::geometry::Scalar::from_num(interpolated_value),
-                            Shape::Circle(ref mut c) => c.center.x = crate::core::geometry::Scalar::from_num(interpolated_value),
-                            Shape::Image(ref mut i) => i.origin.x = crate::core::geometry::Scalar::from_num(interpolated_value),
-                            _ => {}
+                    "fill" => el.fill = Paint::Solid(color),
+                    "stroke_paint" => {
+                        if let Some(stroke) = &mut el.stroke {
+                            stroke.paint = Paint::Solid(color);
                         }
-                    },
-                    "y" => {
-                        match el.shape {
-                            Shape::Rect(ref mut r) => r.origin.y = crate::core::geometry::Scalar::from_num(interpolated_value),
-                            Shape::Circle(ref mut c) => c.center.y = crate::core::geometry::Scalar::from_num(interpolated_value),
-                            Shape::Image(ref mut i) => i.origin.y = crate::core::geometry::Scalar::from_num(interpolated_value),
-                            _ => {}
+                    }
+                    _ => {
+                        if let Some(effect) = effects::parse_track(prop).and_then(|(i, _)| el.effects.get_mut(i)) {
+                            effect.set_color(color);
                         }
-                    },
-                    "opacity" => {
-                        el.opacity = interpolated_value;
-                    },
-                    _ => {}
+                    }
+                }
+                continue;
+            }
+            let interpolated_value = crate::core::interpolation::interpolate(keyframes, self.current_time);
+            match prop.as_str() {
+                "opacity" => {
+                    el.opacity = interpolated_value;
+                },
+                "stroke_width" => {
+                    if let Some(stroke) = &mut el.stroke {
+                        stroke.style.width = Scalar::from_num(interpolated_value.max(0.0));
+                    }
+                },
+                "stroke_dash_offset" => {
+                    if let Some(stroke) = &mut el.stroke {
+                        stroke.style.dash_offset = Scalar::from_num(interpolated_value);
+                    }
+                },
+                _ => {
+                    if let Some((i, param)) = effects::parse_track(prop) {
+                        if let Some(effect) = el.effects.get_mut(i) {
+                            effect.set_property(param, interpolated_value);
+                        }
+                    } else {
+                        el.shape.set_property(prop, interpolated_value);
+                    }
                 }
             }
         }
-        computed
+        relayout
+    }
+
+    /// Re-lays out the given text elements; others are skipped.
+    fn layout_text_of(&mut self, ids: &[String]) {
+        let fonts = Arc::clone(&self.fonts);
+        for id in ids {
+            if let Some(Element { shape: Shape::Text(text), .. }) = self.elements.get_mut(id) {
+                text.relayout(&fonts);
+            }
+        }
+    }
+
+    /// Prepares the morphs of every path track, as after loading a document.
+    pub fn prepare_morphs(&mut self) {
+        for el in self.elements.values_mut() {
+            if let Some(track) = el.animations.get_mut("path") {
+                crate::core::interpolation::prepare_path_track(track);
+            }
+        }
+    }
+
+    /// Lays out every text element again with the loaded fonts.
+    pub fn layout_text(&mut self) {
+        let fonts = Arc::clone(&self.fonts);
+        for el in self.elements.values_mut() {
+            if let Shape::Text(text) = &mut el.shape {
+                text.relayout(&fonts);
+            }
+        }
     }
 
     pub fn snap_to_grid(&self, val: f32, grid_size: f32) -> f32 {
         (val / grid_size).round() * grid_size
     }
 }
+
+/// Accumulated clipping from the masks over an element and its ancestors.
+#[derive(Debug, Clone, Copy)]
+enum Clip {
+    Unclipped,
+    To(Rect),
+    /// A mask over it paints nothing, or nothing where the element is.
+    Hidden,
+}
+
+impl Clip {
+    fn and(self, mask: Option<Rect>) -> Clip {
+        match (self, mask) {
+            (Clip::Hidden, _) | (_, None) => Clip::Hidden,
+            (Clip::Unclipped, Some(mask)) => Clip::To(mask),
+            (Clip::To(clip), Some(mask)) => clip.intersection(&mask).map_or(Clip::Hidden, Clip::To),
+        }
+    }
+
+    fn apply(self, bounds: &Rect) -> Option<Rect> {
+        match self {
+            Clip::Unclipped => Some(*bounds),
+            Clip::To(clip) => bounds.intersection(&clip),
+            Clip::Hidden => None,
+        }
+    }
+}
+
+/// Where each element of one sibling list sits, and where its masks are.
+#[derive(Debug, Clone, Default)]
+struct SiblingList {
+    position: HashMap<String, usize>,
+    /// Positions of the masks, ascending.
+    masks: Vec<usize>,
+}
+
+/// `SiblingList`s for the root and each group. Lists are shared between
+/// snapshots, and one that changes is dropped and indexed again once the
+/// action is done, so lookups fall back to scanning only in between.
+#[derive(Debug, Clone, Default)]
+struct SiblingIndex {
+    root: Option<Arc<SiblingList>>,
+    groups: HashMap<String, Arc<SiblingList>>,
+    /// Groups whose lists changed since they were indexed.
+    stale: HashSet<String>,
+}
+
+impl SiblingIndex {
+    fn list(&self, parent: Option<&str>) -> Option<&SiblingList> {
+        match parent {
+            None => self.root.as_deref(),
+            Some(parent) => self.groups.get(parent).map(|list| &**list),
+        }
+    }
+
+    fn invalidate(&mut self, parent: Option<&str>) {
+        match parent {
+            None => self.root = None,
+            Some(parent) => {
+                self.groups.remove(parent);
+                self.stale.insert(parent.to_string());
+            }
+        }
+    }
+}
+
+/// Group hierarchy and masking. Sibling lists run bottom to top: the root
+/// list for top-level elements and each group's `children` below it. Bounds
+/// and hits are of elements as painted at `current_time`.
+impl EngineState {
+    /// Ids directly under `parent` (the root when `None`), bottom to top.
+    /// Top-level elements missing from `root`, as in older saved states, are
+    /// stacked above the listed ones in id order.
+    pub fn children(&self, parent: Option<&str>) -> Vec<String> {
+        if let Some(parent) = parent {
+            return match self.elements.get(parent).map(|el| &el.shape) {
+                Some(Shape::Group(group)) => group.children.clone(),
+                _ => Vec::new(),
+            };
+        }
+        let is_top = |id: &String| self.elements.get(id).is_some_and(|el| el.parent_id.is_none());
+        let listed: HashSet<&String> = self.root.iter().collect();
+        let mut unlisted: Vec<String> = self.elements.keys().filter(|id| is_top(id) && !listed.contains(id)).cloned().collect();
+        unlisted.sort();
+        let mut ids: Vec<String> = self.root.iter().filter(|id| is_top(id)).cloned().collect();
+        ids.extend(unlisted);
+        ids
+    }
+
+    fn siblings_mut(&mut self, parent: Option<&str>) -> Option<&mut Vec<String>> {
+        self.siblings.invalidate(parent);
+        match parent {
+            None => {
+                self.root = self.children(None);
+                Some(&mut self.root)
+            }
+            Some(parent) => match self.elements.get_mut(parent).map(|el| &mut el.shape) {
+                Some(Shape::Group(group)) => Some(&mut group.children),
+                _ => None,
+            },
+        }
+    }
+
+    /// Indexes every sibling list afresh, as after loading a state.
+    pub fn index_siblings(&mut self) {
+        self.siblings = SiblingIndex::default();
+        self.siblings.stale = self.elements.iter().filter(|(_, el)| matches!(el.shape, Shape::Group(_))).map(|(id, _)| id.clone()).collect();
+        self.refresh_siblings();
+    }
+
+    /// Indexes the sibling lists changed since they were last indexed.
+    fn refresh_siblings(&mut self) {
+        if self.siblings.root.is_none() {
+            self.siblings.root = Some(Arc::new(self.sibling_list(None)));
+        }
+        for parent in std::mem::take(&mut self.siblings.stale) {
+            if matches!(self.elements.get(&parent).map(|el| &el.shape), Some(Shape::Group(_))) {
+                let list = Arc::new(self.sibling_list(Some(&parent)));
+                self.siblings.groups.insert(parent, list);
+            }
+        }
+    }
+
+    /// Positions in the stored list under `parent`, skipping root entries
+    /// for elements that have since moved into a group.
+    fn sibling_list(&self, parent: Option<&str>) -> SiblingList {
+        let mut list = SiblingList::default();
+        for (index, id) in self.stored_siblings(parent).iter().enumerate() {
+            let Some(el) = self.elements.get(id).filter(|el| parent.is_some() || el.parent_id.is_none()) else {
+                continue;
+            };
+            list.position.insert(id.clone(), index);
+            if el.mask.is_some() {
+                list.masks.push(index);
+            }
+        }
+        list
+    }
+
+    /// The list `parent` stores its children in, unlike `children` leaving
+    /// out unlisted top-level elements.
+    fn stored_siblings(&self, parent: Option<&str>) -> &[String] {
+        match parent {
+            None => &self.root,
+            Some(parent) => match self.elements.get(parent).map(|p| &p.shape) {
+                Some(Shape::Group(group)) => &group.children,
+                _ => &[],
+            },
+        }
+    }
+
+    /// Where an element sits in its stored sibling list, when that list is
+    /// indexed and up to date.
+    fn indexed_position(&self, id: &str, parent: Option<&str>) -> Option<(&SiblingList, usize)> {
+        let list = self.siblings.list(parent)?;
+        let index = *list.position.get(id)?;
+        (self.stored_siblings(parent).get(index).map(String::as_str) == Some(id)).then_some((list, index))
+    }
+
+    /// Takes an element out of its parent's sibling list, leaving it parentless.
+    fn detach(&mut self, id: &str) {
+        let parent = self.elements.get(id).and_then(|el| el.parent_id.clone());
+        if let Some(siblings) = self.siblings_mut(parent.as_deref()) {
+            siblings.retain(|s| s != id);
+        }
+        if let Some(el) = self.elements.get_mut(id) {
+            el.parent_id = None;
+        }
+    }
+
+    /// Deletes an element and, for a group, everything in it, rather than
+    /// leaving the children orphaned.
+    fn remove(&mut self, id: &str) {
+        if !self.elements.contains_key(id) {
+            return;
+        }
+        self.detach(id);
+        let mut doomed = vec![id.to_string()];
+        while let Some(id) = doomed.pop() {
+            if let Some(Element { shape: Shape::Group(group), .. }) = self.elements.remove(&id) {
+                self.siblings.invalidate(Some(&id));
+                doomed.extend(group.children);
+            }
+            self.selection.retain(|s| s != &id);
+        }
+    }
+
+    fn attach(&mut self, id: &str, parent: Option<&str>, index: usize) {
+        if let Some(siblings) = self.siblings_mut(parent) {
+            let index = index.min(siblings.len());
+            siblings.insert(index, id.to_string());
+        }
+        if let Some(el) = self.elements.get_mut(id) {
+            el.parent_id = parent.map(str::to_string);
+        }
+    }
+
+    /// Groups enclosing the element, innermost first.
+    pub fn ancestors(&self, id: &str) -> Vec<String> {
+        let mut chain = Vec::new();
+        let mut current = self.elements.get(id).and_then(|el| el.parent_id.clone());
+        while let Some(parent) = current {
+            // A cycle in loaded data would otherwise never end.
+            if chain.contains(&parent) || chain.len() > self.elements.len() {
+                break;
+            }
+            current = self.elements.get(&parent).and_then(|el| el.parent_id.clone());
+            chain.push(parent);
+        }
+        chain
+    }
+
+    /// Each element's index among its siblings at each level, from the root
+    /// down. Sorting by these keys puts elements in paint order, bottom to
+    /// top. Positions come from the sibling index where it is up to date;
+    /// otherwise every sibling list involved is scanned once, however many
+    /// of the ids share it.
+    pub fn paint_keys(&self, ids: &[String]) -> HashMap<String, Vec<usize>> {
+        let chains: Vec<(&String, Vec<String>)> = ids
+            .iter()
+            .map(|id| {
+                let mut chain = self.ancestors(id);
+                chain.reverse();
+                chain.push(id.clone());
+                (id, chain)
+            })
+            .collect();
+
+        let mut wanted: HashMap<Option<&str>, HashSet<&str>> = HashMap::new();
+        for (_, chain) in &chains {
+            let mut parent = None;
+            for node in chain {
+                wanted.entry(parent).or_default().insert(node);
+                parent = Some(node.as_str());
+            }
+        }
+
+        let mut position: HashMap<&str, usize> = HashMap::new();
+        for (parent, mut nodes) in wanted {
+            nodes.retain(|node| match self.indexed_position(node, parent) {
+                Some((_, index)) => {
+                    position.insert(node, index);
+                    false
+                }
+                None => true,
+            });
+            if nodes.is_empty() {
+                continue;
+            }
+            let siblings = self.stored_siblings(parent);
+            for (index, sibling) in siblings.iter().enumerate() {
+                if let Some(node) = nodes.get(sibling.as_str()) {
+                    position.entry(node).or_insert(index);
+                }
+            }
+            // Unlisted top-level elements stack above the listed ones in id
+            // order, as in `children`.
+            let mut unlisted: Vec<&str> = nodes.into_iter().filter(|n| !position.contains_key(n)).collect();
+            unlisted.sort();
+            for (rank, node) in unlisted.into_iter().enumerate() {
+                position.insert(node, siblings.len() + rank);
+            }
+        }
+
+        chains.iter().map(|(id, chain)| ((*id).clone(), chain.iter().map(|n| position[n.as_str()]).collect())).collect()
+    }
+
+    /// Every element, bottom to top as painted, with each group's contents
+    /// in place of the group.
+    pub fn paint_order(&self) -> Vec<String> {
+        let mut order = Vec::with_capacity(self.elements.len());
+        let mut pending: Vec<String> = self.children(None).into_iter().rev().collect();
+        while let Some(id) = pending.pop() {
+            // Guards against cycles in loaded data.
+            if order.len() > self.elements.len() {
+                break;
+            }
+            pending.extend(self.children(Some(&id)).into_iter().rev());
+            order.push(id);
+        }
+        order
+    }
+
+    /// Every element below `id` in the group hierarchy.
+    pub fn descendants(&self, id: &str) -> Vec<String> {
+        let mut found = Vec::new();
+        let mut pending = self.children(Some(id));
+        while let Some(child) = pending.pop() {
+            // Guards against cycles in loaded data.
+            if found.contains(&child) || found.len() > self.elements.len() {
+                continue;
+            }
+            pending.extend(self.children(Some(&child)));
+            found.push(child);
+        }
+        found
+    }
+
+    /// Elements whose clipped bounds can change when `id` does: the element,
+    /// its descendants and ancestors, and, where it or an ancestor is a
+    /// mask, everything stacked above that mask.
+    pub fn spatial_dependents(&self, id: &str) -> Vec<String> {
+        let mut ids = self.descendants(id);
+        for level in std::iter::once(id.to_string()).chain(self.ancestors(id)) {
+            let Some(el) = self.elements.get(&level) else {
+                continue;
+            };
+            if el.mask.is_some() {
+                let siblings = self.children(el.parent_id.as_deref());
+                let index = siblings.iter().position(|s| *s == level).unwrap_or(siblings.len());
+                for sibling in siblings.iter().skip(index + 1) {
+                    ids.extend(self.descendants(sibling));
+                    ids.push(sibling.clone());
+                }
+            }
+            ids.push(level);
+        }
+        ids
+    }
+
+    fn group(&mut self, group_id: String, children: Vec<String>) {
+        if self.elements.contains_key(&group_id) {
+            return;
+        }
+        let mut members: Vec<String> = Vec::new();
+        for id in children {
+            if self.elements.contains_key(&id) && !members.contains(&id) {
+                members.push(id);
+            }
+        }
+        let member_set: HashSet<String> = members.iter().cloned().collect();
+        // Grouping an element together with one of its ancestors is ambiguous; the ancestor wins.
+        members.retain(|id| !self.ancestors(id).iter().any(|a| member_set.contains(a)));
+        let Some(first) = members.first() else {
+            return;
+        };
+
+        let parent = self.elements[first].parent_id.clone();
+        let siblings = self.children(parent.as_deref());
+        let position = |id: &String| siblings.iter().position(|s| s == id).unwrap_or(usize::MAX);
+        // Keep the stacking order the children had, and put the group where the topmost one was.
+        members.sort_by_key(position);
+        let topmost = members.iter().map(position).filter(|&i| i != usize::MAX).max();
+        let index = match topmost {
+            Some(top) => siblings[..=top].iter().filter(|s| !member_set.contains(*s)).count(),
+            None => siblings.len(),
+        };
+
+        for id in &members {
+            self.detach(id);
+        }
+        let shape = Shape::Group(Group { children: Vec::new() });
+        let mut group = Element::new(group_id.clone(), "Group".to_string(), shape, Paint::Solid(Color::TRANSPARENT));
+        group.parent_id = parent.clone();
+        // Listed before it exists so the root's unlisted-element fallback cannot add it twice.
+        if let Some(siblings) = self.siblings_mut(parent.as_deref()) {
+            siblings.insert(index.min(siblings.len()), group_id.clone());
+        }
+        self.elements.insert(group_id.clone(), group);
+        for (i, id) in members.iter().enumerate() {
+            self.attach(id, Some(&group_id), i);
+        }
+    }
+
+    fn ungroup(&mut self, group_id: &str) {
+        let Some(Element { shape: Shape::Group(group), parent_id, .. }) = self.elements.get(group_id) else {
+            return;
+        };
+        let (children, parent) = (group.children.clone(), parent_id.clone());
+        let index = self.children(parent.as_deref()).iter().position(|s| s == group_id).unwrap_or(0);
+        self.detach(group_id);
+        self.elements.remove(group_id);
+        self.siblings.invalidate(Some(group_id));
+        self.selection.retain(|s| s != group_id);
+        for (i, id) in children.iter().enumerate() {
+            self.attach(id, parent.as_deref(), index + i);
+        }
+    }
+
+    /// The mask clipping `id` among its siblings: the nearest mask below it.
+    pub fn mask_for(&self, id: &str) -> Option<&Element> {
+        let el = self.elements.get(id)?;
+        if el.mask.is_some() {
+            return None;
+        }
+        let is_mask = |s: &&Element| s.mask.is_some();
+        let parent = el.parent_id.as_deref();
+        let stored = self.stored_siblings(parent);
+        if let Some((list, index)) = self.indexed_position(id, parent) {
+            let below = list.masks.partition_point(|&m| m < index);
+            return list.masks[..below].last().and_then(|&m| self.elements.get(&stored[m]));
+        }
+        // Read the stored list in place where possible, as building the root
+        // list allocates. A listed root element only has listed ones below it.
+        if let Some(index) = stored.iter().rposition(|s| s == id) {
+            let sibling = |s: &String| self.elements.get(s).filter(|s| parent.is_some() || s.parent_id.is_none());
+            return stored[..index].iter().rev().filter_map(sibling).find(is_mask);
+        }
+        let siblings = self.children(el.parent_id.as_deref());
+        let index = siblings.iter().position(|s| s == id)?;
+        siblings[..index].iter().rev().filter_map(|s| self.elements.get(s)).find(is_mask)
+    }
+
+    /// Bounds of what an element paints at `current_time`, before masks
+    /// around it apply. A group covers its painted children, each clipped to
+    /// its mask there.
+    pub fn painted_bounds(&self, id: &str) -> Option<Rect> {
+        self.painted_bounds_at(id, 0, &mut HashMap::new())
+    }
+
+    /// `painted_bounds`, remembering each group's result in `memo` so nested
+    /// groups are only measured once.
+    fn painted_bounds_at(&self, id: &str, depth: usize, memo: &mut HashMap<String, Option<Rect>>) -> Option<Rect> {
+        self.painted_bounds_of(self.elements.get(id)?, depth, memo)
+    }
+
+    fn painted_bounds_of(&self, el: &Element, depth: usize, memo: &mut HashMap<String, Option<Rect>>) -> Option<Rect> {
+        let Shape::Group(group) = &el.shape else {
+            return Some(self.at_current_time(el).get_bounds());
+        };
+        if let Some(bounds) = memo.get(&el.id) {
+            return *bounds;
+        }
+        if depth > self.elements.len() {
+            return None;
+        }
+        // One pass up the children, tracking the mask below the current one.
+        let mut clip = Clip::Unclipped;
+        let mut bounds: Option<Rect> = None;
+        for c in &group.children {
+            let Some(child) = self.elements.get(c) else {
+                continue;
+            };
+            if child.mask.is_some() {
+                clip = Clip::Unclipped.and(self.painted_bounds_of(child, depth + 1, memo));
+                continue;
+            }
+            if let Some(painted) = self.painted_bounds_of(child, depth + 1, memo).and_then(|b| clip.apply(&b)) {
+                bounds = Some(bounds.map_or(painted, |b| b.union(&painted)));
+            }
+        }
+        let effects = &self.at_current_time(el).effects;
+        let bounds = bounds.map(|bounds| effects.iter().fold(bounds, |bounds, effect| effect.expand_bounds(&bounds)));
+        memo.insert(el.id.clone(), bounds);
+        bounds
+    }
+
+    /// Painted bounds clipped by every mask over the element and its
+    /// ancestors, or `None` when nothing of it is visible. Masks themselves
+    /// keep their own bounds so they can still be found, and picked when
+    /// hit-testing or marquee-selecting with `include_masks`.
+    pub fn clipped_bounds(&self, id: &str) -> Option<Rect> {
+        let mut bounds = self.painted_bounds(id)?;
+        for level in std::iter::once(id.to_string()).chain(self.ancestors(id)) {
+            if let Some(mask) = self.mask_for(&level) {
+                bounds = bounds.intersection(&self.painted_bounds(&mask.id)?)?;
+            }
+        }
+        Some(bounds)
+    }
+
+    /// `clipped_bounds` of every element in one pass down the hierarchy,
+    /// rather than searching each element's siblings for its mask.
+    pub fn all_clipped_bounds(&self) -> HashMap<String, Rect> {
+        let mut found = HashMap::new();
+        let mut visited = HashSet::new();
+        let mut painted = HashMap::new();
+        self.collect_clipped(None, Clip::Unclipped, &mut found, &mut visited, &mut painted);
+        // Elements the hierarchy does not reach, such as children of a missing group.
+        for id in self.elements.keys().filter(|id| !visited.contains(*id)) {
+            if let Some(bounds) = self.clipped_bounds(id) {
+                found.insert(id.clone(), bounds);
+            }
+        }
+        found
+    }
+
+    fn collect_clipped(
+        &self,
+        parent: Option<&str>,
+        clip: Clip,
+        found: &mut HashMap<String, Rect>,
+        visited: &mut HashSet<String>,
+        memo: &mut HashMap<String, Option<Rect>>,
+    ) {
+        // Painted bounds of the mask below the current sibling, once one is passed.
+        let mut mask: Option<Option<Rect>> = None;
+        for id in self.children(parent) {
+            let Some(el) = self.elements.get(&id) else {
+                continue;
+            };
+            if !visited.insert(id.clone()) {
+                continue;
+            }
+            let painted = self.painted_bounds_at(&id, 0, memo);
+            let clip = match mask {
+                Some(mask_bounds) if el.mask.is_none() => clip.and(mask_bounds),
+                _ => clip,
+            };
+            if el.mask.is_some() {
+                mask = Some(painted);
+            }
+            if let Some(bounds) = painted.and_then(|b| clip.apply(&b)) {
+                found.insert(id.clone(), bounds);
+            }
+            if matches!(el.shape, Shape::Group(_)) {
+                self.collect_clipped(Some(&id), clip, found, visited, memo);
+            }
+        }
+    }
+
+    fn paints(&self, id: &str, hits: &impl Fn(&Element) -> bool, depth: usize) -> bool {
+        let Some(el) = self.elements.get(id) else {
+            return false;
+        };
+        let Shape::Group(group) = &el.shape else {
+            return hits(&self.at_current_time(el));
+        };
+        if depth > self.elements.len() {
+            return false;
+        }
+        // Whether the mask below the current child passes, once one is passed.
+        let mut mask_hit = true;
+        group.children.iter().any(|c| match self.elements.get(c) {
+            Some(child) if child.mask.is_some() => {
+                mask_hit = self.paints(c, hits, depth + 1);
+                false
+            }
+            Some(_) => mask_hit && self.paints(c, hits, depth + 1),
+            None => false,
+        })
+    }
+
+    /// Whether the element, and every mask clipping it, pass `hits`.
+    fn masked_paints(&self, id: &str, hits: &impl Fn(&Element) -> bool) -> bool {
+        self.paints(id, hits, 0)
+            && std::iter::once(id.to_string())
+                .chain(self.ancestors(id))
+                .all(|level| self.mask_for(&level).is_none_or(|m| self.paints(&m.id, hits, 0)))
+    }
+
+    /// Whether `p` hits the element, respecting the masks that clip it.
+    pub fn element_contains(&self, id: &str, p: &Point) -> bool {
+        self.masked_paints(id, &|el: &Element| el.contains_point(p))
+    }
+
+    /// Whether the element's painted geometry reaches `rect`. Masks are
+    /// tested against the rect as a whole, so an element and its mask that
+    /// both reach the rect count even where they do not overlap each other.
+    pub fn element_intersects(&self, id: &str, rect: &Rect) -> bool {
+        self.masked_paints(id, &|el: &Element| el.intersects_rect(rect))
+    }
+
+    /// Like `element_intersects`, for an area bounded by `polygons`.
+    pub fn element_intersects_polygons(&self, id: &str, polygons: &[Vec<Point>]) -> bool {
+        self.masked_paints(id, &|el: &Element| crate::core::polygon::polygons_intersect(&el.painted_polygons(), polygons))
+    }
+}
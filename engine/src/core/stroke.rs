@@ -0,0 +1,562 @@
+use crate::core::geometry::{Point, Rect, Scalar, Shape, Vector};
+use crate::core::paint::Paint;
+use crate::core::path::{PathCommand, PathShape, DEFAULT_TOLERANCE};
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+/// Geometry of a stroke, independent of its paint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrokeStyle {
+    pub width: Scalar,
+    pub join: LineJoin,
+    pub miter_limit: Scalar,
+    pub cap: LineCap,
+    #[serde(default)]
+    pub dash_array: Vec<Scalar>,
+    #[serde(default)]
+    pub dash_offset: Scalar,
+}
+
+impl StrokeStyle {
+    pub fn new(width: f32) -> Self {
+        Self {
+            width: Scalar::from_num(width),
+            join: LineJoin::Miter,
+            miter_limit: Scalar::from_num(4),
+            cap: LineCap::Butt,
+            dash_array: Vec::new(),
+            dash_offset: Scalar::ZERO,
+        }
+    }
+
+    /// How far the painted stroke can reach past the geometry, given its
+    /// `reach` along a straight edge; miters and square caps stick out further.
+    pub fn padding(&self, reach: Scalar, sharp_corners: bool) -> Scalar {
+        let mut pad = reach;
+        if sharp_corners && self.join == LineJoin::Miter {
+            pad *= self.miter_limit.max(Scalar::ONE);
+        }
+        if self.cap == LineCap::Square {
+            // The corner of a square cap sits sqrt(2) times further out.
+            pad = pad.max(reach * Scalar::lit("1.4143"));
+        }
+        pad
+    }
+
+    /// The dash pattern with odd-length arrays repeated, as in SVG, or `None` when solid.
+    fn dash_pattern(&self) -> Option<Vec<Scalar>> {
+        if self.dash_array.is_empty() || self.dash_array.iter().any(|d| *d < Scalar::ZERO) {
+            return None;
+        }
+        let total: Scalar = self.dash_array.iter().copied().sum();
+        if total <= Scalar::ZERO {
+            return None;
+        }
+        let mut pattern = self.dash_array.clone();
+        if pattern.len() % 2 == 1 {
+            pattern.extend_from_slice(&self.dash_array);
+        }
+        Some(pattern)
+    }
+}
+
+/// A flattened run of points; `corners` marks vertices that came from path
+/// nodes (where the join style applies) rather than from curve flattening.
+#[derive(Debug, Clone)]
+struct StrokeRun {
+    points: Vec<Point>,
+    corners: Vec<bool>,
+    closed: bool,
+}
+
+impl StrokeRun {
+    fn push(&mut self, p: Point, corner: bool) {
+        if self.points.last() == Some(&p) {
+            if let Some(c) = self.corners.last_mut() {
+                *c |= corner;
+            }
+            return;
+        }
+        self.points.push(p);
+        self.corners.push(corner);
+    }
+}
+
+/// Left-hand normal of a unit direction.
+fn normal(d: &Vector) -> Vector {
+    Vector { x: -d.y, y: d.x }
+}
+
+fn runs_from_path(path: &PathShape, tolerance: Scalar) -> Vec<StrokeRun> {
+    let mut runs = Vec::new();
+    for (segments, closed) in path.subpath_segments() {
+        let Some(first) = segments.first() else { continue };
+        let mut run = StrokeRun { points: Vec::new(), corners: Vec::new(), closed };
+        run.push(first.start(), true);
+        for seg in &segments {
+            if let Some(c) = run.corners.last_mut() {
+                *c = true;
+            }
+            let mut pts = Vec::new();
+            seg.flatten_into(tolerance, &mut pts);
+            for p in pts {
+                run.push(p, false);
+            }
+        }
+        if let Some(c) = run.corners.last_mut() {
+            *c = true;
+        }
+        if closed && run.points.len() > 1 && run.points.first() == run.points.last() {
+            run.points.pop();
+            run.corners.pop();
+        }
+        runs.push(run);
+    }
+    runs
+}
+
+/// Most dashes a single run is split into. Finer patterns are stroked solid,
+/// as they could not be told apart from it anyway.
+const MAX_DASHES: usize = 10_000;
+
+/// Splits runs into the "on" pieces of the dash pattern. Each subpath restarts the pattern.
+fn apply_dashes(runs: Vec<StrokeRun>, pattern: &[Scalar], offset: Scalar) -> Vec<StrokeRun> {
+    let total: Scalar = pattern.iter().copied().sum();
+    let max_cycles = Scalar::from_num(MAX_DASHES / pattern.len());
+    let mut dashes = Vec::new();
+    for run in runs {
+        let mut length = run.points.windows(2).fold(Scalar::ZERO, |sum, w| sum.saturating_add(w[0].distance(&w[1])));
+        if run.closed && run.points.len() > 1 {
+            length = length.saturating_add(run.points[run.points.len() - 1].distance(&run.points[0]));
+        }
+        if length.checked_div(total).is_none_or(|cycles| cycles > max_cycles) {
+            dashes.push(run);
+            continue;
+        }
+
+        let mut points = run.points.clone();
+        let mut corners = run.corners.clone();
+        if run.closed && !points.is_empty() {
+            points.push(points[0]);
+            corners.push(corners[0]);
+        }
+
+        // Position in the pattern at the start of the run.
+        let mut phase = offset % total;
+        if phase < Scalar::ZERO {
+            phase += total;
+        }
+        let mut index = 0;
+        while phase >= pattern[index] {
+            phase -= pattern[index];
+            index = (index + 1) % pattern.len();
+        }
+        let mut remaining = pattern[index] - phase;
+        let mut current = (index % 2 == 0).then(|| StrokeRun { points: vec![points[0]], corners: vec![false], closed: false });
+
+        for i in 1..points.len() {
+            let mut from = points[i - 1];
+            let to = points[i];
+            let mut left = from.distance(&to);
+            while left > remaining {
+                let cut = from.lerp(&to, remaining / left);
+                left -= remaining;
+                from = cut;
+                match current.take() {
+                    Some(mut dash) => {
+                        dash.push(cut, false);
+                        dashes.push(dash);
+                    }
+                    None => current = Some(StrokeRun { points: vec![cut], corners: vec![false], closed: false }),
+                }
+                index = (index + 1) % pattern.len();
+                remaining = pattern[index];
+            }
+            remaining -= left;
+            if let Some(dash) = current.as_mut() {
+                dash.push(to, corners[i]);
+            }
+        }
+        dashes.extend(current);
+    }
+    dashes
+}
+
+/// Emits a circular arc from `center + from * radius` to `center + to * radius`
+/// as cubic beziers of at most 90 degrees each. `bulge` picks the side for half turns.
+fn arc_to(out: &mut Vec<PathCommand>, center: &Point, from: Vector, to: Vector, radius: Scalar, bulge: Vector) {
+    if from == to {
+        return;
+    }
+    if from.dot(&to) < Scalar::ZERO {
+        let mid = if from.dot(&to) <= Scalar::lit("-0.999") {
+            bulge
+        } else {
+            Vector { x: from.x + to.x, y: from.y + to.y }.normalize()
+        };
+        arc_to(out, center, from, mid, radius, bulge);
+        arc_to(out, center, mid, to, radius, bulge);
+        return;
+    }
+    let cos = from.dot(&to).clamp(-Scalar::ONE, Scalar::ONE);
+    let two = Scalar::from_num(2);
+    let half_sin = ((Scalar::ONE - cos) / two).sqrt();
+    let half_cos = ((Scalar::ONE + cos) / two).sqrt();
+    let k = Scalar::from_num(4) / Scalar::from_num(3) * half_sin / (Scalar::ONE + half_cos);
+    let t0 = Vector { x: to.x - from.x * cos, y: to.y - from.y * cos }.normalize();
+    let t1 = Vector { x: from.x - to.x * cos, y: from.y - to.y * cos }.normalize();
+    let p0 = center.offset(&from, radius);
+    let p1 = center.offset(&to, radius);
+    out.push(PathCommand::CurveTo(p0.offset(&t0, radius * k), p1.offset(&t1, radius * k), p1));
+}
+
+impl StrokeStyle {
+    fn half_width(&self) -> Scalar {
+        self.width / Scalar::from_num(2)
+    }
+
+    /// Offsets one side of a run (the left of its direction) with joins at every vertex.
+    /// With `through_pivot`, inner corners are routed through the vertex so the
+    /// overlap stays covered under nonzero fill; otherwise they cross directly.
+    pub(crate) fn side(&self, points: &[Point], corners: &[bool], closed: bool, through_pivot: bool, out: &mut Vec<PathCommand>) {
+        let hw = self.half_width();
+        let n = points.len();
+        let dirs: Vec<Vector> = (0..n)
+            .map(|i| points[(i + 1) % n].sub(&points[i]).normalize())
+            .collect();
+        let seg_count = if closed { n } else { n - 1 };
+
+        let vertex_range = if closed { 0..n } else { 1..n - 1 };
+        if !closed {
+            let start = points[0].offset(&normal(&dirs[0]), hw);
+            out.push(PathCommand::LineTo(start));
+        }
+        for i in vertex_range {
+            let d0 = dirs[(i + seg_count - 1) % seg_count];
+            let d1 = dirs[i % seg_count];
+            let n0 = normal(&d0);
+            let n1 = normal(&d1);
+            let p = points[i];
+            let a = p.offset(&n0, hw);
+            let b = p.offset(&n1, hw);
+            out.push(PathCommand::LineTo(a));
+            if a == b {
+                continue;
+            }
+            if d0.cross(&d1) > Scalar::ZERO {
+                if through_pivot {
+                    out.push(PathCommand::LineTo(p));
+                }
+                out.push(PathCommand::LineTo(b));
+                continue;
+            }
+            let join = if corners[i] { self.join } else { LineJoin::Miter };
+            match join {
+                LineJoin::Round => arc_to(out, &p, n0, n1, hw, d0),
+                LineJoin::Miter => {
+                    // The tip sits tan(turn / 2) half-widths past `a`; the miter ratio is sqrt(1 + tan²).
+                    let limit = if corners[i] { self.miter_limit } else { Scalar::from_num(4) };
+                    let tan_half = d0.cross(&d1).abs().checked_div(Scalar::ONE + d0.dot(&d1));
+                    if let Some(tan_half) = tan_half.filter(|t| *t * *t + Scalar::ONE <= limit * limit) {
+                        out.push(PathCommand::LineTo(a.offset(&d0, hw * tan_half)));
+                    }
+                }
+                LineJoin::Bevel => {}
+            }
+            out.push(PathCommand::LineTo(b));
+        }
+        if !closed {
+            let end = points[n - 1].offset(&normal(&dirs[n - 2]), hw);
+            out.push(PathCommand::LineTo(end));
+        }
+    }
+
+    /// Cap at `p` travelling in direction `d`, from the left side to the right side.
+    fn cap(&self, p: &Point, d: &Vector, out: &mut Vec<PathCommand>) {
+        let hw = self.half_width();
+        let n = normal(d);
+        let right = p.offset(&n, -hw);
+        match self.cap {
+            LineCap::Butt => out.push(PathCommand::LineTo(right)),
+            LineCap::Square => {
+                out.push(PathCommand::LineTo(p.offset(&n, hw).offset(d, hw)));
+                out.push(PathCommand::LineTo(right.offset(d, hw)));
+                out.push(PathCommand::LineTo(right));
+            }
+            LineCap::Round => arc_to(out, p, n, n.scale(-Scalar::ONE), hw, *d),
+        }
+    }
+
+    fn outline_run(&self, run: &StrokeRun, out: &mut PathShape) {
+        let hw = self.half_width();
+        if run.points.len() == 1 {
+            // A degenerate subpath only paints its caps, drawn along the x axis.
+            let p = run.points[0];
+            let d = Vector { x: Scalar::ONE, y: Scalar::ZERO };
+            if self.cap == LineCap::Butt {
+                return;
+            }
+            let back = d.scale(-Scalar::ONE);
+            let start = p.offset(&normal(&back), hw);
+            out.commands.push(PathCommand::MoveTo(start));
+            self.cap(&p, &back, &mut out.commands);
+            self.cap(&p, &d, &mut out.commands);
+            out.commands.push(PathCommand::Close);
+            return;
+        }
+
+        let mut reversed_points = run.points.clone();
+        let mut reversed_corners = run.corners.clone();
+        reversed_points.reverse();
+        reversed_corners.reverse();
+        if run.closed {
+            // Keep vertex 0 first so the two loops stay aligned.
+            reversed_points.rotate_right(1);
+            reversed_corners.rotate_right(1);
+        }
+
+        let mut forward = Vec::new();
+        self.side(&run.points, &run.corners, run.closed, true, &mut forward);
+        let mut backward = Vec::new();
+        self.side(&reversed_points, &reversed_corners, run.closed, true, &mut backward);
+
+        let start_of = |cmds: &[PathCommand]| match cmds.first() {
+            Some(PathCommand::LineTo(p)) => *p,
+            _ => run.points[0],
+        };
+        if run.closed {
+            for loop_cmds in [forward, backward] {
+                out.commands.push(PathCommand::MoveTo(start_of(&loop_cmds)));
+                out.commands.extend(loop_cmds.into_iter().skip(1));
+                out.commands.push(PathCommand::Close);
+            }
+        } else {
+            let n = run.points.len();
+            let end_dir = run.points[n - 1].sub(&run.points[n - 2]).normalize();
+            let start_dir = run.points[0].sub(&run.points[1]).normalize();
+            out.commands.push(PathCommand::MoveTo(start_of(&forward)));
+            out.commands.extend(forward.into_iter().skip(1));
+            self.cap(&run.points[n - 1], &end_dir, &mut out.commands);
+            out.commands.extend(backward.into_iter().skip(1));
+            self.cap(&run.points[0], &start_dir, &mut out.commands);
+            out.commands.push(PathCommand::Close);
+        }
+    }
+}
+
+impl PathShape {
+    /// Converts the stroke of this path into a filled outline (nonzero fill rule).
+    /// Curves are flattened to `tolerance`; round joins and caps stay as arcs.
+    pub fn stroke_outline(&self, style: &StrokeStyle, tolerance: Scalar) -> PathShape {
+        let mut outline = PathShape::new();
+        if style.width <= Scalar::ZERO {
+            return outline;
+        }
+        let mut runs = runs_from_path(self, tolerance);
+        if let Some(pattern) = style.dash_pattern() {
+            runs = apply_dashes(runs, &pattern, style.dash_offset);
+        }
+        for run in runs.iter().filter(|r| !r.points.is_empty()) {
+            style.outline_run(run, &mut outline);
+        }
+        outline
+    }
+
+    /// Whether `p` lies on the painted stroke of this path.
+    pub fn stroke_contains(&self, p: &Point, style: &StrokeStyle, tolerance: Scalar) -> bool {
+        self.stroke_bounds(style).contains(p) && self.stroke_outline(style, tolerance).contains(p)
+    }
+
+    /// Conservative bounds of the painted stroke, covering miters up to the limit.
+    pub fn stroke_bounds(&self, style: &StrokeStyle) -> Rect {
+        self.get_bounds().inflate(style.padding(style.half_width(), true))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StrokeAlignment {
+    Inside,
+    Center,
+    Outside,
+}
+
+/// A stroke as painted on an element.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stroke {
+    pub paint: Paint,
+    pub style: StrokeStyle,
+    pub alignment: StrokeAlignment,
+}
+
+impl Default for Stroke {
+    fn default() -> Self {
+        Self {
+            paint: Paint::default(),
+            style: StrokeStyle::new(1.0),
+            alignment: StrokeAlignment::Center,
+        }
+    }
+}
+
+impl Stroke {
+    /// Extent of the stroke past the shape edge along a straight side.
+    pub fn reach(&self) -> Scalar {
+        match self.alignment {
+            StrokeAlignment::Inside => Scalar::ZERO,
+            StrokeAlignment::Center => self.style.half_width(),
+            StrokeAlignment::Outside => self.style.width,
+        }
+    }
+
+    /// Bounds of `shape` including this stroke.
+    pub fn bounds(&self, shape: &Shape) -> Rect {
+        let sharp = !matches!(shape, Shape::Circle(_) | Shape::Ellipse(_));
+        shape.get_bounding_box().inflate(self.style.padding(self.reach(), sharp))
+    }
+
+    /// Whether `p` lies on the painted stroke of `shape`. Inside and outside
+    /// strokes are a centred stroke of twice the width, clipped to the fill.
+    pub fn contains(&self, shape: &Shape, p: &Point) -> bool {
+        if self.style.width <= Scalar::ZERO || !self.bounds(shape).contains(p) {
+            return false;
+        }
+        let Some(path) = shape.to_path() else {
+            return false;
+        };
+        if self.alignment == StrokeAlignment::Center {
+            return path.stroke_contains(p, &self.style, DEFAULT_TOLERANCE);
+        }
+        let doubled = StrokeStyle { width: self.style.width * Scalar::from_num(2), ..self.style.clone() };
+        path.stroke_contains(p, &doubled, DEFAULT_TOLERANCE)
+            && shape.contains_point(p) == (self.alignment == StrokeAlignment::Inside)
+    }
+
+    /// Outline that, together with the fill of `shape`, covers everything
+    /// the stroke paints. `None` for inside strokes, which stay within the
+    /// fill, and for shapes without a path.
+    pub fn band(&self, shape: &Shape) -> Option<PathShape> {
+        if self.style.width <= Scalar::ZERO || self.alignment == StrokeAlignment::Inside {
+            return None;
+        }
+        let path = shape.to_path()?;
+        let style = match self.alignment {
+            StrokeAlignment::Outside => StrokeStyle { width: self.style.width * Scalar::from_num(2), ..self.style.clone() },
+            _ => self.style.clone(),
+        };
+        Some(path.stroke_outline(&style, DEFAULT_TOLERANCE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_line_with_caps() {
+        let mut path = PathShape::new();
+        path.move_to(0.0, 0.0);
+        path.line_to(100.0, 0.0);
+
+        let mut style = StrokeStyle::new(10.0);
+        let outline = path.stroke_outline(&style, Scalar::ONE);
+        let bounds = outline.get_bounds();
+        assert_eq!(bounds, Rect::new(0.0, -5.0, 100.0, 10.0));
+        assert!(outline.contains(&Point::new(50.0, 4.0)));
+        assert!(!outline.contains(&Point::new(50.0, 6.0)));
+
+        style.cap = LineCap::Square;
+        assert_eq!(path.stroke_outline(&style, Scalar::ONE).get_bounds(), Rect::new(-5.0, -5.0, 110.0, 10.0));
+
+        style.cap = LineCap::Round;
+        assert!(path.stroke_contains(&Point::new(-4.0, 0.0), &style, Scalar::ONE));
+        assert!(!path.stroke_contains(&Point::new(-4.0, 4.0), &style, Scalar::ONE));
+    }
+
+    #[test]
+    fn test_closed_square_joins() {
+        let mut path = PathShape::new();
+        path.move_to(0.0, 0.0);
+        path.line_to(100.0, 0.0);
+        path.line_to(100.0, 100.0);
+        path.line_to(0.0, 100.0);
+        path.close();
+
+        let mut style = StrokeStyle::new(10.0);
+        let outline = path.stroke_outline(&style, Scalar::ONE);
+        assert_eq!(outline.get_bounds(), Rect::new(-5.0, -5.0, 110.0, 110.0));
+        assert!(outline.contains(&Point::new(-4.0, -4.0)));
+        assert!(!outline.contains(&Point::new(50.0, 50.0)));
+        assert!(outline.contains(&Point::new(50.0, 3.0)));
+
+        style.join = LineJoin::Bevel;
+        let outline = path.stroke_outline(&style, Scalar::ONE);
+        assert!(!outline.contains(&Point::new(-4.0, -4.0)));
+        assert!(outline.contains(&Point::new(-1.0, -1.0)));
+    }
+
+    #[test]
+    fn test_dashes_split_the_stroke() {
+        let mut path = PathShape::new();
+        path.move_to(0.0, 0.0);
+        path.line_to(100.0, 0.0);
+
+        let mut style = StrokeStyle::new(2.0);
+        style.dash_array = vec![Scalar::from_num(10)];
+        let outline = path.stroke_outline(&style, Scalar::ONE);
+        let dashes = outline.commands.iter().filter(|c| matches!(c, PathCommand::MoveTo(_))).count();
+        assert_eq!(dashes, 5);
+        assert!(outline.contains(&Point::new(5.0, 0.0)));
+        assert!(!outline.contains(&Point::new(15.0, 0.0)));
+
+        style.dash_offset = Scalar::from_num(5);
+        let outline = path.stroke_outline(&style, Scalar::ONE);
+        assert!(outline.contains(&Point::new(2.0, 0.0)));
+        assert!(!outline.contains(&Point::new(7.0, 0.0)));
+    }
+
+    #[test]
+    fn test_degenerate_dashes_stroke_solid() {
+        let mut path = PathShape::new();
+        path.move_to(0.0, 0.0);
+        path.line_to(1000.0, 0.0);
+
+        let mut style = StrokeStyle::new(2.0);
+        let solid = path.stroke_outline(&style, Scalar::ONE);
+        style.dash_array = vec![Scalar::lit("0.0001")];
+        assert_eq!(path.stroke_outline(&style, Scalar::ONE).commands, solid.commands);
+    }
+
+    #[test]
+    fn test_stroke_alignment() {
+        let shape = Shape::Rect(Rect::new(0.0, 0.0, 100.0, 100.0));
+        let mut stroke = Stroke { style: StrokeStyle::new(10.0), ..Stroke::default() };
+        stroke.style.join = LineJoin::Bevel;
+
+        assert_eq!(stroke.bounds(&shape), Rect::new(-5.0, -5.0, 110.0, 110.0));
+        assert!(stroke.contains(&shape, &Point::new(50.0, -4.0)));
+        assert!(stroke.contains(&shape, &Point::new(50.0, 4.0)));
+
+        stroke.alignment = StrokeAlignment::Inside;
+        assert_eq!(stroke.bounds(&shape), Rect::new(0.0, 0.0, 100.0, 100.0));
+        assert!(!stroke.contains(&shape, &Point::new(50.0, -4.0)));
+        assert!(stroke.contains(&shape, &Point::new(50.0, 9.0)));
+
+        stroke.alignment = StrokeAlignment::Outside;
+        assert_eq!(stroke.bounds(&shape), Rect::new(-10.0, -10.0, 120.0, 120.0));
+        assert!(stroke.contains(&shape, &Point::new(50.0, -9.0)));
+        assert!(!stroke.contains(&shape, &Point::new(50.0, 4.0)));
+    }
+}
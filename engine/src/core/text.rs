@@ -0,0 +1,725 @@
+use serde::{Serialize, Deserialize};
+use ttf_parser::GlyphId;
+use crate::core::font::{Font, FontLibrary, LineMetrics};
+use crate::core::geometry::{Point, Rect, Scalar, Vector};
+use crate::core::paint::Paint;
+use crate::core::path::{PathCommand, PathShape, DEFAULT_TOLERANCE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// A glyph placed by layout. Positions are relative to the text origin, with
+/// `y` on the baseline; `start..end` is the character range it draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlacedGlyph {
+    pub glyph_id: u16,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub x: Scalar,
+    pub y: Scalar,
+    pub advance: Scalar,
+    pub ink: Option<Rect>,
+    /// Index into `Text::spans` of the span styling this glyph.
+    #[serde(default)]
+    pub span: Option<usize>,
+    /// Unit baseline direction of a glyph set along a path; `None` for
+    /// straight text.
+    #[serde(default)]
+    pub direction: Option<Vector>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextLine {
+    pub start: usize,
+    pub end: usize,
+    pub x: Scalar,
+    pub top: Scalar,
+    pub baseline: Scalar,
+    pub width: Scalar,
+    pub height: Scalar,
+}
+
+/// Result of laying out a `Text`, cached on the shape so bounds and hit
+/// testing need no font access. Recomputed by the engine whenever the text
+/// or the loaded fonts change. For text on a path, lines describe the
+/// straight layout before it is bent onto the path.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextLayout {
+    pub width: Scalar,
+    pub height: Scalar,
+    pub lines: Vec<TextLine>,
+    pub glyphs: Vec<PlacedGlyph>,
+}
+
+/// Style overrides for a span; unset fields inherit from the text.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpanStyle {
+    pub font_family: Option<String>,
+    pub font_size: Option<Scalar>,
+    pub font_weight: Option<u16>,
+    pub fill: Option<Paint>,
+}
+
+impl SpanStyle {
+    fn is_empty(&self) -> bool {
+        *self == SpanStyle::default()
+    }
+
+    /// Overwrites the fields `other` sets.
+    fn apply(&mut self, other: &SpanStyle) {
+        if other.font_family.is_some() {
+            self.font_family.clone_from(&other.font_family);
+        }
+        self.font_size = other.font_size.or(self.font_size);
+        self.font_weight = other.font_weight.or(self.font_weight);
+        if other.fill.is_some() {
+            self.fill.clone_from(&other.fill);
+        }
+    }
+}
+
+/// Styled characters `start..end`. Spans are kept sorted, disjoint and
+/// non-empty; characters outside every span use the text's own style.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextSpan {
+    pub start: usize,
+    pub end: usize,
+    pub style: SpanStyle,
+}
+
+/// A path the text runs along, in the text's local space (relative to its
+/// origin). The first baseline follows the path; later lines stack along
+/// its normal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextPath {
+    pub path: PathShape,
+    /// Distance along the path at which the text starts.
+    pub offset: Scalar,
+}
+
+/// Keyframeable text properties that change the layout. The origin is not
+/// among them, as glyphs are placed relative to it.
+pub const LAYOUT_PROPERTIES: &[&str] = &["font_size", "line_height", "letter_spacing", "width", "path_offset"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Text {
+    /// Top-left corner of the text box.
+    pub origin: Point,
+    pub content: String,
+    pub font_family: String,
+    pub font_size: Scalar,
+    pub font_weight: u16,
+    /// Line box height as a multiple of the font size.
+    pub line_height: Scalar,
+    /// Extra space after every character, in pixels.
+    pub letter_spacing: Scalar,
+    pub align: TextAlign,
+    /// Fixed box width that lines wrap within; `None` sizes the box to the
+    /// longest line.
+    pub width: Option<Scalar>,
+    #[serde(default)]
+    pub spans: Vec<TextSpan>,
+    #[serde(default)]
+    pub path: Option<TextPath>,
+    #[serde(default)]
+    pub layout: TextLayout,
+}
+
+/// Characters laid out as one unit: a single character, or several once
+/// shaping forms ligatures.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Cluster {
+    pub glyph_id: u16,
+    pub start: usize,
+    pub end: usize,
+    pub advance: Scalar,
+    /// Shaping displacement from the pen position, y down.
+    pub offset: Vector,
+    pub ink: Option<Rect>,
+    pub kind: ClusterKind,
+    pub span: Option<usize>,
+    /// Line box extent above and below the baseline for this cluster's style.
+    pub above: Scalar,
+    pub below: Scalar,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ClusterKind {
+    Glyph,
+    Space,
+    Newline,
+}
+
+impl ClusterKind {
+    fn of(ch: char) -> ClusterKind {
+        match ch {
+            '\n' => ClusterKind::Newline,
+            c if c.is_whitespace() => ClusterKind::Space,
+            _ => ClusterKind::Glyph,
+        }
+    }
+}
+
+/// Metrics used when no matching font is loaded.
+fn fallback_metrics(size: Scalar) -> LineMetrics {
+    LineMetrics { ascent: size * Scalar::lit("0.8"), descent: size * Scalar::lit("0.2"), line_gap: Scalar::ZERO }
+}
+
+/// Splits a line box of `line_height` around the baseline, sharing the
+/// leading equally above and below as CSS does.
+fn line_extent(metrics: &LineMetrics, line_height: Scalar) -> (Scalar, Scalar) {
+    let above = (line_height - metrics.ascent - metrics.descent) / 2 + metrics.ascent;
+    (above, line_height - above)
+}
+
+/// Shapes one uniformly styled run into clusters. `first` is the character
+/// index the run starts at; `template` carries the run's style fields.
+fn shape_run(font: &Font, text: &str, size: Scalar, first: usize, ligatures: bool, template: Cluster) -> Vec<Cluster> {
+    // Shaping reports byte offsets; layout works in characters.
+    let char_starts: Vec<usize> = text.char_indices().map(|(b, _)| b).collect();
+    let char_at = |byte: usize| first + char_starts.partition_point(|&b| b < byte);
+    let shaped = font.shape(text, size, ligatures);
+    shaped
+        .iter()
+        .enumerate()
+        .map(|(i, g)| {
+            let next = shaped[i + 1..].iter().map(|n| n.cluster).find(|&c| c > g.cluster);
+            let kind = text[g.cluster..].chars().next().map_or(ClusterKind::Glyph, ClusterKind::of);
+            let (advance, ink) = match kind {
+                ClusterKind::Newline => (Scalar::ZERO, None),
+                _ => (g.advance, font.ink_bounds(g.glyph, size)),
+            };
+            let (start, end) = (char_at(g.cluster), next.map_or(first + char_starts.len(), char_at));
+            Cluster { glyph_id: g.glyph.0, start, end, advance, offset: g.offset, ink, kind, ..template }
+        })
+        .collect()
+}
+
+/// Maps a point from a glyph's frame (x along `direction`, y along its
+/// normal) to the frame the glyph is placed in.
+fn turn(p: Point, direction: Vector, origin: Point) -> Point {
+    Point {
+        x: origin.x + direction.x * p.x - direction.y * p.y,
+        y: origin.y + direction.y * p.x + direction.x * p.y,
+    }
+}
+
+/// Bounding box of `r` after `turn`.
+fn turn_rect(r: &Rect, direction: Vector, origin: Point) -> Rect {
+    let (x0, y0) = (r.origin.x, r.origin.y);
+    let (x1, y1) = (x0 + r.width, y0 + r.height);
+    let corners = [(x0, y0), (x1, y0), (x1, y1), (x0, y1)].map(|(x, y)| turn(Point { x, y }, direction, origin));
+    let min_x = corners.iter().map(|p| p.x).min().unwrap_or(origin.x);
+    let min_y = corners.iter().map(|p| p.y).min().unwrap_or(origin.y);
+    let max_x = corners.iter().map(|p| p.x).max().unwrap_or(origin.x);
+    let max_y = corners.iter().map(|p| p.y).max().unwrap_or(origin.y);
+    Rect { origin: Point { x: min_x, y: min_y }, width: max_x - min_x, height: max_y - min_y }
+}
+
+impl Text {
+    pub fn new(x: f32, y: f32, content: &str, font_family: &str, font_size: f32) -> Self {
+        Self {
+            origin: Point::new(x, y),
+            content: content.to_string(),
+            font_family: font_family.to_string(),
+            font_size: Scalar::from_num(font_size),
+            font_weight: 400,
+            line_height: Scalar::lit("1.2"),
+            letter_spacing: Scalar::ZERO,
+            align: TextAlign::Left,
+            width: None,
+            spans: Vec::new(),
+            path: None,
+            layout: TextLayout::default(),
+        }
+    }
+
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        self.origin.x += Scalar::from_num(dx);
+        self.origin.y += Scalar::from_num(dy);
+    }
+
+    pub fn char_count(&self) -> usize {
+        self.content.chars().count()
+    }
+
+    /// Family, size and weight in effect for a span, or the text's own for `None`.
+    pub fn span_font(&self, span: Option<usize>) -> (&str, Scalar, u16) {
+        let style = span.and_then(|i| self.spans.get(i)).map(|s| &s.style);
+        (
+            style.and_then(|s| s.font_family.as_deref()).unwrap_or(&self.font_family),
+            style.and_then(|s| s.font_size).unwrap_or(self.font_size),
+            style.and_then(|s| s.font_weight).unwrap_or(self.font_weight),
+        )
+    }
+
+    /// Per-character span styles, the form range edits work on.
+    fn char_styles(&self) -> Vec<SpanStyle> {
+        let mut styles = vec![SpanStyle::default(); self.char_count()];
+        for span in &self.spans {
+            let end = span.end.min(styles.len());
+            for style in styles.iter_mut().take(end).skip(span.start) {
+                style.clone_from(&span.style);
+            }
+        }
+        styles
+    }
+
+    /// Rebuilds `spans` from per-character styles, merging equal neighbours.
+    fn set_char_styles(&mut self, styles: Vec<SpanStyle>) {
+        self.spans.clear();
+        for (i, style) in styles.into_iter().enumerate() {
+            match self.spans.last_mut() {
+                Some(last) if last.end == i && last.style == style => last.end += 1,
+                _ if style.is_empty() => {}
+                _ => self.spans.push(TextSpan { start: i, end: i + 1, style }),
+            }
+        }
+    }
+
+    /// Applies the fields `style` sets to characters `start..end`.
+    pub fn set_span_style(&mut self, start: usize, end: usize, style: &SpanStyle) {
+        let mut styles = self.char_styles();
+        let end = end.min(styles.len());
+        for s in styles.iter_mut().take(end).skip(start) {
+            s.apply(style);
+        }
+        self.set_char_styles(styles);
+    }
+
+    /// Returns characters `start..end` to the text's own style.
+    pub fn clear_span_style(&mut self, start: usize, end: usize) {
+        let mut styles = self.char_styles();
+        let end = end.min(styles.len());
+        for s in styles.iter_mut().take(end).skip(start) {
+            *s = SpanStyle::default();
+        }
+        self.set_char_styles(styles);
+    }
+
+    /// Replaces characters `start..end` with `text`, keeping spans on the
+    /// surrounding characters. Inserted text takes the style of the
+    /// character before it, or of the first replaced one at the very start.
+    pub fn replace_range(&mut self, start: usize, end: usize, text: &str) {
+        let mut styles = self.char_styles();
+        let end = end.min(styles.len());
+        let start = start.min(end);
+        let inherited = match start {
+            0 => styles.first().filter(|_| end > 0).cloned().unwrap_or_default(),
+            i => styles[i - 1].clone(),
+        };
+        let inserted = text.chars().count();
+        styles.splice(start..end, std::iter::repeat_n(inherited, inserted));
+
+        let byte = |i: usize| self.content.char_indices().nth(i).map_or(self.content.len(), |(b, _)| b);
+        let (from, to) = (byte(start), byte(end));
+        self.content.replace_range(from..to, text);
+        self.set_char_styles(styles);
+    }
+
+    /// Character ranges of uniform style covering the content, tolerating
+    /// spans that overlap or run past the end.
+    fn runs(&self) -> Vec<(usize, usize, Option<usize>)> {
+        let count = self.char_count();
+        let mut runs = Vec::new();
+        let mut pos = 0;
+        for (i, span) in self.spans.iter().enumerate() {
+            let (start, end) = (span.start.max(pos).min(count), span.end.min(count));
+            if start >= end {
+                continue;
+            }
+            if start > pos {
+                runs.push((pos, start, None));
+            }
+            runs.push((start, end, Some(i)));
+            pos = end;
+        }
+        if pos < count {
+            runs.push((pos, count, None));
+        }
+        runs
+    }
+
+    /// Shaped clusters for every run, plus the line box extent of the text's
+    /// own style, which every line has at least. Runs without a matching
+    /// font get fixed fallback metrics so layout stays stable until the font
+    /// is loaded.
+    fn measure(&self, fonts: &FontLibrary) -> ((Scalar, Scalar), Vec<Cluster>) {
+        let resolve = |family: &str, size: Scalar, weight: u16| {
+            let font = fonts.resolve(family, weight);
+            let metrics = font.map_or_else(|| fallback_metrics(size), |f| f.line_metrics(size));
+            (font, line_extent(&metrics, size * self.line_height))
+        };
+        let (_, strut) = resolve(&self.font_family, self.font_size, self.font_weight);
+        // Letter spacing would pull ligature components apart, so as in CSS
+        // it turns optional ligatures off.
+        let ligatures = self.letter_spacing == Scalar::ZERO;
+
+        let char_starts: Vec<usize> = self.content.char_indices().map(|(b, _)| b).collect();
+        let byte = |i: usize| char_starts.get(i).copied().unwrap_or(self.content.len());
+        let mut clusters = Vec::new();
+        for (start, end, span) in self.runs() {
+            let (family, size, weight) = self.span_font(span);
+            let (font, (above, below)) = resolve(family, size, weight);
+            let text = &self.content[byte(start)..byte(end)];
+            let template = Cluster {
+                glyph_id: 0,
+                start,
+                end,
+                advance: Scalar::ZERO,
+                offset: Vector::zero(),
+                ink: None,
+                kind: ClusterKind::Glyph,
+                span,
+                above,
+                below,
+            };
+            match font {
+                Some(font) => clusters.extend(shape_run(font, text, size, start, ligatures, template)),
+                None => clusters.extend(text.chars().enumerate().map(|(i, ch)| {
+                    let kind = ClusterKind::of(ch);
+                    let advance = match kind {
+                        ClusterKind::Newline => Scalar::ZERO,
+                        ClusterKind::Space => size / 4,
+                        ClusterKind::Glyph => size / 2,
+                    };
+                    Cluster { start: start + i, end: start + i + 1, advance, kind, ..template }
+                })),
+            }
+        }
+        (strut, clusters)
+    }
+
+    pub fn compute_layout(&self, fonts: &FontLibrary) -> TextLayout {
+        let (strut, clusters) = self.measure(fonts);
+        let mut layout = self.place(strut, clusters);
+        if let Some(text_path) = &self.path {
+            follow_path(&mut layout, text_path);
+        }
+        layout
+    }
+
+    pub fn relayout(&mut self, fonts: &FontLibrary) {
+        self.layout = self.compute_layout(fonts);
+    }
+
+    /// Breaks clusters into lines and positions them. Lines wrap after
+    /// whitespace, or mid-word when a single word is wider than the box.
+    /// Each line is tall enough for the largest style on it.
+    pub(crate) fn place(&self, strut: (Scalar, Scalar), mut clusters: Vec<Cluster>) -> TextLayout {
+        for c in clusters.iter_mut().filter(|c| c.kind != ClusterKind::Newline) {
+            c.advance += self.letter_spacing;
+        }
+
+        let mut ranges = Vec::new();
+        let (mut start, mut width, mut break_at) = (0, Scalar::ZERO, None);
+        let mut i = 0;
+        while i < clusters.len() {
+            let c = clusters[i];
+            match c.kind {
+                ClusterKind::Newline => {
+                    ranges.push((start, i));
+                    (start, width, break_at) = (i + 1, Scalar::ZERO, None);
+                }
+                ClusterKind::Space => {
+                    width += c.advance;
+                    break_at = Some(i + 1);
+                }
+                ClusterKind::Glyph => {
+                    if self.width.is_some_and(|max| width + c.advance > max) && i > start {
+                        let cut = break_at.unwrap_or(i);
+                        ranges.push((start, cut));
+                        start = cut;
+                        width = clusters[start..i].iter().map(|c| c.advance).sum();
+                        break_at = None;
+                        // Re-check this cluster against the fresh line.
+                        continue;
+                    }
+                    width += c.advance;
+                }
+            }
+            i += 1;
+        }
+        ranges.push((start, clusters.len()));
+
+        // Trailing whitespace hangs past the line end and does not count.
+        let line_width = |&(s, e): &(usize, usize)| -> Scalar {
+            let visible = clusters[s..e].iter().rposition(|c| c.kind == ClusterKind::Glyph).map_or(s, |p| s + p + 1);
+            clusters[s..visible].iter().map(|c| c.advance).sum()
+        };
+        let box_width = self.width.unwrap_or_else(|| ranges.iter().map(line_width).max().unwrap_or(Scalar::ZERO));
+
+        let mut layout = TextLayout { width: box_width, ..Default::default() };
+        let mut top = Scalar::ZERO;
+        for (n, range) in ranges.iter().enumerate() {
+            let width = line_width(range);
+            let x = match self.align {
+                TextAlign::Left => Scalar::ZERO,
+                TextAlign::Center => (box_width - width) / 2,
+                TextAlign::Right => box_width - width,
+            };
+            let line = &clusters[range.0..range.1];
+            let above = line.iter().map(|c| c.above).fold(strut.0, Scalar::max);
+            let below = line.iter().map(|c| c.below).fold(strut.1, Scalar::max);
+            let baseline = top + above;
+            let char_range = |i: usize| clusters.get(i).map_or(self.char_count(), |c| c.start);
+            layout.lines.push(TextLine { start: char_range(range.0), end: char_range(range.1), x, top, baseline, width, height: above + below });
+
+            let mut pen = x;
+            for c in line.iter().filter(|c| c.kind != ClusterKind::Newline) {
+                let (x, y) = (pen + c.offset.x, baseline + c.offset.y);
+                let ink = c.ink.map(|r| Rect { origin: Point { x: r.origin.x + x, y: r.origin.y + y }, ..r });
+                layout.glyphs.push(PlacedGlyph {
+                    glyph_id: c.glyph_id,
+                    start: c.start,
+                    end: c.end,
+                    line: n,
+                    x,
+                    y,
+                    advance: c.advance,
+                    ink,
+                    span: c.span,
+                    direction: None,
+                });
+                pen += c.advance;
+            }
+            top += above + below;
+        }
+        layout.height = top;
+        layout
+    }
+
+    /// Glyph outlines of the laid out text as one path in canvas space; span
+    /// fills are not kept. `None` when a font in use is not loaded, as
+    /// fallback boxes have no outlines.
+    pub fn to_outlines(&self, fonts: &FontLibrary) -> Option<PathShape> {
+        let mut path = PathShape::new();
+        for g in &self.compute_layout(fonts).glyphs {
+            let (family, size, weight) = self.span_font(g.span);
+            let font = fonts.resolve(family, weight)?;
+            let Some(outline) = font.glyph_outline(GlyphId(g.glyph_id), size) else {
+                continue;
+            };
+            let direction = g.direction.unwrap_or(Vector { x: Scalar::ONE, y: Scalar::ZERO });
+            let at = Point { x: self.origin.x + g.x, y: self.origin.y + g.y };
+            path.commands.extend(outline.commands.into_iter().map(|cmd| match cmd {
+                PathCommand::MoveTo(p) => PathCommand::MoveTo(turn(p, direction, at)),
+                PathCommand::LineTo(p) => PathCommand::LineTo(turn(p, direction, at)),
+                PathCommand::CurveTo(a, b, p) => PathCommand::CurveTo(turn(a, direction, at), turn(b, direction, at), turn(p, direction, at)),
+                PathCommand::Close => PathCommand::Close,
+            }));
+        }
+        Some(path)
+    }
+
+    fn at_origin(&self, r: &Rect) -> Rect {
+        Rect { origin: Point { x: r.origin.x + self.origin.x, y: r.origin.y + self.origin.y }, ..*r }
+    }
+
+    /// Line boxes of non-empty lines plus any ink that overhangs them. On a
+    /// path each glyph instead covers its own slice of the line box, turned
+    /// with the glyph.
+    pub fn painted_rects(&self) -> Vec<Rect> {
+        let layout = &self.layout;
+        let boxes: Vec<Rect> = if self.path.is_some() {
+            layout
+                .glyphs
+                .iter()
+                .filter_map(|g| {
+                    let line = layout.lines.get(g.line)?;
+                    let cell = Rect { origin: Point { x: Scalar::ZERO, y: line.top - line.baseline }, width: g.advance, height: line.height };
+                    Some(turn_rect(&cell, g.direction?, Point { x: g.x, y: g.y }))
+                })
+                .collect()
+        } else {
+            layout
+                .lines
+                .iter()
+                .filter(|l| l.width > Scalar::ZERO)
+                .map(|l| Rect { origin: Point { x: l.x, y: l.top }, width: l.width, height: l.height })
+                .collect()
+        };
+        let inks = layout.glyphs.iter().filter_map(|g| g.ink);
+        boxes.into_iter().chain(inks).map(|r| self.at_origin(&r)).collect()
+    }
+
+    pub fn get_bounding_box(&self) -> Rect {
+        self.painted_rects()
+            .into_iter()
+            .reduce(|a, b| a.union(&b))
+            .unwrap_or(Rect { origin: self.origin, width: Scalar::ZERO, height: self.layout.height })
+    }
+
+    pub fn contains(&self, p: &Point) -> bool {
+        self.painted_rects().iter().any(|r| r.contains(p))
+    }
+
+    pub fn intersects_rect(&self, rect: &Rect) -> bool {
+        self.painted_rects().iter().any(|r| r.intersects(rect))
+    }
+}
+
+/// Bends a straight layout onto a path: each glyph's centre moves to the
+/// point at its distance along the path, offset along the normal by its
+/// distance from the first baseline. Glyphs whose centre falls off either
+/// end of the path are dropped, as in SVG.
+fn follow_path(layout: &mut TextLayout, text_path: &TextPath) {
+    let path = text_path.path.measure(DEFAULT_TOLERANCE);
+    let length = path.length();
+    let first_baseline = layout.lines.first().map_or(Scalar::ZERO, |l| l.baseline);
+    layout.glyphs.retain_mut(|g| {
+        let half = g.advance / 2;
+        let distance = text_path.offset + g.x + half;
+        if distance < Scalar::ZERO || distance > length {
+            return false;
+        }
+        let Some(sample) = path.sample(distance) else {
+            return false;
+        };
+        let direction = sample.tangent;
+        let origin = turn(Point { x: -half, y: g.y - first_baseline }, direction, sample.point);
+        g.ink = g.ink.map(|r| {
+            let local = Rect { origin: Point { x: r.origin.x - g.x, y: r.origin.y - g.y }, ..r };
+            turn_rect(&local, direction, origin)
+        });
+        (g.x, g.y, g.direction) = (origin.x, origin.y, Some(direction));
+        true
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::font::tests::test_fonts;
+
+    #[test]
+    fn test_wrapping_and_alignment() {
+        let fonts = test_fonts();
+        // At 10px: "A" and "V" advance 6, "i" 2.5, space 2.5; ascent 8, descent 2.
+        let mut text = Text::new(100.0, 50.0, "AV AV\nA", "Kinetic Test", 10.0);
+        text.line_height = Scalar::lit("1.5");
+        text.relayout(&fonts);
+        assert_eq!(text.layout.lines.len(), 2);
+        assert_eq!(text.layout.width, Scalar::lit("26.5"));
+        assert_eq!(text.layout.lines[0].baseline, Scalar::lit("10.5"));
+        assert_eq!(text.layout.lines[1].start, 6);
+
+        text.width = Some(Scalar::from_num(14));
+        text.align = TextAlign::Right;
+        text.relayout(&fonts);
+        let lines: Vec<(usize, usize)> = text.layout.lines.iter().map(|l| (l.start, l.end)).collect();
+        assert_eq!(lines, vec![(0, 3), (3, 5), (6, 7)]);
+        // The trailing space hangs, so "AV" right-aligns flush to the 14px box.
+        assert_eq!(text.layout.lines[0].x, Scalar::from_num(2));
+        assert_eq!(text.get_bounding_box(), Rect::new(102.0, 50.0, 12.0, 45.0));
+        assert!(text.contains(&Point::new(113.0, 55.0)));
+        assert!(!text.contains(&Point::new(103.0, 82.0)));
+
+        // A word wider than the box breaks between characters.
+        text.content = "AAAA".to_string();
+        text.relayout(&fonts);
+        assert_eq!(text.layout.lines.len(), 2);
+    }
+
+    #[test]
+    fn test_weight_and_fallback_metrics() {
+        let fonts = test_fonts();
+        let mut text = Text::new(0.0, 0.0, "AA", "Kinetic Test", 10.0);
+        text.font_weight = 700;
+        text.letter_spacing = Scalar::ONE;
+        assert_eq!(text.compute_layout(&fonts).width, Scalar::from_num(15));
+
+        text.font_family = "Not Loaded".to_string();
+        let layout = text.compute_layout(&fonts);
+        assert_eq!(layout.width, Scalar::from_num(12));
+        assert!(layout.glyphs.iter().all(|g| g.ink.is_none()));
+    }
+
+    #[test]
+    fn test_shaping_and_outlines() {
+        let fonts = test_fonts();
+        // "V" followed by "A" kerns by -100 units, -1px at 10px.
+        let text = Text::new(0.0, 0.0, "VA AV", "Kinetic Test", 10.0);
+        let layout = text.compute_layout(&fonts);
+        assert_eq!(layout.glyphs[1].x, Scalar::from_num(5));
+        assert_eq!(layout.width, Scalar::lit("25.5"));
+
+        // "fi" forms a single ligature glyph covering both characters...
+        let mut text = Text::new(0.0, 0.0, "fio", "Kinetic Test", 10.0);
+        let layout = text.compute_layout(&fonts);
+        let ranges: Vec<(usize, usize)> = layout.glyphs.iter().map(|g| (g.start, g.end)).collect();
+        assert_eq!(ranges, vec![(0, 2), (2, 3)]);
+        assert_eq!(layout.width, Scalar::from_num(10));
+        // ...unless letter spacing pulls the letters apart.
+        text.letter_spacing = Scalar::ONE;
+        assert_eq!(text.compute_layout(&fonts).glyphs.len(), 3);
+
+        let mut text = Text::new(100.0, 50.0, "A", "Kinetic Test", 10.0);
+        text.line_height = Scalar::ONE;
+        text.relayout(&fonts);
+        let outlines = text.to_outlines(&fonts).unwrap();
+        assert_eq!(outlines.get_bounds(), Rect::new(100.0, 51.0, 6.0, 7.0));
+        assert_eq!(outlines.get_bounds(), text.get_bounding_box().intersection(&outlines.get_bounds()).unwrap());
+        text.font_family = "Not Loaded".to_string();
+        assert!(text.to_outlines(&fonts).is_none());
+    }
+
+    #[test]
+    fn test_spans_follow_range_edits() {
+        let fonts = test_fonts();
+        let mut text = Text::new(0.0, 0.0, "AAAA", "Kinetic Test", 10.0);
+        text.line_height = Scalar::lit("1.5");
+        let big = SpanStyle { font_size: Some(Scalar::from_num(20)), ..Default::default() };
+        text.set_span_style(1, 3, &big);
+        let layout = text.compute_layout(&fonts);
+        assert_eq!(layout.width, Scalar::from_num(36));
+        // The 20px span sets the line box: 30px tall with its baseline at 21.
+        assert_eq!((layout.lines[0].height, layout.lines[0].baseline), (Scalar::from_num(30), Scalar::from_num(21)));
+        assert_eq!(layout.glyphs[1].span, Some(0));
+
+        let ranges = |t: &Text| t.spans.iter().map(|s| (s.start, s.end)).collect::<Vec<_>>();
+        // Inserted text picks up the style before it; deletions shift spans.
+        text.replace_range(2, 2, "VV");
+        assert_eq!((text.content.as_str(), ranges(&text)), ("AAVVAA", vec![(1, 5)]));
+        text.replace_range(0, 1, "");
+        assert_eq!(ranges(&text), vec![(0, 4)]);
+        text.clear_span_style(0, 1);
+        text.set_span_style(2, 3, &SpanStyle { font_weight: Some(700), ..Default::default() });
+        assert_eq!(ranges(&text), vec![(1, 2), (2, 3), (3, 4)]);
+        assert_eq!(text.span_font(Some(1)), ("Kinetic Test", Scalar::from_num(20), 700));
+        text.clear_span_style(0, 10);
+        assert!(text.spans.is_empty());
+    }
+
+    #[test]
+    fn test_text_on_path() {
+        let fonts = test_fonts();
+        let mut path = PathShape::new();
+        path.move_to(0.0, 0.0);
+        // 128px long, so distances along it are exact in fixed point.
+        path.line_to(0.0, 128.0);
+        let mut text = Text::new(50.0, 50.0, "AV", "Kinetic Test", 10.0);
+        text.path = Some(TextPath { path, offset: Scalar::from_num(10) });
+        text.relayout(&fonts);
+        // Running down the path, "A" is centred 13px along it and turned a
+        // quarter clockwise, so its top faces +x.
+        let a = text.layout.glyphs[0];
+        assert_eq!((a.x, a.y), (Scalar::ZERO, Scalar::from_num(10)));
+        assert_eq!(a.direction, Some(Vector::new(0.0, 1.0)));
+        assert_eq!(a.ink, Some(Rect::new(0.0, 10.0, 7.0, 6.0)));
+        assert_eq!(text.to_outlines(&fonts).unwrap().get_bounds(), Rect::new(50.0, 60.0, 7.0, 12.0));
+        assert!(text.contains(&Point::new(48.0, 62.0)));
+        assert!(!text.contains(&Point::new(62.0, 62.0)));
+
+        // Glyphs whose centre runs past the end are dropped.
+        text.path.as_mut().unwrap().offset = Scalar::from_num(123);
+        text.relayout(&fonts);
+        assert_eq!(text.layout.glyphs.len(), 1);
+    }
+}
@@ -0,0 +1,480 @@
+//! WOFF2 decoding into a plain sfnt (TrueType/OpenType) font, including the
+//! reversal of the `glyf`/`loca` and `hmtx` table transforms.
+
+use std::io::Read;
+
+const SIGNATURE: u32 = 0x774F_4632; // "wOF2"
+const COLLECTION: u32 = 0x7474_6366; // "ttcf"
+/// Refuse to inflate beyond this, whatever the header claims.
+const MAX_DECODED_SIZE: u64 = 64 * 1024 * 1024;
+
+const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm", b"glyf",
+    b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern", b"LTSH", b"PCLT",
+    b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC", b"JSTF", b"MATH", b"CBDT",
+    b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar", b"bdat", b"bloc", b"bsln", b"cvar",
+    b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty", b"just", b"lcar", b"mort", b"morx", b"opbd",
+    b"prop", b"trak", b"Zapf", b"Silf", b"Glat", b"Gloc", b"Feat", b"Sill",
+];
+
+pub fn is_woff2(data: &[u8]) -> bool {
+    data.len() >= 4 && u32::from_be_bytes([data[0], data[1], data[2], data[3]]) == SIGNATURE
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(n).filter(|&e| e <= self.data.len()).ok_or("truncated WOFF2 data")?;
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16, String> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn base128(&mut self) -> Result<u32, String> {
+        let mut value: u32 = 0;
+        for i in 0..5 {
+            let byte = self.u8()?;
+            if i == 0 && byte == 0x80 {
+                return Err("invalid UIntBase128".to_string());
+            }
+            if value & 0xFE00_0000 != 0 {
+                return Err("UIntBase128 overflow".to_string());
+            }
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("UIntBase128 too long".to_string())
+    }
+
+    fn u255(&mut self) -> Result<u16, String> {
+        Ok(match self.u8()? {
+            253 => self.u16()?,
+            254 => self.u8()? as u16 + 506,
+            255 => self.u8()? as u16 + 253,
+            code => code as u16,
+        })
+    }
+}
+
+struct TableEntry {
+    tag: [u8; 4],
+    transformed: bool,
+    orig_length: u32,
+    stored_length: u32,
+}
+
+/// Decodes a WOFF2 file into sfnt bytes that `ttf-parser` can read.
+pub fn decode(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut r = Reader::new(data);
+    if r.u32()? != SIGNATURE {
+        return Err("not a WOFF2 file".to_string());
+    }
+    let flavor = r.u32()?;
+    if flavor == COLLECTION {
+        return Err("WOFF2 font collections are not supported".to_string());
+    }
+    let _length = r.u32()?;
+    let num_tables = r.u16()?;
+    let _reserved = r.u16()?;
+    let _total_sfnt_size = r.u32()?;
+    let compressed_size = r.u32()?;
+    r.bytes(4 + 4 * 5)?; // version, metadata and private block locations
+
+    let mut tables = Vec::with_capacity(num_tables as usize);
+    for _ in 0..num_tables {
+        let flags = r.u8()?;
+        let tag = match flags & 0x3F {
+            63 => {
+                let b = r.bytes(4)?;
+                [b[0], b[1], b[2], b[3]]
+            }
+            i => *KNOWN_TAGS[i as usize],
+        };
+        let version = flags >> 6;
+        let orig_length = r.base128()?;
+        // For glyf and loca version 0 is the transform and 3 the null one; for
+        // every other table it is the other way round.
+        let transformed = if &tag == b"glyf" || &tag == b"loca" { version == 0 } else { version != 0 };
+        let stored_length = if transformed { r.base128()? } else { orig_length };
+        tables.push(TableEntry { tag, transformed, orig_length, stored_length });
+    }
+
+    let compressed = r.bytes(compressed_size as usize)?;
+    let mut stream = Vec::new();
+    brotli_decompressor::Decompressor::new(compressed, 4096)
+        .take(MAX_DECODED_SIZE)
+        .read_to_end(&mut stream)
+        .map_err(|e| format!("WOFF2 decompression failed: {}", e))?;
+
+    let mut raw: Vec<(&TableEntry, &[u8])> = Vec::new();
+    let mut offset = 0usize;
+    for table in &tables {
+        let end = offset.checked_add(table.stored_length as usize).ok_or("WOFF2 table data is truncated")?;
+        let bytes = stream.get(offset..end).ok_or("WOFF2 table data is truncated")?;
+        raw.push((table, bytes));
+        offset = end;
+    }
+    let find = |tag: &[u8; 4]| raw.iter().find(|(t, _)| &t.tag == tag).map(|(t, b)| (*t, *b));
+
+    let mut output: Vec<([u8; 4], Vec<u8>)> = Vec::new();
+    let mut glyph_x_mins: Option<Vec<i16>> = None;
+    if let Some((glyf, data)) = find(b"glyf").filter(|(t, _)| t.transformed) {
+        let tables = reconstruct_glyf(data)?;
+        if let Some((loca, _)) = find(b"loca") {
+            if loca.orig_length as usize != tables.loca.len() {
+                return Err("reconstructed loca size does not match".to_string());
+            }
+        }
+        output.push((glyf.tag, tables.glyf));
+        output.push((*b"loca", tables.loca));
+        glyph_x_mins = Some(tables.x_mins);
+    }
+    for (table, data) in &raw {
+        match &table.tag {
+            b"glyf" | b"loca" if table.transformed => {}
+            b"hmtx" if table.transformed => {
+                let x_mins = glyph_x_mins.as_ref().ok_or("transformed hmtx needs a transformed glyf")?;
+                let hhea = find(b"hhea").ok_or("missing hhea table")?.1;
+                let num_h_metrics = hhea.get(34..36).map(|b| u16::from_be_bytes([b[0], b[1]])).ok_or("short hhea table")?;
+                output.push((table.tag, reconstruct_hmtx(data, num_h_metrics, x_mins)?));
+            }
+            _ if table.transformed => return Err("unsupported WOFF2 table transform".to_string()),
+            _ => output.push((table.tag, data.to_vec())),
+        }
+    }
+    assemble_sfnt(flavor, output)
+}
+
+/// One decoded glyph point.
+struct GlyphPoint {
+    x: i32,
+    y: i32,
+    on_curve: bool,
+}
+
+/// Reconstructed `glyf` and `loca`, plus each glyph's xMin for `hmtx`.
+struct GlyphTables {
+    glyf: Vec<u8>,
+    loca: Vec<u8>,
+    x_mins: Vec<i16>,
+}
+
+/// Rebuilds `glyf` and `loca` from the WOFF2 transformed glyph streams.
+fn reconstruct_glyf(data: &[u8]) -> Result<GlyphTables, String> {
+    let mut header = Reader::new(data);
+    let _reserved = header.u16()?;
+    let option_flags = header.u16()?;
+    let num_glyphs = header.u16()? as usize;
+    let index_format = header.u16()?;
+    let mut sizes = [0usize; 7];
+    for size in sizes.iter_mut() {
+        *size = header.u32()? as usize;
+    }
+    let mut streams = Vec::with_capacity(7);
+    for size in sizes {
+        streams.push(Reader::new(header.bytes(size)?));
+    }
+    let overlap_bitmap = if option_flags & 1 != 0 { Some(header.bytes(num_glyphs.div_ceil(8))?) } else { None };
+    let mut instructions = streams.pop().unwrap();
+    let mut bbox_stream = streams.pop().unwrap();
+    let mut composite_stream = streams.pop().unwrap();
+    let mut glyph_stream = streams.pop().unwrap();
+    let mut flag_stream = streams.pop().unwrap();
+    let mut points_stream = streams.pop().unwrap();
+    let mut contour_stream = streams.pop().unwrap();
+
+    let bbox_bitmap = bbox_stream.bytes(4 * num_glyphs.div_ceil(32))?;
+    let has_bbox = |i: usize| bbox_bitmap[i >> 3] & (0x80 >> (i & 7)) != 0;
+
+    let mut glyf = Vec::new();
+    let mut offsets = Vec::with_capacity(num_glyphs + 1);
+    let mut x_mins = Vec::with_capacity(num_glyphs);
+    for i in 0..num_glyphs {
+        offsets.push(glyf.len());
+        let n_contours = contour_stream.i16()?;
+        let explicit_bbox = if has_bbox(i) { Some([bbox_stream.i16()?, bbox_stream.i16()?, bbox_stream.i16()?, bbox_stream.i16()?]) } else { None };
+        match n_contours {
+            0 => {
+                if explicit_bbox.is_some() {
+                    return Err("empty glyph with a bounding box".to_string());
+                }
+                x_mins.push(0);
+            }
+            -1 => {
+                let bbox = explicit_bbox.ok_or("composite glyph without a bounding box")?;
+                let start = composite_stream.pos;
+                let mut has_instructions = false;
+                loop {
+                    let flags = composite_stream.u16()?;
+                    has_instructions |= flags & 0x0100 != 0;
+                    let mut skip = 2 + if flags & 0x0001 != 0 { 4 } else { 2 };
+                    skip += if flags & 0x0008 != 0 { 2 } else if flags & 0x0040 != 0 { 4 } else if flags & 0x0080 != 0 { 8 } else { 0 };
+                    composite_stream.bytes(skip)?;
+                    if flags & 0x0020 == 0 {
+                        break;
+                    }
+                }
+                let components = &composite_stream.data[start..composite_stream.pos];
+                glyf.extend_from_slice(&(-1i16).to_be_bytes());
+                bbox.iter().for_each(|v| glyf.extend_from_slice(&v.to_be_bytes()));
+                glyf.extend_from_slice(components);
+                if has_instructions {
+                    let len = glyph_stream.u255()?;
+                    glyf.extend_from_slice(&len.to_be_bytes());
+                    glyf.extend_from_slice(instructions.bytes(len as usize)?);
+                }
+                x_mins.push(bbox[0]);
+            }
+            n if n > 0 => {
+                let mut end_points = Vec::with_capacity(n as usize);
+                let mut total = 0u32;
+                for _ in 0..n {
+                    total += points_stream.u255()? as u32;
+                    let end = total.checked_sub(1).ok_or("contour without points")?;
+                    end_points.push(u16::try_from(end).map_err(|_| "too many points in glyph")?);
+                }
+                // Every point takes a flag byte, so a count the flags cannot
+                // cover is rejected before anything is allocated for it.
+                if total as usize > flag_stream.remaining() {
+                    return Err("truncated WOFF2 glyph flags".to_string());
+                }
+                let points = decode_points(total as usize, &mut flag_stream, &mut glyph_stream)?;
+                let instruction_len = glyph_stream.u255()?;
+                let instruction_bytes = instructions.bytes(instruction_len as usize)?;
+                let bbox = explicit_bbox.unwrap_or_else(|| {
+                    let clamp = |v: i32| v.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+                    let xs = points.iter().map(|p| p.x);
+                    let ys = points.iter().map(|p| p.y);
+                    [clamp(xs.clone().min().unwrap_or(0)), clamp(ys.clone().min().unwrap_or(0)), clamp(xs.max().unwrap_or(0)), clamp(ys.max().unwrap_or(0))]
+                });
+                let overlap = overlap_bitmap.is_some_and(|b| b[i >> 3] & (0x80 >> (i & 7)) != 0);
+                write_simple_glyph(&mut glyf, n, bbox, &end_points, instruction_bytes, &points, overlap);
+                x_mins.push(bbox[0]);
+            }
+            _ => return Err("invalid contour count".to_string()),
+        }
+        // Keep every glyph 4-byte aligned so short loca offsets stay exact.
+        glyf.resize(glyf.len().div_ceil(4) * 4, 0);
+    }
+    offsets.push(glyf.len());
+
+    let mut loca = Vec::new();
+    for offset in offsets {
+        if index_format == 0 {
+            let half = u16::try_from(offset / 2).map_err(|_| "glyf too large for short loca")?;
+            loca.extend_from_slice(&half.to_be_bytes());
+        } else {
+            loca.extend_from_slice(&(offset as u32).to_be_bytes());
+        }
+    }
+    Ok(GlyphTables { glyf, loca, x_mins })
+}
+
+/// Decodes the WOFF2 triplet encoding of a simple glyph's points.
+fn decode_points(count: usize, flags: &mut Reader, data: &mut Reader) -> Result<Vec<GlyphPoint>, String> {
+    let with_sign = |flag: u8, value: i32| if flag & 1 != 0 { value } else { -value };
+    let mut points = Vec::with_capacity(count);
+    let (mut x, mut y) = (0i32, 0i32);
+    for _ in 0..count {
+        let flag = flags.u8()?;
+        let on_curve = flag & 0x80 == 0;
+        let flag = flag & 0x7F;
+        let n = if flag < 84 { 1 } else if flag < 120 { 2 } else if flag < 124 { 3 } else { 4 };
+        let b: Vec<i32> = data.bytes(n)?.iter().map(|&v| v as i32).collect();
+        let f = flag as i32;
+        let (dx, dy) = if flag < 10 {
+            (0, with_sign(flag, ((f & 14) << 7) + b[0]))
+        } else if flag < 20 {
+            (with_sign(flag, (((f - 10) & 14) << 7) + b[0]), 0)
+        } else if flag < 84 {
+            let b0 = f - 20;
+            (with_sign(flag, 1 + (b0 & 0x30) + (b[0] >> 4)), with_sign(flag >> 1, 1 + ((b0 & 0x0C) << 2) + (b[0] & 0x0F)))
+        } else if flag < 120 {
+            let b0 = f - 84;
+            (with_sign(flag, 1 + ((b0 / 12) << 8) + b[0]), with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b[1]))
+        } else if flag < 124 {
+            (with_sign(flag, (b[0] << 4) + (b[1] >> 4)), with_sign(flag >> 1, ((b[1] & 0x0F) << 8) + b[2]))
+        } else {
+            (with_sign(flag, (b[0] << 8) + b[1]), with_sign(flag >> 1, (b[2] << 8) + b[3]))
+        };
+        x += dx;
+        y += dy;
+        points.push(GlyphPoint { x, y, on_curve });
+    }
+    Ok(points)
+}
+
+fn write_simple_glyph(out: &mut Vec<u8>, n_contours: i16, bbox: [i16; 4], end_points: &[u16], instructions: &[u8], points: &[GlyphPoint], overlap: bool) {
+    out.extend_from_slice(&n_contours.to_be_bytes());
+    bbox.iter().for_each(|v| out.extend_from_slice(&v.to_be_bytes()));
+    end_points.iter().for_each(|e| out.extend_from_slice(&e.to_be_bytes()));
+    out.extend_from_slice(&(instructions.len() as u16).to_be_bytes());
+    out.extend_from_slice(instructions);
+    // Plain 16-bit deltas for every point: larger than the optimal encoding
+    // but simple, and the font is only ever read back, never shipped.
+    for (i, p) in points.iter().enumerate() {
+        let overlap_flag = if i == 0 && overlap { 0x40 } else { 0 };
+        out.push(p.on_curve as u8 | overlap_flag);
+    }
+    for axis in [0, 1] {
+        let mut prev = 0i32;
+        for p in points {
+            let v = if axis == 0 { p.x } else { p.y };
+            out.extend_from_slice(&((v - prev) as i16).to_be_bytes());
+            prev = v;
+        }
+    }
+}
+
+fn reconstruct_hmtx(data: &[u8], num_h_metrics: u16, x_mins: &[i16]) -> Result<Vec<u8>, String> {
+    let mut r = Reader::new(data);
+    let flags = r.u8()?;
+    let num_h_metrics = num_h_metrics as usize;
+    if num_h_metrics == 0 || num_h_metrics > x_mins.len() {
+        return Err("invalid hmtx metrics count".to_string());
+    }
+    let advances = (0..num_h_metrics).map(|_| r.u16()).collect::<Result<Vec<_>, _>>()?;
+    let proportional = if flags & 1 == 0 {
+        (0..num_h_metrics).map(|_| r.i16()).collect::<Result<Vec<_>, _>>()?
+    } else {
+        x_mins[..num_h_metrics].to_vec()
+    };
+    let monospaced = if flags & 2 == 0 {
+        (num_h_metrics..x_mins.len()).map(|_| r.i16()).collect::<Result<Vec<_>, _>>()?
+    } else {
+        x_mins[num_h_metrics..].to_vec()
+    };
+    let mut out = Vec::new();
+    for (advance, lsb) in advances.iter().zip(&proportional) {
+        out.extend_from_slice(&advance.to_be_bytes());
+        out.extend_from_slice(&lsb.to_be_bytes());
+    }
+    monospaced.iter().for_each(|lsb| out.extend_from_slice(&lsb.to_be_bytes()));
+    Ok(out)
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn assemble_sfnt(flavor: u32, mut tables: Vec<([u8; 4], Vec<u8>)>) -> Result<Vec<u8>, String> {
+    tables.sort_by_key(|(tag, _)| *tag);
+    // The table directory's search fields are 16-bit multiples of the count.
+    let n = u16::try_from(tables.len()).ok().filter(|n| n.checked_mul(16).is_some()).ok_or("too many font tables")?;
+    let entry_selector = 15 - n.max(1).leading_zeros() as u16;
+    let search_range = (1u16 << entry_selector) * 16;
+    let mut out = Vec::new();
+    out.extend_from_slice(&flavor.to_be_bytes());
+    for v in [n, search_range, entry_selector, n * 16 - search_range] {
+        out.extend_from_slice(&v.to_be_bytes());
+    }
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, data) in &tables {
+        out.extend_from_slice(tag);
+        out.extend_from_slice(&checksum(data).to_be_bytes());
+        out.extend_from_slice(&(offset as u32).to_be_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len().div_ceil(4) * 4;
+    }
+    for (_, data) in &tables {
+        out.extend_from_slice(data);
+        out.resize(out.len().div_ceil(4) * 4, 0);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_woff2() {
+        let woff2 = include_bytes!("../../tests/fixtures/SourceCodePro-It.ttf.woff2");
+        assert!(is_woff2(woff2));
+        let sfnt = decode(woff2).unwrap();
+        let face = ttf_parser::Face::parse(&sfnt, 0).unwrap();
+        let glyph = face.glyph_index('g').unwrap();
+
+        struct Count(usize);
+        impl ttf_parser::OutlineBuilder for Count {
+            fn move_to(&mut self, _: f32, _: f32) { self.0 += 1; }
+            fn line_to(&mut self, _: f32, _: f32) { self.0 += 1; }
+            fn quad_to(&mut self, _: f32, _: f32, _: f32, _: f32) { self.0 += 1; }
+            fn curve_to(&mut self, _: f32, _: f32, _: f32, _: f32, _: f32, _: f32) { self.0 += 1; }
+            fn close(&mut self) {}
+        }
+        let mut count = Count(0);
+        let bbox = face.outline_glyph(glyph, &mut count).unwrap();
+        assert!(count.0 > 10);
+        // The reconstructed side bearing matches the reconstructed outline.
+        assert_eq!(face.glyph_hor_side_bearing(glyph), Some(bbox.x_min));
+        assert_eq!(decode(b"wOF2").unwrap_err(), "truncated WOFF2 data");
+        assert!(decode(&woff2[..woff2.len() / 2]).is_err());
+    }
+
+    /// Transformed `glyf` data for one glyph from its seven streams.
+    fn transformed_glyf(contours: &[u8], points: &[u8], flags: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for v in [0u16, 0, 1, 0] {
+            data.extend_from_slice(&v.to_be_bytes());
+        }
+        let bbox = [0u8; 4];
+        let streams: [&[u8]; 7] = [contours, points, flags, &[], &[], &bbox, &[]];
+        streams.iter().for_each(|s| data.extend_from_slice(&(s.len() as u32).to_be_bytes()));
+        streams.iter().for_each(|s| data.extend_from_slice(s));
+        data
+    }
+
+    #[test]
+    fn test_rejects_malformed_glyphs() {
+        // 32767 contours of 65535 points each claim over two billion points,
+        // with not a single flag byte behind them.
+        let contours = i16::MAX.to_be_bytes();
+        let points: Vec<u8> = (0..i16::MAX).flat_map(|_| [253, 0xFF, 0xFF]).collect();
+        assert!(reconstruct_glyf(&transformed_glyf(&contours, &points, &[])).is_err());
+
+        // One contour of three points, but flags for only two.
+        let glyph = transformed_glyf(&1i16.to_be_bytes(), &[3], &[0, 0]);
+        assert_eq!(reconstruct_glyf(&glyph).err().as_deref(), Some("truncated WOFF2 glyph flags"));
+        assert_eq!(reconstruct_glyf(&glyph[..20]).err().as_deref(), Some("truncated WOFF2 data"));
+
+        let tables = (0..4096u32).map(|i| (i.to_be_bytes(), Vec::new())).collect();
+        assert_eq!(assemble_sfnt(0x0001_0000, tables).unwrap_err(), "too many font tables");
+    }
+}
@@ -1,43 +1,49 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
 use wasm_bindgen::prelude::*;
 use serde_wasm_bindgen::to_value;
 
 pub mod core;
 use crate::core::state::{EngineState, Action, reducer};
+use crate::core::picking::{HitTestOptions, MarqueeOptions, SelectionRegion};
+use crate::core::spatial::{IndexOptions, SpatialIndex};
 
 #[wasm_bindgen]
 pub struct KineticEngine {
     state: EngineState,
-    quadtree: crate::core::spatial::Quadtree,
+    index: Box<dyn SpatialIndex>,
+    /// Bounds each element is currently indexed under.
+    indexed: HashMap<String, crate::core::geometry::Rect>,
+    /// Elements with keyframes, whose bounds can change with the time.
+    animated: BTreeSet<String>,
+}
+
+impl Default for KineticEngine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
 impl KineticEngine {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
-        let mut bounds = crate::core::geometry::Rect::new(-5000.0, -5000.0, 10000.0, 10000.0);
-        Self {
-            state: EngineState::new(),
-            quadtree: crate::core::spatial::Quadtree::new(bounds, 4),
-        }
+        Self::with_index(&IndexOptions::default())
     }
 
-    fn rebuild_quadtree(&mut self) {
-        self.quadtree.clear();
-        for (id, el) in &self.state.elements {
-            let bounds = el.shape.get_bounding_box();
-            self.quadtree.insert(id, &bounds);
-        }
+    /// An engine whose spatial index is built as `options` describe: the
+    /// `backend` ("quadtree" or "rtree"), node `capacity` and quadtree
+    /// `max_depth`. Omitted fields keep their defaults.
+    pub fn with_spatial_index(options: JsValue) -> Result<KineticEngine, JsValue> {
+        let options: IndexOptions = options_from(options)?;
+        Ok(Self::with_index(&options))
     }
 
     pub fn dispatch(&mut self, action_val: JsValue) -> Result<JsValue, JsValue> {
         let action: Action = serde_wasm_bindgen::from_value(action_val)
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
-        
-        reducer(&mut self.state, action);
-        
-        // Simple rebuild strategy for now
-        self.rebuild_quadtree();
-        
+
+        self.apply(action);
+
         let computed = self.state.get_computed_state();
         to_value(&computed).map_err(|e| JsValue::from_str(&e.to_string()))
     }
@@ -52,63 +58,876 @@ impl KineticEngine {
     }
 
     pub fn deserialize_state(&mut self, data: String) -> Result<(), JsValue> {
-        self.state = serde_json::from_str(&data).map_err(|e| JsValue::from_str(&e.to_string()))?;
-        self.rebuild_quadtree();
+        let mut state: EngineState = serde_json::from_str(&data).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        // Fonts belong to the host session, not the document.
+        state.fonts = std::sync::Arc::clone(&self.state.fonts);
+        state.layout_text();
+        state.prepare_morphs();
+        state.index_siblings();
+        self.state = state;
+        self.rebuild_index();
         Ok(())
     }
 
-    pub fn query_spatial(&self, x: f32, y: f32, w: f32, h: f32) -> Result<JsValue, JsValue> {
-        let range = crate::core::geometry::Rect::new(x, y, w, h);
-        let mut ids = Vec::new();
-        self.quadtree.query(&range, &mut ids);
+    /// Loads TTF, OTF or WOFF2 font bytes, re-laying out any text using the
+    /// family. Returns the family name the font was registered under.
+    pub fn load_font(&mut self, data: Vec<u8>) -> Result<String, JsValue> {
+        let family = std::sync::Arc::make_mut(&mut self.state.fonts).load(data).map_err(|e| JsValue::from_str(&e))?;
+        self.state.layout_text();
+        self.rebuild_index();
+        Ok(family)
+    }
+
+    pub fn font_families(&self) -> Result<JsValue, JsValue> {
+        to_value(&self.state.fonts.families()).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Glyph outlines of a text element at the current time, for renderers
+    /// that draw text as paths. Null for other elements or unloaded fonts.
+    pub fn text_outlines(&self, id: &str) -> Result<JsValue, JsValue> {
+        let computed = self.state.get_computed_state();
+        let outlines = match computed.elements.get(id).map(|el| &el.shape) {
+            Some(crate::core::geometry::Shape::Text(text)) => text.to_outlines(&computed.fonts),
+            _ => None,
+        };
+        to_value(&outlines).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn query_spatial(&self, x: f32, y: f32, w: f32, h: f32, precise: Option<bool>) -> Result<JsValue, JsValue> {
+        let ids = self.query(&crate::core::geometry::Rect::new(x, y, w, h), precise.unwrap_or(false));
         to_value(&ids).map_err(|e| JsValue::from_str(&e.to_string()))
     }
+
+    /// Ids of the elements under `(x, y)`, bottom to top: just the topmost
+    /// unless `options.all` is set. `options` may be omitted.
+    pub fn hit_test(&self, x: f32, y: f32, options: JsValue) -> Result<JsValue, JsValue> {
+        let options: HitTestOptions = options_from(options)?;
+        let hits = self.hits(&crate::core::geometry::Point::new(x, y), &options);
+        to_value(&hits).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Up to `k` elements nearest `(x, y)` by their bounds, nearest first,
+    /// as `[id, distance]` pairs.
+    pub fn nearest_elements(&self, x: f32, y: f32, k: usize) -> Result<JsValue, JsValue> {
+        let found = self.index.nearest(&crate::core::geometry::Point::new(x, y), k);
+        to_value(&distances_to_f32(found)).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Elements whose bounds come within `radius` of `(x, y)`, nearest
+    /// first, as `[id, distance]` pairs.
+    pub fn elements_within(&self, x: f32, y: f32, radius: f32) -> Result<JsValue, JsValue> {
+        let radius = crate::core::geometry::Scalar::from_num(radius);
+        let found = self.index.within_radius(&crate::core::geometry::Point::new(x, y), radius);
+        to_value(&distances_to_f32(found)).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Ids of the elements a rect or lasso `region` selects, bottom to top.
+    /// This only reports them; dispatch `MARQUEE_SELECT` to change the
+    /// selection. `options` may be omitted.
+    pub fn marquee_select(&self, region: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+        let region: SelectionRegion = serde_wasm_bindgen::from_value(region).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let options: MarqueeOptions = options_from(options)?;
+        to_value(&self.marquee(&region, &options)).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// Reads an optional options object, falling back to the defaults when the
+/// caller left it out.
+fn options_from<T: serde::de::DeserializeOwned + Default>(value: JsValue) -> Result<T, JsValue> {
+    if value.is_undefined() || value.is_null() {
+        return Ok(T::default());
+    }
+    serde_wasm_bindgen::from_value(value).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+fn distances_to_f32(found: Vec<(String, crate::core::geometry::Scalar)>) -> Vec<(String, f32)> {
+    found.into_iter().map(|(id, d)| (id, d.to_num())).collect()
+}
+
+impl KineticEngine {
+    /// An empty engine with the given spatial index.
+    pub fn with_index(options: &IndexOptions) -> Self {
+        Self {
+            state: EngineState::new(),
+            index: options.build(),
+            indexed: HashMap::new(),
+            animated: BTreeSet::new(),
+        }
+    }
+
+    /// Wraps an existing state, laying out its text and indexing it.
+    pub fn from_state(state: EngineState) -> Self {
+        Self::from_state_with_index(state, &IndexOptions::default())
+    }
+
+    /// Like `from_state`, bulk loading the given kind of spatial index.
+    pub fn from_state_with_index(state: EngineState, options: &IndexOptions) -> Self {
+        let mut engine = Self { state, ..Self::with_index(options) };
+        engine.state.layout_text();
+        engine.state.prepare_morphs();
+        engine.state.index_siblings();
+        engine.rebuild_index();
+        engine
+    }
+
+    /// The base state, before keyframes are applied.
+    pub fn state(&self) -> &EngineState {
+        &self.state
+    }
+
+    /// Runs an action through the reducer and updates the spatial index for
+    /// just the elements it can have moved. A marquee selection is picked
+    /// against the index first and reaches the reducer as the selection.
+    pub fn apply(&mut self, action: Action) {
+        let action = match action {
+            Action::MarqueeSelect { region, options, extend } => {
+                let mut ids = if extend { self.state.selection.clone() } else { Vec::new() };
+                let mut seen: HashSet<String> = ids.iter().cloned().collect();
+                ids.extend(self.marquee(&region, &options).into_iter().filter(|id| seen.insert(id.clone())));
+                Action::SetSelection { ids }
+            }
+            action => action,
+        };
+        let mut targets = action.target_ids();
+        if let Action::SetTime { .. } = action {
+            let elements = &self.state.elements;
+            self.animated.retain(|id| elements.get(id).is_some_and(|el| !el.animations.is_empty()));
+            targets.extend(self.animated.iter().cloned());
+        }
+        // Dependents are gathered on both sides of the change, as it can
+        // rearrange the hierarchy they are derived from.
+        let mut touched = BTreeSet::new();
+        for id in &targets {
+            touched.extend(self.state.spatial_dependents(id));
+        }
+        reducer(&mut self.state, action);
+        for id in &targets {
+            touched.extend(self.state.spatial_dependents(id));
+            match self.state.elements.get(id) {
+                Some(el) if !el.animations.is_empty() => self.animated.insert(id.clone()),
+                _ => self.animated.remove(id),
+            };
+        }
+        self.reindex(touched);
+    }
+
+    /// Elements whose indexed bounds intersect `range`; with `precise`, only
+    /// those whose painted geometry does.
+    pub fn query(&self, range: &crate::core::geometry::Rect, precise: bool) -> Vec<String> {
+        let mut ids = Vec::new();
+        self.index.query(range, &mut ids);
+        if precise {
+            ids.retain(|id| self.state.element_intersects(id, range));
+        }
+        ids
+    }
+
+    /// Hit-tests the elements the spatial index finds at `p`, as painted at
+    /// the current time.
+    pub fn hits(&self, p: &crate::core::geometry::Point, options: &HitTestOptions) -> Vec<String> {
+        let mut candidates = Vec::new();
+        let zero = crate::core::geometry::Scalar::ZERO;
+        self.index.query(&crate::core::geometry::Rect { origin: *p, width: zero, height: zero }, &mut candidates);
+        crate::core::picking::hit_test(&self.state, &candidates, p, options)
+    }
+
+    /// Marquee-selects among the elements the spatial index finds in the region's bounds.
+    pub fn marquee(&self, region: &SelectionRegion, options: &MarqueeOptions) -> Vec<String> {
+        let Some(bounds) = region.bounds() else {
+            return Vec::new();
+        };
+        let mut candidates = Vec::new();
+        self.index.query(&bounds, &mut candidates);
+        crate::core::picking::marquee_select(&self.state, &candidates, region, options)
+    }
+
+    /// Re-indexes every element from scratch, for when the whole state is replaced.
+    pub fn rebuild_index(&mut self) {
+        self.animated = self.state.elements.values().filter(|el| !el.animations.is_empty()).map(|el| el.id.clone()).collect();
+        self.indexed = self.state.all_clipped_bounds();
+        let mut entries: Vec<(String, crate::core::geometry::Rect)> = self.indexed.iter().map(|(id, b)| (id.clone(), *b)).collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        self.index.load(entries);
+    }
+
+    /// Brings the given elements' index entries in line with their current
+    /// bounds, touching the tree only where bounds changed. Removed and fully
+    /// masked-out elements cannot be hit, so they leave the index.
+    fn reindex(&mut self, ids: impl IntoIterator<Item = String>) {
+        for id in ids {
+            let bounds = self.state.clipped_bounds(&id);
+            let old = self.indexed.get(&id).copied();
+            if bounds == old {
+                continue;
+            }
+            if let Some(old) = old {
+                self.index.remove(&id, &old);
+            }
+            match bounds {
+                Some(bounds) => {
+                    self.index.insert(&id, &bounds);
+                    self.indexed.insert(id, bounds);
+                }
+                None => {
+                    self.indexed.remove(&id);
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::geometry::{Shape, Rect};
+    use crate::core::geometry::{Shape, Rect, Circle};
+    use crate::core::state::{Keyframe, KeyframeValue};
+    use crate::core::color::Color;
+    use crate::core::paint::Paint;
 
-    #[test]
-    fn test_determinism() {
-        let mut engine = KineticEngine::new();
-        let id = "box1".to_string();
-        
+    fn run_script(state: &mut EngineState, id: &str) {
         // Add element
-        engine.dispatch(serde_wasm_bindgen::to_value(&Action::AddElement { 
-            id: id.clone(), 
+        reducer(state, Action::AddElement {
+            id: id.to_string(),
+            name: "Box".to_string(),
             shape: Shape::Rect(Rect::new(0.0, 0.0, 100.0, 100.0)),
-            fill: "#ff0000".to_string()
-        }).unwrap()).unwrap();
-        
+            fill: Paint::Solid(Color::rgba8(255, 0, 0, 255))
+        });
+
         // Move element
-        engine.dispatch(serde_wasm_bindgen::to_value(&Action::MoveElement { 
-            id: id.clone(), 
-            dx: 10.5, 
-            dy: 20.7 
-        }).unwrap()).unwrap();
-        
+        reducer(state, Action::MoveElement {
+            id: id.to_string(),
+            dx: 10.5,
+            dy: 20.7
+        });
+    }
+
+    #[test]
+    fn test_determinism() {
+        let mut state = EngineState::new();
+        run_script(&mut state, "box1");
+
         // Same input should yield same output (using fixed point)
-        let state = engine.get_state().unwrap();
-        let state_json = serde_json::to_string(&state).unwrap();
-        
-        let mut engine2 = KineticEngine::new();
-        engine2.dispatch(serde_wasm_bindgen::to_value(&Action::AddElement { 
-            id: id.clone(), 
-            shape: Shape::Rect(Rect::new(0.0, 0.0, 100.0, 100.0)),
-            fill: "#ff0000".to_string()
-        }).unwrap()).unwrap();
-        
-        engine2.dispatch(serde_wasm_bindgen::to_value(&Action::MoveElement { 
-            id, 
-            dx: 10.5, 
-            dy: 20.7 
-        }).unwrap()).unwrap();
-        
-        let state2 = engine2.get_state().unwrap();
-        let state_json2 = serde_json::to_string(&state2).unwrap();
-        
+        let state_json = serde_json::to_string(&state.get_computed_state().elements["box1"]).unwrap();
+
+        let mut state2 = EngineState::new();
+        run_script(&mut state2, "box1");
+        let state_json2 = serde_json::to_string(&state2.get_computed_state().elements["box1"]).unwrap();
+
         assert_eq!(state_json, state_json2);
     }
+
+    #[test]
+    fn test_convert_to_path_keeps_animations() {
+        let mut state = EngineState::new();
+        reducer(&mut state, Action::AddElement {
+            id: "dot".to_string(),
+            name: "Dot".to_string(),
+            shape: Shape::Circle(Circle::new(50.0, 50.0, 10.0)),
+            fill: Paint::Solid(Color::rgba8(0, 255, 0, 255))
+        });
+        for (property, value) in [("x", 100.0), ("radius", 20.0), ("opacity", 0.5)] {
+            reducer(&mut state, Action::AddKeyframe {
+                element_id: "dot".to_string(),
+                property: property.to_string(),
+                keyframe: Keyframe { time: 0.0, value: KeyframeValue::Number(value), easing: "linear".to_string(), color_space: Default::default(), morph: None },
+            });
+        }
+        reducer(&mut state, Action::ConvertToPath { id: "dot".to_string() });
+        let el = &state.elements["dot"];
+        assert!(matches!(el.shape, Shape::Path(_)));
+        assert_eq!(serde_json::to_string(&el.fill).unwrap(), "\"#00ff00\"");
+        assert!(!el.animations.contains_key("radius"));
+
+        let computed = state.get_computed_state();
+        assert_eq!(computed.elements["dot"].opacity, 0.5);
+        // Centre animated to x = 100 with the static radius of 10.
+        assert_eq!(computed.elements["dot"].shape.get_bounding_box().origin.x, crate::core::geometry::Scalar::from_num(90));
+    }
+
+    #[test]
+    fn test_path_morphs_are_prepared_per_keyframe_pair() {
+        use crate::core::geometry::Scalar;
+        use crate::core::interpolation::interpolate_path;
+        use crate::core::morph::morph;
+
+        let square = Shape::Rect(Rect::new(0.0, 0.0, 100.0, 100.0)).to_path().unwrap();
+        let circle = Shape::Circle(Circle::new(50.0, 50.0, 50.0)).to_path().unwrap();
+        let star = Shape::Circle(Circle::new(0.0, 0.0, 20.0)).to_path().unwrap();
+        let mut state = EngineState::new();
+        reducer(&mut state, Action::AddElement { id: "p".to_string(), name: "p".to_string(), shape: Shape::Path(square.clone()), fill: Paint::Solid(Color::BLACK) });
+        for (time, path) in [(0.0, &square), (2.0, &star), (1.0, &circle)] {
+            let keyframe = Keyframe { time, value: KeyframeValue::Path(path.clone()), easing: "linear".to_string(), color_space: Default::default(), morph: None };
+            reducer(&mut state, Action::AddKeyframe { element_id: "p".to_string(), property: "path".to_string(), keyframe });
+        }
+
+        let check = |track: &[Keyframe]| {
+            assert!(track[0].morph.is_some() && track[1].morph.is_some() && track[2].morph.is_none());
+            let at = |time| interpolate_path(track, time).unwrap().commands;
+            assert_eq!(at(0.25), morph(&square, &circle, Scalar::lit("0.25")).commands);
+            assert_eq!(at(1.5), morph(&circle, &star, Scalar::lit("0.5")).commands);
+            assert_eq!(at(-1.0), square.commands);
+            assert_eq!(at(3.0), star.commands);
+        };
+        check(&state.elements["p"].animations["path"]);
+
+        let json = serde_json::to_string(&state).unwrap();
+        let engine = KineticEngine::from_state(serde_json::from_str(&json).unwrap());
+        check(&engine.state.elements["p"].animations["path"]);
+    }
+
+    #[test]
+    fn test_fill_keyframes_interpolate() {
+        for (space, expected) in [("Srgb", "#800080"), ("Oklab", "#8c53a2"), ("Oklch", "#ba00c2")] {
+            let mut state = EngineState::new();
+            run_script(&mut state, "box1");
+            for (time, fill) in [(0.0, "#ff0000"), (1000.0, "rgb(0, 0, 255)")] {
+                let keyframe = serde_json::from_str(&format!(
+                    r#"{{"time": {}, "value": "{}", "easing": "linear", "color_space": "{}"}}"#,
+                    time, fill, space
+                )).unwrap();
+                reducer(&mut state, Action::AddKeyframe { element_id: "box1".to_string(), property: "fill".to_string(), keyframe });
+            }
+            reducer(&mut state, Action::SetTime { time: 500.0 });
+            let computed = state.get_computed_state();
+            assert_eq!(serde_json::to_string(&computed.elements["box1"].fill).unwrap(), format!("\"{}\"", expected));
+        }
+    }
+
+    #[test]
+    fn test_legacy_string_fills_load() {
+        let mut state = EngineState::new();
+        run_script(&mut state, "box1");
+        run_script(&mut state, "box2");
+        // Strip the document back to the shape it had before paints were structured.
+        let mut doc = serde_json::to_value(&state).unwrap();
+        doc.as_object_mut().unwrap().remove("root");
+        for (id, fill) in [("box1", "none"), ("box2", "var(--accent)")] {
+            let el = doc["elements"][id].as_object_mut().unwrap();
+            for field in ["stroke", "effects", "blend_mode", "mask", "locked"] {
+                el.remove(field);
+            }
+            el.insert("fill".to_string(), fill.into());
+        }
+
+        let mut engine = KineticEngine::new();
+        assert!(engine.deserialize_state(doc.to_string()).is_ok());
+        assert_eq!(engine.state.elements["box1"].fill, Paint::Solid(Color::TRANSPARENT));
+        assert_eq!(engine.state.elements["box2"].shape.get_bounding_box(), Rect::new(10.5, 20.7, 100.0, 100.0));
+        assert_eq!(engine.hits(&crate::core::geometry::Point::new(50.0, 50.0), &HitTestOptions::default()), vec!["box2"]);
+    }
+
+    #[test]
+    fn test_effects_extend_spatial_bounds() {
+        use crate::core::effects::Effect;
+        use crate::core::geometry::Scalar;
+
+        let mut engine = KineticEngine::new();
+        run_script(&mut engine.state, "box1");
+        for radius in [20, 40] {
+            reducer(&mut engine.state, Action::AddEffect {
+                id: "box1".to_string(),
+                effect: Effect::LayerBlur { radius: Scalar::from_num(radius) },
+            });
+        }
+        let keyframe = Keyframe { time: 0.0, value: KeyframeValue::Number(5.0), easing: "linear".to_string(), color_space: Default::default(), morph: None };
+        reducer(&mut engine.state, Action::AddKeyframe { element_id: "box1".to_string(), property: "effects.1.radius".to_string(), keyframe });
+        engine.rebuild_index();
+
+        // The box spans x 10.5..110.5; the blurs push its bounds out by 20
+        // plus the keyframed 5 rather than the stored 40.
+        let mut ids = Vec::new();
+        engine.index.query(&Rect::new(-14.0, 0.0, 1.0, 1.0), &mut ids);
+        assert_eq!(ids, vec!["box1".to_string()]);
+        ids.clear();
+        engine.index.query(&Rect::new(-40.0, 0.0, 1.0, 1.0), &mut ids);
+        assert!(ids.is_empty());
+
+        reducer(&mut engine.state, Action::RemoveEffect { id: "box1".to_string(), index: 0 });
+        let el = &engine.state.elements["box1"];
+        assert_eq!(el.effects, vec![Effect::LayerBlur { radius: Scalar::from_num(40) }]);
+        assert!(el.animations.contains_key("effects.0.radius"));
+        let computed = engine.state.get_computed_state();
+        assert_eq!(computed.elements["box1"].effects[0], Effect::LayerBlur { radius: Scalar::from_num(5) });
+    }
+
+    #[test]
+    fn test_opacity_visibility_and_blend_mode() {
+        use crate::core::paint::BlendMode;
+
+        let mut state = EngineState::new();
+        run_script(&mut state, "box1");
+        let action: Action = serde_json::from_str(r#"{"type": "SET_BLEND_MODE", "payload": {"id": "box1", "blend_mode": "color-dodge"}}"#).unwrap();
+        reducer(&mut state, action);
+        reducer(&mut state, Action::SetOpacity { id: "box1".to_string(), opacity: 1.5 });
+        reducer(&mut state, Action::SetVisibility { id: "box1".to_string(), visible: false });
+
+        let el = &state.get_computed_state().elements["box1"];
+        assert_eq!(el.blend_mode, BlendMode::ColorDodge);
+        assert_eq!(el.opacity, 1.0);
+        assert!(!el.visible);
+        assert!(serde_json::to_string(el).unwrap().contains(r#""blend_mode":"color-dodge""#));
+    }
+
+    #[test]
+    fn test_masks_clip_hits_and_bounds() {
+        use crate::core::geometry::Point;
+        use crate::core::state::MaskMode;
+
+        let mut engine = KineticEngine::new();
+        for (id, shape) in [
+            ("mask", Shape::Circle(Circle::new(50.0, 50.0, 20.0))),
+            ("art", Shape::Rect(Rect::new(0.0, 0.0, 100.0, 100.0))),
+            ("free", Shape::Rect(Rect::new(200.0, 0.0, 10.0, 10.0))),
+        ] {
+            reducer(&mut engine.state, Action::AddElement { id: id.to_string(), name: id.to_string(), shape, fill: Paint::Solid(Color::BLACK) });
+        }
+        reducer(&mut engine.state, Action::GroupElements { group_id: "g".to_string(), children: vec!["art".to_string(), "mask".to_string()] });
+        reducer(&mut engine.state, Action::SetMask { id: "mask".to_string(), mask: Some(MaskMode::Clip) });
+        assert_eq!(engine.state.children(None), vec!["g".to_string(), "free".to_string()]);
+        assert_eq!(engine.state.children(Some("g")), vec!["mask".to_string(), "art".to_string()]);
+
+        let state = &engine.state;
+        assert!(state.element_contains("art", &Point::new(50.0, 50.0)));
+        assert!(!state.element_contains("art", &Point::new(5.0, 5.0)));
+        assert!(state.element_contains("g", &Point::new(60.0, 60.0)));
+        assert!(!state.element_contains("g", &Point::new(95.0, 50.0)));
+        assert_eq!(state.clipped_bounds("art"), Some(Rect::new(30.0, 30.0, 40.0, 40.0)));
+        assert_eq!(state.painted_bounds("g"), Some(Rect::new(30.0, 30.0, 40.0, 40.0)));
+
+        assert_eq!(state.clipped_bounds("free"), Some(Rect::new(200.0, 0.0, 10.0, 10.0)));
+
+        reducer(&mut engine.state, Action::Ungroup { group_id: "g".to_string() });
+        assert_eq!(engine.state.children(None), vec!["mask".to_string(), "art".to_string(), "free".to_string()]);
+        assert!(engine.state.element_contains("art", &Point::new(50.0, 50.0)));
+        assert!(!engine.state.element_contains("art", &Point::new(5.0, 5.0)));
+        // Now at the root, the mask also covers "free", which lies entirely outside it.
+        assert_eq!(engine.state.clipped_bounds("free"), None);
+    }
+
+    #[test]
+    fn test_masks_are_picked_only_on_request() {
+        use crate::core::geometry::Point;
+        use crate::core::picking::MarqueeMode;
+        use crate::core::state::MaskMode;
+
+        let mut engine = KineticEngine::new();
+        for (id, shape) in [
+            ("mask", Shape::Circle(Circle::new(50.0, 50.0, 20.0))),
+            ("art", Shape::Rect(Rect::new(0.0, 0.0, 100.0, 100.0))),
+        ] {
+            engine.apply(Action::AddElement { id: id.to_string(), name: id.to_string(), shape, fill: Paint::Solid(Color::BLACK) });
+        }
+        engine.apply(Action::GroupElements { group_id: "g".to_string(), children: vec!["art".to_string(), "mask".to_string()] });
+        engine.apply(Action::SetMask { id: "mask".to_string(), mask: Some(MaskMode::Clip) });
+
+        let deep = HitTestOptions { all: true, deep: true, ..HitTestOptions::default() };
+        let with_masks = HitTestOptions { include_masks: true, ..deep.clone() };
+        assert_eq!(engine.hits(&Point::new(50.0, 50.0), &deep), vec!["art"]);
+        assert_eq!(engine.hits(&Point::new(50.0, 50.0), &with_masks), vec!["mask", "art"]);
+        assert!(engine.hits(&Point::new(5.0, 5.0), &with_masks).is_empty());
+
+        let region = SelectionRegion::Rect { x: 25.0, y: 25.0, width: 50.0, height: 50.0 };
+        let contain = MarqueeOptions { mode: MarqueeMode::Contain, deep: true, ..MarqueeOptions::default() };
+        assert_eq!(engine.marquee(&region, &contain), vec!["art"]);
+        assert_eq!(engine.marquee(&region, &MarqueeOptions { include_masks: true, ..contain }), vec!["mask", "art"]);
+    }
+
+    #[test]
+    fn test_stacked_masks_in_group() {
+        use crate::core::geometry::Point;
+        use crate::core::state::MaskMode;
+
+        let mut state = EngineState::new();
+        for (id, shape) in [
+            ("low", Shape::Rect(Rect::new(0.0, 0.0, 100.0, 100.0))),
+            ("circle", Shape::Circle(Circle::new(50.0, 50.0, 20.0))),
+            ("mid", Shape::Rect(Rect::new(0.0, 0.0, 100.0, 100.0))),
+            ("corner", Shape::Rect(Rect::new(0.0, 0.0, 10.0, 10.0))),
+            ("top", Shape::Rect(Rect::new(0.0, 0.0, 100.0, 100.0))),
+        ] {
+            reducer(&mut state, Action::AddElement { id: id.to_string(), name: id.to_string(), shape, fill: Paint::Solid(Color::BLACK) });
+        }
+        let ids = ["low", "circle", "mid", "corner", "top"].map(str::to_string).to_vec();
+        reducer(&mut state, Action::GroupElements { group_id: "g".to_string(), children: ids });
+        for id in ["circle", "corner"] {
+            reducer(&mut state, Action::SetMask { id: id.to_string(), mask: Some(MaskMode::Clip) });
+        }
+
+        // Each mask clips only the siblings up to the next one.
+        assert_eq!(state.clipped_bounds("mid"), Some(Rect::new(30.0, 30.0, 40.0, 40.0)));
+        assert_eq!(state.clipped_bounds("top"), Some(Rect::new(0.0, 0.0, 10.0, 10.0)));
+        assert_eq!(state.painted_bounds("g"), Some(Rect::new(0.0, 0.0, 100.0, 100.0)));
+        let all = state.all_clipped_bounds();
+        for id in ["low", "mid", "top", "g"] {
+            assert_eq!(all.get(id).copied(), state.clipped_bounds(id), "{}", id);
+        }
+        assert!(state.element_contains("mid", &Point::new(50.0, 50.0)));
+        assert!(!state.element_contains("top", &Point::new(50.0, 50.0)));
+        assert!(state.element_contains("g", &Point::new(95.0, 95.0)));
+        assert!(state.element_contains("top", &Point::new(5.0, 5.0)));
+    }
+
+    #[test]
+    fn test_hit_test_respects_paint_order_and_flags() {
+        use crate::core::geometry::Point;
+
+        let mut engine = KineticEngine::new();
+        for (id, shape) in [
+            ("bg", Shape::Rect(Rect::new(0.0, 0.0, 100.0, 100.0))),
+            ("circle", Shape::Circle(Circle::new(50.0, 50.0, 20.0))),
+            ("hidden", Shape::Rect(Rect::new(0.0, 0.0, 10.0, 10.0))),
+            ("locked", Shape::Rect(Rect::new(90.0, 90.0, 10.0, 10.0))),
+            ("a", Shape::Rect(Rect::new(40.0, 40.0, 20.0, 20.0))),
+            ("b", Shape::Rect(Rect::new(45.0, 45.0, 10.0, 10.0))),
+        ] {
+            engine.apply(Action::AddElement { id: id.to_string(), name: id.to_string(), shape, fill: Paint::Solid(Color::BLACK) });
+        }
+        engine.apply(Action::GroupElements { group_id: "g".to_string(), children: vec!["a".to_string(), "b".to_string()] });
+        engine.apply(Action::SetVisibility { id: "hidden".to_string(), visible: false });
+        engine.apply(Action::SetLocked { id: "locked".to_string(), locked: true });
+
+        let all = HitTestOptions { all: true, ..HitTestOptions::default() };
+        let centre = Point::new(50.0, 50.0);
+        assert_eq!(engine.hits(&centre, &HitTestOptions::default()), vec!["g".to_string()]);
+        assert_eq!(engine.hits(&centre, &all), vec!["bg", "circle", "g"]);
+        assert_eq!(engine.hits(&centre, &HitTestOptions { deep: true, ..all.clone() }), vec!["bg", "circle", "a", "b"]);
+        // Inside the circle's bounds but outside the circle itself.
+        assert_eq!(engine.hits(&Point::new(33.0, 33.0), &all), vec!["bg"]);
+
+        assert_eq!(engine.hits(&Point::new(5.0, 5.0), &HitTestOptions::default()), vec!["bg"]);
+        let include_hidden = serde_json::from_str(r#"{"include_hidden": true}"#).unwrap();
+        assert_eq!(engine.hits(&Point::new(5.0, 5.0), &include_hidden), vec!["hidden"]);
+        assert_eq!(engine.hits(&Point::new(95.0, 95.0), &HitTestOptions::default()), vec!["bg"]);
+        assert_eq!(engine.hits(&Point::new(95.0, 95.0), &HitTestOptions { include_locked: true, ..HitTestOptions::default() }), vec!["locked"]);
+
+        // Locking a group locks everything in it.
+        engine.apply(Action::SetLocked { id: "g".to_string(), locked: true });
+        assert_eq!(engine.hits(&centre, &HitTestOptions { deep: true, ..HitTestOptions::default() }), vec!["circle"]);
+        assert!(engine.hits(&Point::new(150.0, 150.0), &all).is_empty());
+    }
+
+    #[test]
+    fn test_hit_test_follows_keyframes() {
+        use crate::core::geometry::Point;
+
+        let mut engine = KineticEngine::new();
+        for (id, shape) in [("slider", Shape::Rect(Rect::new(0.0, 0.0, 20.0, 20.0))), ("dot", Shape::Circle(Circle::new(300.0, 300.0, 10.0)))] {
+            engine.apply(Action::AddElement { id: id.to_string(), name: id.to_string(), shape, fill: Paint::Solid(Color::BLACK) });
+        }
+        engine.apply(Action::GroupElements { group_id: "g".to_string(), children: vec!["dot".to_string()] });
+        for (id, property, from, to) in [("slider", "x", 0.0, 200.0), ("dot", "radius", 10.0, 50.0)] {
+            for (time, value) in [(0.0, from), (1000.0, to)] {
+                let keyframe = Keyframe { time, value: KeyframeValue::Number(value), easing: "linear".to_string(), color_space: Default::default(), morph: None };
+                engine.apply(Action::AddKeyframe { element_id: id.to_string(), property: property.to_string(), keyframe });
+            }
+        }
+
+        let deep = HitTestOptions { deep: true, ..HitTestOptions::default() };
+        assert_eq!(engine.hits(&Point::new(10.0, 10.0), &HitTestOptions::default()), vec!["slider"]);
+        assert!(engine.hits(&Point::new(210.0, 10.0), &HitTestOptions::default()).is_empty());
+        assert!(engine.hits(&Point::new(340.0, 300.0), &deep).is_empty());
+
+        engine.apply(Action::SetTime { time: 1000.0 });
+        assert!(engine.hits(&Point::new(10.0, 10.0), &HitTestOptions::default()).is_empty());
+        assert_eq!(engine.hits(&Point::new(210.0, 10.0), &HitTestOptions::default()), vec!["slider"]);
+        assert_eq!(engine.hits(&Point::new(340.0, 300.0), &deep), vec!["dot"]);
+        assert_eq!(engine.hits(&Point::new(340.0, 300.0), &HitTestOptions::default()), vec!["g"]);
+        assert_eq!(engine.indexed, KineticEngine::from_state(engine.state.clone()).indexed);
+    }
+
+    #[test]
+    fn test_marquee_and_lasso_selection() {
+        use crate::core::geometry::Point;
+        use crate::core::picking::MarqueeMode;
+
+        let mut engine = KineticEngine::new();
+        for (id, shape) in [
+            ("circle", Shape::Circle(Circle::new(50.0, 50.0, 20.0))),
+            ("bar", Shape::Rect(Rect::new(100.0, 0.0, 10.0, 100.0))),
+            ("locked", Shape::Rect(Rect::new(300.0, 300.0, 10.0, 10.0))),
+            ("a", Shape::Rect(Rect::new(200.0, 0.0, 10.0, 10.0))),
+            ("b", Shape::Rect(Rect::new(260.0, 0.0, 10.0, 10.0))),
+        ] {
+            engine.apply(Action::AddElement { id: id.to_string(), name: id.to_string(), shape, fill: Paint::Solid(Color::BLACK) });
+        }
+        engine.apply(Action::GroupElements { group_id: "g".to_string(), children: vec!["a".to_string(), "b".to_string()] });
+        engine.apply(Action::SetLocked { id: "locked".to_string(), locked: true });
+
+        let rect = |x: f32, y: f32, width: f32, height: f32| SelectionRegion::Rect { x, y, width, height };
+        let intersect = MarqueeOptions::default();
+        let contain = MarqueeOptions { mode: MarqueeMode::Contain, ..MarqueeOptions::default() };
+
+        // The corner of the circle's bounds, which the circle itself misses.
+        assert!(engine.marquee(&rect(25.0, 25.0, 10.0, 10.0), &intersect).is_empty());
+        assert_eq!(engine.marquee(&rect(0.0, 0.0, 105.0, 120.0), &intersect), vec!["circle", "bar"]);
+        assert_eq!(engine.marquee(&rect(0.0, 0.0, 105.0, 120.0), &contain), vec!["circle"]);
+        assert_eq!(engine.marquee(&rect(105.0, 120.0, -105.0, -120.0), &contain), vec!["circle"]);
+
+        assert_eq!(engine.marquee(&rect(195.0, -5.0, 20.0, 20.0), &intersect), vec!["g"]);
+        assert!(engine.marquee(&rect(195.0, -5.0, 20.0, 20.0), &contain).is_empty());
+        assert_eq!(engine.marquee(&rect(195.0, -5.0, 20.0, 20.0), &MarqueeOptions { deep: true, ..contain.clone() }), vec!["a"]);
+        assert_eq!(engine.marquee(&rect(195.0, -5.0, 80.0, 20.0), &contain), vec!["g"]);
+
+        let lasso = SelectionRegion::Lasso { points: vec![Point::new(0.0, 0.0), Point::new(150.0, 0.0), Point::new(0.0, 150.0)] };
+        assert_eq!(engine.marquee(&lasso, &intersect), vec!["circle", "bar"]);
+        assert_eq!(engine.marquee(&lasso, &contain), vec!["circle"]);
+
+        assert!(engine.marquee(&rect(290.0, 290.0, 30.0, 30.0), &intersect).is_empty());
+        assert_eq!(engine.marquee(&rect(290.0, 290.0, 30.0, 30.0), &MarqueeOptions { include_locked: true, ..intersect }), vec!["locked"]);
+
+        let action: Action = serde_json::from_str(r#"{"type": "MARQUEE_SELECT", "payload": {"region": {"kind": "rect", "x": 0, "y": 0, "width": 105, "height": 120}, "options": {"mode": "contain"}}}"#).unwrap();
+        engine.apply(action);
+        assert_eq!(engine.state.selection, vec!["circle"]);
+        engine.apply(Action::MarqueeSelect { region: rect(195.0, -5.0, 20.0, 20.0), options: MarqueeOptions::default(), extend: true });
+        assert_eq!(engine.state.selection, vec!["circle", "g"]);
+        let action: Action = serde_json::from_str(r#"{"type": "SET_SELECTION", "payload": {"ids": ["bar", "missing"]}}"#).unwrap();
+        engine.apply(action);
+        assert_eq!(engine.state.selection, vec!["bar"]);
+    }
+
+    #[test]
+    fn test_marquee_follows_keyframes() {
+        use crate::core::picking::MarqueeMode;
+
+        let mut engine = KineticEngine::new();
+        engine.apply(Action::AddElement { id: "slider".to_string(), name: "Slider".to_string(), shape: Shape::Rect(Rect::new(0.0, 0.0, 20.0, 20.0)), fill: Paint::Solid(Color::BLACK) });
+        for (time, value) in [(0.0, 0.0), (1000.0, 200.0)] {
+            let keyframe = Keyframe { time, value: KeyframeValue::Number(value), easing: "linear".to_string(), color_space: Default::default(), morph: None };
+            engine.apply(Action::AddKeyframe { element_id: "slider".to_string(), property: "x".to_string(), keyframe });
+        }
+        let start = SelectionRegion::Rect { x: -5.0, y: -5.0, width: 30.0, height: 30.0 };
+        let end = SelectionRegion::Rect { x: 195.0, y: -5.0, width: 30.0, height: 30.0 };
+        let contain = MarqueeOptions { mode: MarqueeMode::Contain, ..MarqueeOptions::default() };
+
+        engine.apply(Action::SetTime { time: 1000.0 });
+        assert!(engine.marquee(&start, &MarqueeOptions::default()).is_empty());
+        assert_eq!(engine.marquee(&end, &contain), vec!["slider"]);
+        engine.apply(Action::MarqueeSelect { region: start.clone(), options: MarqueeOptions::default(), extend: false });
+        assert!(engine.state.selection.is_empty());
+        engine.apply(Action::MarqueeSelect { region: end.clone(), options: contain.clone(), extend: false });
+        assert_eq!(engine.state.selection, vec!["slider"]);
+
+        engine.apply(Action::SetTime { time: 0.0 });
+        engine.apply(Action::MarqueeSelect { region: end, options: MarqueeOptions::default(), extend: false });
+        assert!(engine.state.selection.is_empty());
+        engine.apply(Action::MarqueeSelect { region: start, options: contain, extend: true });
+        assert_eq!(engine.state.selection, vec!["slider"]);
+    }
+
+    #[test]
+    fn test_precise_queries_test_shape_geometry() {
+        use crate::core::state::MaskMode;
+        use crate::core::stroke::{Stroke, StrokeAlignment, StrokeStyle};
+
+        let mut engine = KineticEngine::new();
+        for (id, shape) in [
+            ("circle", Shape::Circle(Circle::new(50.0, 50.0, 40.0))),
+            ("mask", Shape::Rect(Rect::new(200.0, 0.0, 50.0, 50.0))),
+            ("art", Shape::Rect(Rect::new(200.0, 0.0, 100.0, 100.0))),
+        ] {
+            engine.apply(Action::AddElement { id: id.to_string(), name: id.to_string(), shape, fill: Paint::Solid(Color::BLACK) });
+        }
+        engine.apply(Action::SetMask { id: "mask".to_string(), mask: Some(MaskMode::Clip) });
+
+        let corner = Rect::new(12.0, 12.0, 4.0, 4.0);
+        let mut ids = Vec::new();
+        engine.index.query(&corner, &mut ids);
+        assert_eq!(ids, vec!["circle".to_string()]);
+        assert!(!engine.state.element_intersects("circle", &corner));
+        assert!(engine.state.element_intersects("circle", &Rect::new(45.0, 45.0, 4.0, 4.0)));
+
+        // An outside stroke paints past the fill; an inside one never does.
+        let stroke = Stroke { style: StrokeStyle::new(10.0), alignment: StrokeAlignment::Outside, ..Stroke::default() };
+        engine.apply(Action::SetStroke { id: "circle".to_string(), stroke: Some(stroke.clone()) });
+        assert!(engine.state.element_intersects("circle", &Rect::new(50.0, 2.0, 1.0, 1.0)));
+        let stroke = Stroke { alignment: StrokeAlignment::Inside, ..stroke };
+        engine.apply(Action::SetStroke { id: "circle".to_string(), stroke: Some(stroke) });
+        assert!(!engine.state.element_intersects("circle", &Rect::new(50.0, 2.0, 1.0, 1.0)));
+
+        // Only the part of "art" under its mask counts.
+        assert!(engine.state.element_intersects("art", &Rect::new(210.0, 10.0, 5.0, 5.0)));
+        assert!(!engine.state.element_intersects("art", &Rect::new(260.0, 60.0, 5.0, 5.0)));
+    }
+
+    #[test]
+    fn test_text_layout_follows_edits_and_keyframes() {
+        use crate::core::font::tests::test_fonts;
+        use crate::core::text::Text;
+
+        let mut state = EngineState::new();
+        state.fonts = std::sync::Arc::new(test_fonts());
+        let mut text = Text::new(0.0, 0.0, "AV AV", "Kinetic Test", 10.0);
+        text.line_height = crate::core::geometry::Scalar::lit("1.5");
+        reducer(&mut state, Action::AddElement {
+            id: "title".to_string(),
+            name: "Title".to_string(),
+            shape: Shape::Text(text),
+            fill: Paint::Solid(Color::BLACK),
+        });
+        let bounds = |state: &EngineState| state.elements["title"].shape.get_bounding_box();
+        assert_eq!(bounds(&state), Rect::new(0.0, 0.0, 26.5, 15.0));
+
+        reducer(&mut state, Action::SetTextWidth { id: "title".to_string(), width: Some(14.0) });
+        assert_eq!(bounds(&state), Rect::new(0.0, 0.0, 12.0, 30.0));
+
+        let keyframe = Keyframe { time: 0.0, value: KeyframeValue::Number(20.0), easing: "linear".to_string(), color_space: Default::default(), morph: None };
+        reducer(&mut state, Action::AddKeyframe { element_id: "title".to_string(), property: "font_size".to_string(), keyframe });
+        // At 20px each 12px glyph fills the 14px box on its own.
+        let computed = state.get_computed_state();
+        let Shape::Text(text) = &computed.elements["title"].shape else { panic!("not text") };
+        assert_eq!(text.layout.lines.len(), 4);
+
+        let caption = Text::new(0.0, 40.0, "AV", "Kinetic Test", 10.0);
+        reducer(&mut state, Action::AddElement { id: "caption".to_string(), name: "Caption".to_string(), shape: Shape::Text(caption), fill: Paint::Solid(Color::BLACK) });
+        let keyframe = Keyframe { time: 0.0, value: KeyframeValue::Number(30.0), easing: "linear".to_string(), color_space: Default::default(), morph: None };
+        reducer(&mut state, Action::AddKeyframe { element_id: "caption".to_string(), property: "x".to_string(), keyframe });
+        // Without the fonts a fresh layout falls back to other metrics, so
+        // only the text whose font size is animated is laid out again.
+        state.fonts = std::sync::Arc::new(crate::core::font::FontLibrary::default());
+        let computed = state.get_computed_state();
+        let layout = |state: &EngineState, id: &str| match &state.elements[id].shape {
+            Shape::Text(text) => text.layout.clone(),
+            _ => panic!("not text"),
+        };
+        assert_eq!(layout(&computed, "caption"), layout(&state, "caption"));
+        assert_eq!(computed.elements["caption"].shape.get_bounding_box().origin.x, crate::core::geometry::Scalar::from_num(30));
+        let Shape::Text(title) = &computed.elements["title"].shape else { panic!("not text") };
+        assert_eq!(title.layout, title.compute_layout(&computed.fonts));
+    }
+
+    #[test]
+    fn test_load_font_and_convert_text_to_path() {
+        use crate::core::text::Text;
+
+        let mut engine = KineticEngine::new();
+        let text = Text::new(0.0, 0.0, "fi", "Kinetic Test", 10.0);
+        reducer(&mut engine.state, Action::AddElement { id: "t".to_string(), name: "Text".to_string(), shape: Shape::Text(text), fill: Paint::Solid(Color::BLACK) });
+        // Without the font, text keeps fallback metrics and cannot be outlined.
+        assert_eq!(engine.state.elements["t"].shape.get_bounding_box().width, crate::core::geometry::Scalar::from_num(10));
+        reducer(&mut engine.state, Action::ConvertToPath { id: "t".to_string() });
+        assert!(matches!(engine.state.elements["t"].shape, Shape::Text(_)));
+
+        let family = engine.load_font(include_bytes!("../tests/fixtures/kinetic-test-regular.ttf").to_vec()).unwrap();
+        assert_eq!(family, "Kinetic Test");
+        // The "fi" ligature glyph is 500 units wide.
+        assert_eq!(engine.state.elements["t"].shape.get_bounding_box().width, crate::core::geometry::Scalar::from_num(5));
+        assert_eq!(engine.load_font(include_bytes!("../tests/fixtures/SourceCodePro-It.ttf.woff2").to_vec()).unwrap(), "Source Code Pro");
+
+        reducer(&mut engine.state, Action::ConvertToPath { id: "t".to_string() });
+        let Shape::Path(path) = &engine.state.elements["t"].shape else { panic!("not a path") };
+        assert_eq!(path.get_bounds().width, crate::core::geometry::Scalar::from_num(4));
+    }
+
+    #[test]
+    fn test_rich_text_actions_survive_serialization() {
+        use crate::core::text::Text;
+
+        let mut engine = KineticEngine::new();
+        engine.load_font(include_bytes!("../tests/fixtures/kinetic-test-regular.ttf").to_vec()).unwrap();
+        let text = Text::new(0.0, 0.0, "AVAV", "Kinetic Test", 10.0);
+        reducer(&mut engine.state, Action::AddElement { id: "t".to_string(), name: "Text".to_string(), shape: Shape::Text(text), fill: Paint::Solid(Color::BLACK) });
+        for action in [
+            r##"{"type": "SET_TEXT_SPAN_STYLE", "payload": {"id": "t", "start": 2, "end": 4, "font_size": 20, "fill": "#ff0000"}}"##,
+            r#"{"type": "REPLACE_TEXT", "payload": {"id": "t", "start": 4, "end": 4, "text": "A"}}"#,
+            r#"{"type": "SET_TEXT_PATH", "payload": {"id": "t", "path": {"commands": []}, "offset": 0}}"#,
+            r#"{"type": "SET_TEXT_PATH", "payload": {"id": "t", "path": null, "offset": 0}}"#,
+        ] {
+            reducer(&mut engine.state, serde_json::from_str(action).unwrap());
+        }
+
+        let json = engine.serialize_state().unwrap();
+        engine.deserialize_state(json).unwrap();
+        let Shape::Text(text) = &engine.state.elements["t"].shape else { panic!("not text") };
+        assert_eq!(text.content, "AVAVA");
+        assert_eq!((text.spans[0].start, text.spans[0].end), (2, 5));
+        assert_eq!(text.spans[0].style.fill, Some(Paint::Solid(Color::rgba8(255, 0, 0, 255))));
+        assert!(text.path.is_none());
+        // "AV" at 10px, then "AVA" at 20px with its "VA" pair kerned by 2px.
+        assert_eq!(text.layout.width, crate::core::geometry::Scalar::from_num(46));
+    }
+
+    #[test]
+    fn test_backends_answer_alike() {
+        use crate::core::geometry::Point;
+        use crate::core::spatial::SpatialBackend;
+
+        let options = IndexOptions { backend: SpatialBackend::RTree, capacity: 3, ..IndexOptions::default() };
+        let mut engines = [KineticEngine::new(), KineticEngine::with_index(&options)];
+        for engine in &mut engines {
+            engine.apply(Action::AddElement { id: "background".to_string(), name: "Background".to_string(), shape: Shape::Rect(Rect::new(-20000.0, -20000.0, 40000.0, 40000.0)), fill: Paint::Solid(Color::BLACK) });
+            for i in 0..40 {
+                let shape = Shape::Circle(Circle::new((i % 8) as f32 * 25.0, (i / 8) as f32 * 25.0, 5.0));
+                engine.apply(Action::AddElement { id: format!("icon{:02}", i), name: "Icon".to_string(), shape, fill: Paint::Solid(Color::BLACK) });
+            }
+            engine.apply(Action::MoveElement { id: "icon09".to_string(), dx: 300.0, dy: 0.0 });
+            engine.apply(Action::RemoveElement { id: "icon10".to_string() });
+        }
+
+        let [quadtree, rtree] = &engines;
+        let p = Point::new(25.0, 25.0);
+        let all = HitTestOptions { all: true, ..HitTestOptions::default() };
+        // "icon09" was centred here before it moved away.
+        assert_eq!(quadtree.hits(&p, &all), vec!["background"]);
+        assert_eq!(rtree.hits(&p, &all), quadtree.hits(&p, &all));
+        let region = SelectionRegion::Rect { x: 20.0, y: 0.0, width: 40.0, height: 40.0 };
+        assert_eq!(rtree.marquee(&region, &MarqueeOptions::default()), quadtree.marquee(&region, &MarqueeOptions::default()));
+        assert_eq!(rtree.index.nearest(&Point::new(330.0, 25.0), 3), quadtree.index.nearest(&Point::new(330.0, 25.0), 3));
+        assert_eq!(rtree.index.nearest(&Point::new(330.0, 25.0), 2)[1].0, "icon09");
+
+        let rebuilt = KineticEngine::from_state_with_index(rtree.state.clone(), &options);
+        let mut ids = Vec::new();
+        rebuilt.index.query(&Rect::new(290.0, 0.0, 50.0, 50.0), &mut ids);
+        ids.sort();
+        assert_eq!(ids, vec!["background", "icon09"]);
+    }
+
+    #[test]
+    fn test_replacing_a_group_removes_its_contents() {
+        use crate::core::geometry::Point;
+
+        let mut engine = KineticEngine::new();
+        for (id, x) in [("a", 0.0), ("b", 50.0)] {
+            engine.apply(Action::AddElement { id: id.to_string(), name: id.to_string(), shape: Shape::Rect(Rect::new(x, 0.0, 20.0, 20.0)), fill: Paint::Solid(Color::BLACK) });
+        }
+        engine.apply(Action::GroupElements { group_id: "g".to_string(), children: vec!["a".to_string(), "b".to_string()] });
+        engine.apply(Action::AddElement { id: "g".to_string(), name: "Box".to_string(), shape: Shape::Rect(Rect::new(200.0, 0.0, 20.0, 20.0)), fill: Paint::Solid(Color::BLACK) });
+
+        assert!(!engine.state.elements.contains_key("a") && !engine.state.elements.contains_key("b"));
+        assert_eq!(engine.state.paint_order(), vec!["g"]);
+        let deep = HitTestOptions { deep: true, all: true, ..HitTestOptions::default() };
+        assert!(engine.hits(&Point::new(10.0, 10.0), &deep).is_empty());
+        assert_eq!(engine.hits(&Point::new(210.0, 10.0), &deep), vec!["g"]);
+        assert_eq!(engine.indexed, KineticEngine::from_state(engine.state.clone()).indexed);
+    }
+
+    #[test]
+    fn test_incremental_index_matches_rebuild() {
+        use crate::core::state::MaskMode;
+
+        let mut engine = KineticEngine::new();
+        for i in 0..6 {
+            let shape = Shape::Rect(Rect::new(i as f32 * 30.0, 0.0, 20.0, 20.0));
+            engine.apply(Action::AddElement { id: format!("e{}", i), name: "Box".to_string(), shape, fill: Paint::Solid(Color::BLACK) });
+        }
+        let actions = [
+            Action::GroupElements { group_id: "g".to_string(), children: vec!["e1".to_string(), "e2".to_string()] },
+            Action::SetMask { id: "e3".to_string(), mask: Some(MaskMode::Clip) },
+            // Moving the mask re-clips everything stacked above it.
+            Action::MoveElement { id: "e3".to_string(), dx: 60.0, dy: 0.0 },
+            Action::AddElement { id: "e6".to_string(), name: "Box".to_string(), shape: Shape::Rect(Rect::new(80.0, 0.0, 40.0, 20.0)), fill: Paint::Solid(Color::BLACK) },
+            Action::SetMask { id: "e1".to_string(), mask: Some(MaskMode::Clip) },
+            Action::MoveElement { id: "e1".to_string(), dx: 0.0, dy: 50.0 },
+            Action::SetMask { id: "e1".to_string(), mask: None },
+            Action::SetTime { time: 100.0 },
+            Action::Ungroup { group_id: "g".to_string() },
+            Action::RemoveElement { id: "e3".to_string() },
+        ];
+        for action in actions {
+            engine.apply(action);
+            let rebuilt = KineticEngine::from_state(engine.state.clone());
+            assert_eq!(engine.indexed, rebuilt.indexed);
+        }
+        assert_eq!(engine.indexed["e4"], Rect::new(120.0, 0.0, 20.0, 20.0));
+        assert!(Action::SetTime { time: 0.0 }.target_ids().is_empty());
+    }
 }
@@ -0,0 +1,155 @@
+#!/usr/bin/env python3
+"""Builds the tiny TrueType fonts used by the engine's text tests.
+
+Glyphs are plain boxes and triangles on a 1000 unit em so expected layout
+values are easy to work out by hand. A `kern` table and a GSUB "liga"
+lookup give shaping something to do. Run from this directory:
+
+    python3 make_test_font.py
+"""
+import struct
+
+UPM = 1000
+ASCENDER, DESCENDER = 800, -200
+
+# name, char, advance, contours (lists of on-curve points)
+GLYPHS = [
+    (".notdef", None, 500, [[(50, 0), (450, 0), (450, 700), (50, 700)]]),
+    ("space", " ", 250, []),
+    ("A", "A", 600, [[(0, 0), (600, 0), (300, 700)]]),
+    ("V", "V", 600, [[(0, 700), (300, 0), (600, 700)]]),
+    ("f", "f", 300, [[(50, 0), (250, 0), (250, 750), (50, 750)]]),
+    ("i", "i", 250, [[(50, 0), (200, 0), (200, 500), (50, 500)]]),
+    ("o", "o", 500, [[(50, 0), (450, 0), (450, 500), (50, 500)],
+                     [(150, 100), (150, 400), (350, 400), (350, 100)]]),
+    # Only reachable through the "liga" feature, never through cmap.
+    ("f_i", None, 500, [[(50, 0), (450, 0), (450, 750), (50, 750)]]),
+]
+
+# Kerning pairs by glyph name, in font units.
+KERNING = [("V", "A", -100)]
+LIGATURES = [(("f", "i"), "f_i")]
+
+
+def glyph_id(name):
+    return next(i for i, g in enumerate(GLYPHS) if g[0] == name)
+
+
+def glyf_entry(contours):
+    if not contours:
+        return b""
+    xs = [x for c in contours for x, _ in c]
+    ys = [y for c in contours for _, y in c]
+    out = struct.pack(">hhhhh", len(contours), min(xs), min(ys), max(xs), max(ys))
+    end, ends = -1, []
+    for c in contours:
+        end += len(c)
+        ends.append(end)
+    out += struct.pack(">%dH" % len(ends), *ends) + struct.pack(">H", 0)
+    points = [p for c in contours for p in c]
+    out += bytes([0x01] * len(points))
+    px = py = 0
+    dx, dy = b"", b""
+    for x, y in points:
+        dx += struct.pack(">h", x - px)
+        dy += struct.pack(">h", y - py)
+        px, py = x, y
+    out += dx + dy
+    if len(out) % 2:
+        out += b"\0"
+    return out
+
+
+def name_table(family, subfamily):
+    records, strings = [], b""
+    for name_id, text in [(1, family), (2, subfamily), (4, f"{family} {subfamily}")]:
+        data = text.encode("utf-16-be")
+        records.append(struct.pack(">6H", 3, 1, 0x409, name_id, len(data), len(strings)))
+        strings += data
+    return struct.pack(">3H", 0, len(records), 6 + 12 * len(records)) + b"".join(records) + strings
+
+
+def cmap_table(mapping):
+    chars = sorted(mapping)
+    segments = [(c, c, mapping[c]) for c in chars] + [(0xFFFF, 0xFFFF, 0)]
+    n = len(segments)
+    ends = [s[1] for s in segments]
+    starts = [s[0] for s in segments]
+    deltas = [(g - s[0]) & 0xFFFF if s[0] != 0xFFFF else 1 for s, g in zip(segments, [s[2] for s in segments])]
+    body = struct.pack(">4H", 2 * n, 0, 0, 0)
+    body += struct.pack(">%dH" % n, *ends) + b"\0\0"
+    body += struct.pack(">%dH" % n, *starts)
+    body += struct.pack(">%dH" % n, *deltas)
+    body += struct.pack(">%dH" % n, *([0] * n))
+    sub = struct.pack(">3H", 4, 6 + len(body), 0) + body
+    return struct.pack(">2H", 0, 1) + struct.pack(">2HI", 3, 1, 12) + sub
+
+
+def kern_table():
+    pairs = sorted((glyph_id(l), glyph_id(r), v) for l, r, v in KERNING)
+    body = struct.pack(">4H", len(pairs), 6, 0, 0)
+    body += b"".join(struct.pack(">HHh", *p) for p in pairs)
+    return struct.pack(">2H", 0, 1) + struct.pack(">3H", 0, 6 + len(body), 0x0001) + body
+
+
+def gsub_table():
+    """A single "liga" lookup under the DFLT script."""
+    script_list = struct.pack(">H4sH", 1, b"DFLT", 8) + struct.pack(">2H", 4, 0) + struct.pack(">4H", 0, 0xFFFF, 1, 0)
+    feature_list = struct.pack(">H4sH", 1, b"liga", 8) + struct.pack(">3H", 0, 1, 0)
+    (first, second), lig = LIGATURES[0]
+    ligature = struct.pack(">3H", glyph_id(lig), 2, glyph_id(second))
+    lig_set = struct.pack(">2H", 1, 4) + ligature
+    coverage = struct.pack(">3H", 1, 1, glyph_id(first))
+    subtable = struct.pack(">4H", 1, 8, 1, 8 + len(coverage)) + coverage + lig_set
+    lookup = struct.pack(">4H", 4, 0, 1, 8) + subtable
+    lookup_list = struct.pack(">2H", 1, 4) + lookup
+    offsets = [10, 10 + len(script_list), 10 + len(script_list) + len(feature_list)]
+    return struct.pack(">2H3H", 1, 0, *offsets) + script_list + feature_list + lookup_list
+
+
+def build(family, subfamily, weight, extra_advance, tables_extra=None):
+    glyf, loca = b"", []
+    for _, _, _, contours in GLYPHS:
+        loca.append(len(glyf))
+        glyf += glyf_entry(contours)
+    loca.append(len(glyf))
+    advances = [adv + (extra_advance if ch and ch != " " else 0) for _, ch, adv, _ in GLYPHS]
+    lsbs = [min((x for c in contours for x, _ in c), default=0) for _, _, _, contours in GLYPHS]
+    tables = {
+        b"head": struct.pack(">IIIIHHQQhhhhHHhhh", 0x00010000, 0x00010000, 0, 0x5F0F3CF5, 0, UPM,
+                             0, 0, 0, DESCENDER, max(advances), ASCENDER, 0, 8, 2, 0, 0),
+        b"hhea": struct.pack(">IhhhHhhhhhh4hhH", 0x00010000, ASCENDER, DESCENDER, 0, max(advances),
+                             0, 0, max(advances), 1, 0, 0, 0, 0, 0, 0, 0, len(GLYPHS)),
+        b"maxp": struct.pack(">IH13H", 0x00010000, len(GLYPHS), 16, 2, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0),
+        b"OS/2": struct.pack(">HhHHH", 4, 500, weight, 5, 0) + b"\0" * 20
+                 + b"\0" * 12 + b"\0" * 16 + b"KTST"
+                 + struct.pack(">HHH", 0x20 if weight >= 700 else 0x40, 0x20, 0x7A)
+                 + struct.pack(">hhhHH", ASCENDER, DESCENDER, 0, ASCENDER, -DESCENDER)
+                 + b"\0" * 8 + struct.pack(">hhHHH", 500, 700, 0, 0x20, 0),
+        b"hmtx": b"".join(struct.pack(">Hh", a, l) for a, l in zip(advances, lsbs)),
+        b"loca": b"".join(struct.pack(">H", o // 2) for o in loca),
+        b"glyf": glyf,
+        b"cmap": cmap_table({ord(ch): i for i, (_, ch, _, _) in enumerate(GLYPHS) if ch}),
+        b"name": name_table(family, subfamily),
+        b"post": struct.pack(">IIhhIIIII", 0x00030000, 0, -100, 50, 0, 0, 0, 0, 0),
+        b"kern": kern_table(),
+        b"GSUB": gsub_table(),
+    }
+    tables.update(tables_extra or {})
+    tags = sorted(tables)
+    offset = 12 + 16 * len(tags)
+    directory, data = b"", b""
+    for tag in tags:
+        body = tables[tag]
+        directory += struct.pack(">4sIII", tag, 0, offset + len(data), len(body))
+        data += body + b"\0" * (-len(body) % 4)
+    selector = len(tags).bit_length() - 1
+    header = struct.pack(">IHHHH", 0x00010000, len(tags), 16 << selector, selector, (len(tags) - (1 << selector)) * 16)
+    return header + directory + data
+
+
+if __name__ == "__main__":
+    with open("kinetic-test-regular.ttf", "wb") as f:
+        f.write(build("Kinetic Test", "Regular", 400, 0))
+    with open("kinetic-test-bold.ttf", "wb") as f:
+        f.write(build("Kinetic Test", "Bold", 700, 50))